@@ -0,0 +1,115 @@
+//! Формирует описание сборки библиотеки для символа `core_lib_describe`.
+//!
+//! Описание в формате JSON записывается в `$OUT_DIR/descriptor.json` и
+//! встраивается в библиотеку как статические данные: наборы инструкций,
+//! с которыми скомпилирован код, аллокатор, вариант, версии крейта и
+//! rustc и хеш коммита git.
+
+use serde_json::json;
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/// Варианты сборки (features крейта) в порядке проверки
+const VARIANTS: &[&str] = &[
+    "x86-64-v4",
+    "x86-64-v3",
+    "x86-64-v2",
+    "x86-64",
+    "avx2",
+    "avx",
+    "sse4_2",
+    "neon",
+    "rva23",
+    "rvv",
+    "rva22",
+    "pwr10",
+    "pwr9",
+    "pwr8",
+    "z15",
+    "z14",
+    "z13",
+];
+
+/// Аллокаторы (features крейта `<имя>-allocator`)
+const ALLOCATORS: &[&str] = &["jemalloc", "mimalloc"];
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=RUSTFLAGS");
+    println!("cargo:rerun-if-env-changed=CARGO_ENCODED_RUSTFLAGS");
+    // Хеш коммита меняется вместе с веткой, на которую указывает HEAD
+    let mut git_files = vec![
+        "../.git/HEAD".to_string(),
+        "../.git/index".to_string(),
+        "../.git/packed-refs".to_string(),
+    ];
+    if let Some(head_ref) = std::fs::read_to_string("../.git/HEAD")
+        .ok()
+        .and_then(|head| head.strip_prefix("ref: ").map(|r| r.trim().to_string()))
+    {
+        git_files.push(format!("../.git/{}", head_ref));
+    }
+    for git_file in git_files {
+        if Path::new(&git_file).exists() {
+            println!("cargo:rerun-if-changed={}", git_file);
+        }
+    }
+
+    let feature_enabled = |name: &str| {
+        let var = format!("CARGO_FEATURE_{}", name.to_uppercase().replace('-', "_"));
+        env::var_os(var).is_some()
+    };
+
+    let target_features: Vec<String> = env::var("CARGO_CFG_TARGET_FEATURE")
+        .unwrap_or_default()
+        .split(',')
+        .filter(|feature| !feature.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    let variant = VARIANTS
+        .iter()
+        .find(|variant| feature_enabled(variant))
+        .unwrap_or(&"base");
+
+    let allocator = ALLOCATORS
+        .iter()
+        .find(|allocator| feature_enabled(&format!("{}-allocator", allocator)))
+        .unwrap_or(&"system");
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = command_output(&rustc, &["--version"]);
+    let git_hash = command_output("git", &["rev-parse", "--short=12", "HEAD"]);
+
+    let descriptor = json!({
+        "variant": variant,
+        "arch": env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default(),
+        "os": env::var("CARGO_CFG_TARGET_OS").unwrap_or_default(),
+        "target_features": target_features,
+        "allocator": allocator,
+        "version": env::var("CARGO_PKG_VERSION").unwrap_or_default(),
+        "rustc": rustc_version,
+        "git_hash": git_hash,
+    });
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR не задан");
+    std::fs::write(
+        Path::new(&out_dir).join("descriptor.json"),
+        descriptor.to_string(),
+    )
+    .expect("не удалось записать описание сборки");
+}
+
+/// Первая строка вывода команды или `unknown`
+fn command_output(program: &str, args: &[&str]) -> String {
+    Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .and_then(|stdout| stdout.lines().next().map(|line| line.trim().to_string()))
+        .filter(|line| !line.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
@@ -0,0 +1,45 @@
+//! Встроенное описание сборки библиотеки.
+//!
+//! Символ `core_lib_describe` - статические данные, а не функция:
+//! приложение читает их сразу после загрузки библиотеки, не выполняя ее
+//! код. Поэтому описание доступно даже на процессоре, который не
+//! поддерживает наборы инструкций, с которыми собрана библиотека.
+
+use cpu_detection::CoreLibDescriptor;
+
+/// Описание сборки в формате JSON, сформированное build.rs
+const DESCRIPTOR_JSON: &str = include_str!(concat!(env!("OUT_DIR"), "/descriptor.json"));
+
+/// Описание сборки, которое читает загрузчик приложения
+///
+/// Раскладка и версии определены в `cpu_detection::CoreLibDescriptor`.
+#[no_mangle]
+#[allow(non_upper_case_globals)]
+pub static core_lib_describe: CoreLibDescriptor = CoreLibDescriptor::new(DESCRIPTOR_JSON);
+
+/// Описание сборки в формате JSON
+pub fn descriptor_json() -> &'static str {
+    DESCRIPTOR_JSON
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_descriptor() {
+        assert!(core_lib_describe.is_supported());
+        assert_eq!(core_lib_describe.abi_version, cpu_detection::ABI_VERSION);
+        assert_eq!(
+            unsafe { core_lib_describe.json_bytes() },
+            descriptor_json().as_bytes()
+        );
+
+        let descriptor: serde_json::Value = serde_json::from_str(descriptor_json()).unwrap();
+        assert_eq!(descriptor["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(descriptor["arch"], std::env::consts::ARCH);
+        assert_eq!(descriptor["allocator"], "system");
+        assert!(descriptor["rustc"].as_str().unwrap().starts_with("rustc"));
+        assert!(descriptor["target_features"].is_array());
+    }
+}
@@ -65,10 +65,7 @@ pub fn error<T, S: Into<String>>(message: S) -> CoreResult<T> {
 /// Создает ошибку ввода-вывода с сообщением
 #[allow(dead_code)]
 pub fn io_error<T, S: Into<String>>(message: S) -> CoreResult<T> {
-    Err(CoreError::IoError(io::Error::new(
-        io::ErrorKind::Other,
-        message.into(),
-    )))
+    Err(CoreError::IoError(io::Error::other(message.into())))
 }
 
 /// Создает ошибку обработки данных с сообщением
@@ -96,7 +93,7 @@ mod tests {
         let core_err: CoreError = io_err.into();
 
         match core_err {
-            CoreError::IoError(_) => assert!(true),
+            CoreError::IoError(_) => {},
             _ => panic!("Неправильная конвертация ошибки"),
         }
     }
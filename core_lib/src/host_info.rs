@@ -0,0 +1,366 @@
+//! Информация о процессоре, переданная основным приложением.
+//!
+//! Приложение вызывает `set_host_info` перед `run` и передает описание
+//! процессора в формате JSON. Библиотека использует его для выбора
+//! параметров обработки: размера блока по размеру кэшей, количества
+//! рабочих потоков по топологии процессора и использования больших
+//! страниц по настройкам подсистемы памяти.
+
+use cpu_detection::{CacheInfo, CacheType, CpuInfo, ThpMode};
+use log::{debug, warn};
+use std::ops::Deref;
+use std::sync::OnceLock;
+
+use crate::error::CoreResult;
+
+/// Размер блока по умолчанию, если параметры кэшей неизвестны
+pub const DEFAULT_BLOCK_SIZE: usize = 16 * 1024;
+
+/// Размер прозрачной большой страницы, если ядро его не сообщило
+const DEFAULT_THP_SIZE: u64 = 2 * 1024 * 1024;
+
+/// Аллокатор, с которым собрана библиотека
+#[cfg(feature = "jemalloc-allocator")]
+const ALLOCATOR: &str = "jemalloc";
+#[cfg(feature = "mimalloc-allocator")]
+const ALLOCATOR: &str = "mimalloc";
+#[cfg(not(any(feature = "jemalloc-allocator", feature = "mimalloc-allocator")))]
+const ALLOCATOR: &str = "system";
+
+/// Информация о процессоре, переданная приложением
+static HOST_INFO: OnceLock<HostInfo> = OnceLock::new();
+
+/// Способ размещения буфера в больших страницах
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HugePages {
+    /// Обычные страницы
+    None,
+    /// Страницы из пула hugetlb (`mmap` с `MAP_HUGETLB`)
+    Hugetlb {
+        /// Размер страницы в байтах
+        page_size: u64,
+    },
+    /// Прозрачные большие страницы выделяются ядром автоматически
+    Transparent {
+        /// Размер страницы в байтах
+        page_size: u64,
+    },
+    /// Прозрачные большие страницы после `madvise(MADV_HUGEPAGE)`
+    Madvise {
+        /// Размер страницы в байтах
+        page_size: u64,
+    },
+}
+
+/// Описание процессора, на котором запущена библиотека
+///
+/// Содержит `CpuInfo` приложения; отсутствующие в JSON поля принимают
+/// значения по умолчанию.
+#[derive(Debug, Clone, Default)]
+pub struct HostInfo(CpuInfo);
+
+impl Deref for HostInfo {
+    type Target = CpuInfo;
+
+    fn deref(&self) -> &CpuInfo {
+        &self.0
+    }
+}
+
+impl HostInfo {
+    /// Разбирает описание процессора из JSON
+    pub fn from_json(json: &str) -> CoreResult<Self> {
+        Ok(HostInfo(cpu_detection::parse_profile(json)?))
+    }
+
+    /// Возвращает кэш данных заданного уровня (для L2 и выше - общий кэш)
+    pub fn data_cache(&self, level: u8) -> Option<&CacheInfo> {
+        self.caches
+            .iter()
+            .find(|cache| cache.level == level && cache.cache_type != CacheType::Instruction)
+    }
+
+    /// Количество рабочих потоков по топологии процессора
+    ///
+    /// Один поток на физическое ядро: потоки SMT одного ядра делят его
+    /// исполнительные блоки и не ускоряют вычисления. Возвращает `None`,
+    /// если количество ядер неизвестно.
+    pub fn core_threads(&self) -> Option<usize> {
+        let physical_cores = self.topology.physical_cores;
+        (physical_cores > 0).then_some(physical_cores as usize)
+    }
+
+    /// Количество рабочих потоков для вычислительной нагрузки
+    ///
+    /// Один поток на физическое ядро, но не больше, чем позволяют привязка
+    /// к процессорам и квота cgroup: иначе потоки в контейнере с
+    /// ограничением CPU будут простаивать из-за троттлинга.
+    pub fn worker_threads(&self) -> Option<usize> {
+        let effective_cpus = self.parallelism.effective_cpus as usize;
+        match (self.core_threads(), effective_cpus) {
+            (Some(threads), 0) => Some(threads),
+            (Some(threads), effective_cpus) => Some(threads.min(effective_cpus)),
+            (None, 0) => None,
+            (None, effective_cpus) => Some(effective_cpus),
+        }
+    }
+
+    /// Размер блока обработки данных в байтах
+    ///
+    /// Блок занимает половину кэша L1d, чтобы в кэше оставалось место для
+    /// остальных данных. Результат кратен размеру строки кэша.
+    pub fn block_size(&self) -> usize {
+        let Some(l1d) = self.data_cache(1) else {
+            return DEFAULT_BLOCK_SIZE;
+        };
+
+        let line_size = (l1d.line_size as usize).max(1);
+        let block_size = (l1d.size as usize / 2) / line_size * line_size;
+
+        if block_size == 0 {
+            DEFAULT_BLOCK_SIZE
+        } else {
+            block_size
+        }
+    }
+
+    /// Способ размещения буфера размером `buffer_size` байт
+    ///
+    /// Предпочитается пул hugetlb наименьшего размера страницы, в котором
+    /// достаточно свободных страниц: они зарезервированы заранее и не
+    /// требуют уплотнения памяти. Иначе используются прозрачные большие
+    /// страницы, если буфер не меньше одной такой страницы.
+    pub fn huge_pages(&self, buffer_size: u64) -> HugePages {
+        let memory = &self.memory;
+
+        if let Some(pool) = memory
+            .huge_pages
+            .iter()
+            .find(|pool| pool.page_size <= buffer_size && pool.free * pool.page_size >= buffer_size)
+        {
+            return HugePages::Hugetlb {
+                page_size: pool.page_size,
+            };
+        }
+
+        let Some(thp) = &memory.transparent_hugepage else {
+            return HugePages::None;
+        };
+        let page_size = thp.page_size.unwrap_or(DEFAULT_THP_SIZE);
+        if buffer_size < page_size {
+            return HugePages::None;
+        }
+
+        match thp.enabled {
+            ThpMode::Always => HugePages::Transparent { page_size },
+            ThpMode::Madvise => HugePages::Madvise { page_size },
+            ThpMode::Never => HugePages::None,
+        }
+    }
+
+    /// Рекомендуемая настройка аллокатора `allocator` (`system`,
+    /// `jemalloc`, `mimalloc`) в виде `ПЕРЕМЕННАЯ=значение`
+    ///
+    /// Аллокатор читает настройки при запуске процесса, поэтому библиотека
+    /// только сообщает их. Возвращает `None`, если большие страницы
+    /// недоступны или аллокатор использует их без настройки.
+    pub fn allocator_tuning(&self, allocator: &str) -> Option<String> {
+        let memory = &self.memory;
+        let thp = memory.transparent_hugepage.map(|thp| thp.enabled);
+        let hugetlb = memory.huge_pages.iter().find(|pool| pool.free > 0);
+
+        match allocator {
+            "jemalloc" => match thp? {
+                ThpMode::Always => Some("MALLOC_CONF=metadata_thp:auto".to_string()),
+                ThpMode::Madvise => Some("MALLOC_CONF=thp:always,metadata_thp:auto".to_string()),
+                ThpMode::Never => None,
+            },
+            "mimalloc" => {
+                let gigantic = memory
+                    .huge_pages
+                    .iter()
+                    .find(|pool| pool.page_size == 1 << 30 && pool.free > 0);
+                if let Some(pool) = gigantic {
+                    Some(format!("MIMALLOC_RESERVE_HUGE_OS_PAGES={}", pool.free))
+                } else if hugetlb.is_some() || thp.is_some_and(|mode| mode != ThpMode::Never) {
+                    Some("MIMALLOC_ALLOW_LARGE_OS_PAGES=1".to_string())
+                } else {
+                    None
+                }
+            },
+            // glibc 2.35+: 1 - madvise для THP, 2 - страницы из пула hugetlb
+            "system" => {
+                if hugetlb.is_some() {
+                    Some("GLIBC_TUNABLES=glibc.malloc.hugetlb=2".to_string())
+                } else if thp == Some(ThpMode::Madvise) {
+                    Some("GLIBC_TUNABLES=glibc.malloc.hugetlb=1".to_string())
+                } else {
+                    None
+                }
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Возвращает информацию о процессоре, если приложение ее передало
+pub fn host_info() -> Option<&'static HostInfo> {
+    HOST_INFO.get()
+}
+
+/// Размер блока обработки данных для текущего процессора
+pub fn block_size() -> usize {
+    host_info().map_or(DEFAULT_BLOCK_SIZE, HostInfo::block_size)
+}
+
+/// Количество рабочих потоков по топологии процессора и доступному
+/// ресурсу, если приложение передало информацию о процессоре
+pub fn worker_threads() -> Option<usize> {
+    host_info().and_then(HostInfo::worker_threads)
+}
+
+/// Способ размещения буфера размером `buffer_size` байт в больших
+/// страницах; без информации о системе - обычные страницы
+pub fn huge_pages(buffer_size: u64) -> HugePages {
+    host_info().map_or(HugePages::None, |info| info.huge_pages(buffer_size))
+}
+
+/// Рекомендуемая настройка аллокатора, с которым собрана библиотека
+pub fn allocator_tuning() -> Option<String> {
+    host_info().and_then(|info| info.allocator_tuning(ALLOCATOR))
+}
+
+/// Принимает описание процессора от основного приложения.
+///
+/// # Аргументы
+///
+/// * `json` - Описание процессора (`CpuInfo` приложения) в формате JSON
+#[no_mangle]
+pub fn set_host_info(json: &str) {
+    match HostInfo::from_json(json) {
+        Ok(info) => {
+            debug!("Получена информация о процессоре: {:?}", info);
+            if HOST_INFO.set(info).is_err() {
+                warn!("Информация о процессоре уже была передана ранее");
+            }
+        },
+        Err(err) => warn!("Не удалось разобрать информацию о процессоре: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_info_block_size() {
+        let json = r#"{
+            "vendor": "GenuineIntel",
+            "model": "Test CPU",
+            "microarchitecture": {"id": "alderlake", "name": "Alder Lake"},
+            "frequency": {"base_mhz": 2100, "max_mhz": 4900, "bus_mhz": 100},
+            "features": ["sse2", "avx2"],
+            "suppressed_features": [],
+            "caches": [
+                {"level": 1, "cache_type": "data", "size": 49152, "line_size": 64,
+                 "associativity": 12, "sets": 64, "shared_by": 2},
+                {"level": 1, "cache_type": "instruction", "size": 32768, "line_size": 64,
+                 "associativity": 8, "sets": 64, "shared_by": 2},
+                {"level": 2, "cache_type": "unified", "size": 2097152, "line_size": 64,
+                 "associativity": 16, "sets": 2048, "shared_by": 2}
+            ],
+            "topology": {
+                "logical_cpus": 16, "physical_cores": 12, "packages": 1,
+                "threads_per_core": 2, "numa_nodes": [],
+                "performance_cpus": [0, 1, 2, 3, 4, 5, 6, 7],
+                "efficiency_cpus": [8, 9, 10, 11, 12, 13, 14, 15]
+            },
+            "parallelism": {"affinity_cpus": 16, "cgroup_cpu_quota": null, "effective_cpus": 16}
+        }"#;
+
+        let info = HostInfo::from_json(json).unwrap();
+        assert_eq!(info.vendor, "GenuineIntel");
+        assert_eq!(info.microarchitecture.as_ref().unwrap().id, "alderlake");
+        assert_eq!(info.frequency.max_mhz, Some(4900));
+        assert_eq!(info.caches.len(), 3);
+        assert_eq!(info.data_cache(2).unwrap().size, 2 * 1024 * 1024);
+        assert_eq!(info.block_size(), 24 * 1024);
+        assert_eq!(info.core_threads(), Some(12));
+        assert_eq!(info.worker_threads(), Some(12));
+
+        // Без сведений о кэшах используется размер по умолчанию
+        let info = HostInfo::from_json(r#"{"vendor": "Unknown"}"#).unwrap();
+        assert_eq!(info.block_size(), DEFAULT_BLOCK_SIZE);
+        assert_eq!(info.core_threads(), None);
+        assert_eq!(info.worker_threads(), None);
+
+        assert!(HostInfo::from_json("not json").is_err());
+    }
+
+    #[test]
+    fn test_host_info_worker_threads() {
+        // Квота cgroup 2.5 процессора на 64-ядерном сервере
+        let json = r#"{
+            "topology": {"logical_cpus": 128, "physical_cores": 64},
+            "parallelism": {"affinity_cpus": 128, "cgroup_cpu_quota": 2.5, "effective_cpus": 3}
+        }"#;
+        let info = HostInfo::from_json(json).unwrap();
+        assert_eq!(info.worker_threads(), Some(3));
+
+        // Топология неизвестна, известен только доступный ресурс
+        let json = r#"{"parallelism": {"effective_cpus": 4}}"#;
+        let info = HostInfo::from_json(json).unwrap();
+        assert_eq!(info.worker_threads(), Some(4));
+    }
+
+    #[test]
+    fn test_host_info_huge_pages() {
+        let json = r#"{
+            "memory": {
+                "total_bytes": 68719476736,
+                "available_bytes": 34359738368,
+                "huge_pages": [
+                    {"page_size": 2097152, "total": 64, "free": 16},
+                    {"page_size": 1073741824, "total": 1, "free": 0}
+                ],
+                "transparent_hugepage": {
+                    "enabled": "madvise", "defrag": "defer+madvise", "page_size": 2097152
+                }
+            }
+        }"#;
+        let info = HostInfo::from_json(json).unwrap();
+        let page_size = 2 * 1024 * 1024;
+
+        // Буфер меньше страницы размещается в обычных страницах
+        assert_eq!(info.huge_pages(64 * 1024), HugePages::None);
+        // 16 свободных страниц hugetlb по 2 МБ
+        assert_eq!(
+            info.huge_pages(32 * 1024 * 1024),
+            HugePages::Hugetlb { page_size }
+        );
+        // Пула не хватает, используется THP по madvise
+        assert_eq!(
+            info.huge_pages(64 * 1024 * 1024),
+            HugePages::Madvise { page_size }
+        );
+
+        assert_eq!(
+            info.allocator_tuning("jemalloc").as_deref(),
+            Some("MALLOC_CONF=thp:always,metadata_thp:auto")
+        );
+        assert_eq!(
+            info.allocator_tuning("mimalloc").as_deref(),
+            Some("MIMALLOC_ALLOW_LARGE_OS_PAGES=1")
+        );
+        assert_eq!(
+            info.allocator_tuning("system").as_deref(),
+            Some("GLIBC_TUNABLES=glibc.malloc.hugetlb=2")
+        );
+
+        // Без THP и пулов hugetlb большие страницы не используются
+        let info = HostInfo::from_json(r#"{"memory": {"total_bytes": 1073741824}}"#).unwrap();
+        assert_eq!(info.huge_pages(1 << 30), HugePages::None);
+        assert_eq!(info.allocator_tuning("jemalloc"), None);
+        assert_eq!(info.allocator_tuning("system"), None);
+    }
+}
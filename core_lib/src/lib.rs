@@ -3,9 +3,14 @@
 //! Эта библиотека компилируется отдельно для каждой архитектуры процессора
 //! с соответствующими оптимизациями.
 
+mod descriptor;
 mod error;
+mod host_info;
 mod runtime;
 
+pub use descriptor::core_lib_describe;
+pub use host_info::set_host_info;
+
 use log::{debug, info, warn};
 use std::sync::Once;
 // use error::CoreError;
@@ -65,6 +70,18 @@ fn print_optimization_info() {
     #[allow(unused_mut)]
     let mut optimizations: Vec<&str> = Vec::new();
 
+    #[cfg(feature = "x86-64-v4")]
+    optimizations.push("x86-64-v4");
+
+    #[cfg(feature = "x86-64-v3")]
+    optimizations.push("x86-64-v3");
+
+    #[cfg(feature = "x86-64-v2")]
+    optimizations.push("x86-64-v2");
+
+    #[cfg(feature = "x86-64")]
+    optimizations.push("x86-64");
+
     #[cfg(feature = "avx2")]
     optimizations.push("AVX2");
 
@@ -82,6 +99,8 @@ fn print_optimization_info() {
     } else {
         info!("Используемые оптимизации: {}", optimizations.join(", "));
     }
+
+    debug!("Описание сборки: {}", descriptor::descriptor_json());
 }
 
 /// Выводит информацию об используемом аллокаторе памяти
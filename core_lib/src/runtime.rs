@@ -10,6 +10,7 @@ use std::time::Instant;
 use tokio::runtime::Runtime;
 
 use crate::error::{CoreError, CoreResult};
+use crate::host_info;
 
 /// Структура для разбора аргументов командной строки
 #[derive(Parser, Debug)]
@@ -132,6 +133,11 @@ fn create_runtime(opts: &GlobalOpts) -> CoreResult<Runtime> {
     if opts.threads > 0 {
         builder.worker_threads(opts.threads);
         debug!("Установлено количество потоков: {}", opts.threads);
+    } else if let Some(threads) = host_info::worker_threads() {
+        // По умолчанию один поток на физическое ядро с учетом привязки к
+        // процессорам и квоты cgroup
+        builder.worker_threads(threads);
+        debug!("Количество потоков по топологии процессора: {}", threads);
     }
 
     // Настройка размера стека
@@ -164,8 +170,19 @@ async fn run_benchmark(iterations: usize, size: usize) -> CoreResult<i32> {
         iterations, size
     );
 
+    // Размер блока подбирается по размеру кэша L1d
+    let block_size = host_info::block_size();
+    debug!("Размер блока обработки: {} Б", block_size);
+
     let start = Instant::now();
 
+    // Буферы от размера большой страницы выигрывают от меньшего числа
+    // промахов TLB
+    debug!(
+        "Размещение данных бенчмарка: {:?}",
+        host_info::huge_pages(size as u64 * 1024)
+    );
+
     // Создание тестовых данных
     let data = vec![0u8; size * 1024];
     let data = Arc::new(data);
@@ -176,10 +193,12 @@ async fn run_benchmark(iterations: usize, size: usize) -> CoreResult<i32> {
     for i in 0..iterations {
         let data_clone = Arc::clone(&data);
         let handle = tokio::spawn(async move {
-            // Имитация обработки данных
+            // Имитация обработки данных блоками, помещающимися в кэш
             let mut sum = 0u64;
-            for byte in data_clone.iter() {
-                sum = sum.wrapping_add(*byte as u64);
+            for block in data_clone.chunks(block_size) {
+                for byte in block {
+                    sum = sum.wrapping_add(*byte as u64);
+                }
             }
             (i, sum)
         });
@@ -212,10 +231,10 @@ async fn process_data(input: &str, output: &str, mode: &str) -> CoreResult<i32>
 
     // Чтение входного файла
     let data = tokio::fs::read(input).await.map_err(|e| {
-        CoreError::IoError(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("Не удалось прочитать входной файл: {}", e),
-        ))
+        CoreError::IoError(std::io::Error::other(format!(
+            "Не удалось прочитать входной файл: {}",
+            e
+        )))
     })?;
 
     // Обработка данных в зависимости от режима
@@ -244,10 +263,10 @@ async fn process_data(input: &str, output: &str, mode: &str) -> CoreResult<i32>
     tokio::fs::write(output, processed_data)
         .await
         .map_err(|e| {
-            CoreError::IoError(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("Не удалось записать выходной файл: {}", e),
-            ))
+            CoreError::IoError(std::io::Error::other(format!(
+                "Не удалось записать выходной файл: {}",
+                e
+            )))
         })?;
 
     info!("Обработка данных завершена успешно");
@@ -262,6 +281,18 @@ async fn print_system_info() -> CoreResult<i32> {
     println!("Версия библиотеки: {}", env!("CARGO_PKG_VERSION"));
 
     // Информация об используемых оптимизациях
+    #[cfg(feature = "x86-64-v4")]
+    println!("Оптимизация: x86-64-v4");
+
+    #[cfg(feature = "x86-64-v3")]
+    println!("Оптимизация: x86-64-v3");
+
+    #[cfg(feature = "x86-64-v2")]
+    println!("Оптимизация: x86-64-v2");
+
+    #[cfg(feature = "x86-64")]
+    println!("Оптимизация: x86-64");
+
     #[cfg(feature = "avx2")]
     println!("Оптимизация: AVX2");
 
@@ -284,6 +315,51 @@ async fn print_system_info() -> CoreResult<i32> {
     #[cfg(feature = "system-allocator")]
     println!("Аллокатор: system (стандартный)");
 
+    // Информация о кэшах процессора, переданная приложением
+    if let Some(info) = host_info::host_info() {
+        println!("Процессор: {} ({})", info.model, info.vendor);
+        if let Some(microarchitecture) = &info.microarchitecture {
+            println!(
+                "Микроархитектура: {} ({})",
+                microarchitecture.name, microarchitecture.id
+            );
+        }
+        if let Some(max_mhz) = info.frequency.max_mhz {
+            println!("Максимальная частота: {} МГц", max_mhz);
+        }
+        if let Some(base_mhz) = info.frequency.base_mhz {
+            println!("Базовая частота: {} МГц", base_mhz);
+        }
+        println!(
+            "Ядер: {}, логических процессоров: {}, сокетов: {}",
+            info.topology.physical_cores, info.topology.logical_cpus, info.topology.packages
+        );
+        println!("Доступно процессоров: {}", info.parallelism.effective_cpus);
+        if let Some(quota) = info.parallelism.cgroup_cpu_quota {
+            println!("Квота cgroup: {} процессоров", quota);
+        }
+        if !info.topology.efficiency_cpus.is_empty() {
+            println!(
+                "P-ядра: {:?}, E-ядра: {:?}",
+                info.topology.performance_cpus, info.topology.efficiency_cpus
+            );
+        }
+        for cache in &info.caches {
+            println!(
+                "Кэш L{} ({:?}): {} КБ, строка {} Б",
+                cache.level,
+                cache.cache_type,
+                cache.size / 1024,
+                cache.line_size
+            );
+        }
+        println!("Память: {}", info.memory);
+        if let Some(tuning) = host_info::allocator_tuning() {
+            println!("Рекомендуемая настройка аллокатора: {}", tuning);
+        }
+    }
+    println!("Размер блока обработки: {} Б", host_info::block_size());
+
     // Информация о Tokio
     println!(
         "Tokio runtime: {}",
@@ -0,0 +1,983 @@
+//! Модуль для определения архитектуры процессора и поддерживаемых инструкций.
+//!
+//! Использует библиотеку raw-cpuid для получения информации о процессоре x86_64
+//! и данные ядра Linux для aarch64, riscv64, powerpc64 и s390x.
+
+mod aarch64;
+mod cache;
+mod cpuid_dump;
+mod frequency;
+mod memory;
+mod microarch;
+mod parallelism;
+mod powerpc64;
+mod riscv64;
+mod s390x;
+mod sysfs;
+mod topology;
+mod virtualization;
+
+pub use aarch64::decode_aarch64_hwcaps;
+pub use cache::{CacheInfo, CacheType};
+pub use cpuid_dump::{CpuIdDump, CpuIdLeaf};
+pub use frequency::CpuFrequency;
+pub use memory::{HugePagePool, MemoryInfo, ThpDefrag, ThpMode, TransparentHugePages};
+pub use microarch::{CpuSignature, Microarchitecture};
+pub use parallelism::Parallelism;
+pub use powerpc64::{decode_powerpc64_hwcaps, PowerLevel};
+pub use riscv64::{decode_riscv_hwcap, decode_riscv_hwprobe, RiscvProfile};
+pub use s390x::{decode_s390x_hwcap, S390xLevel};
+pub use topology::{CoreType, CpuTopology, NumaNode};
+pub use virtualization::{ContainerRuntime, Hypervisor, Virtualization};
+
+use crate::error::CpuDetectionError;
+use crate::features::{CpuFeature, FeatureSet, X86_64Level};
+use log::warn;
+use raw_cpuid::{CpuId, CpuIdReader};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::Path;
+
+/// Структура с информацией о процессоре
+///
+/// Сериализуется в профиль процессора (`--export-cpu-profile`), который
+/// можно загрузить на другой машине через `CPU_PROFILE`. Отсутствующие в
+/// профиле поля принимают значения по умолчанию.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CpuInfo {
+    /// Архитектура процессора (`x86_64`, `aarch64`, `riscv64`, ...)
+    pub arch: String,
+    /// Производитель процессора
+    pub vendor: String,
+    /// Модель процессора
+    pub model: String,
+    /// Сигнатура процессора x86 (семейство, модель, степпинг)
+    pub signature: Option<CpuSignature>,
+    /// Микроархитектура (`zen3`, `sapphirerapids`, `neoverse-v1`, ...)
+    pub microarchitecture: Option<Microarchitecture>,
+    /// Частоты процессора
+    pub frequency: CpuFrequency,
+    /// Поддерживаемые наборы инструкций
+    pub features: FeatureSet,
+    /// Наборы инструкций, которые процессор поддерживает, но которые были
+    /// исключены из списка `features`, с указанием причины
+    pub suppressed_features: Vec<SuppressedFeature>,
+    /// Битовые маски `AT_HWCAP`/`AT_HWCAP2` (aarch64, riscv64, powerpc64 и s390x)
+    pub hwcaps: Option<Hwcaps>,
+    /// Иерархия кэшей (L1d, L1i, L2, L3), пустая если не удалось определить
+    pub caches: Vec<CacheInfo>,
+    /// Топология: ядра, потоки SMT, сокеты, узлы NUMA и типы ядер
+    pub topology: CpuTopology,
+    /// Доступный процессу вычислительный ресурс с учетом привязки к
+    /// процессорам и квот cgroup
+    pub parallelism: Parallelism,
+    /// Гипервизор и среда контейнеризации
+    pub virtualization: Virtualization,
+    /// Объем памяти, пулы hugetlb и режимы прозрачных больших страниц
+    pub memory: MemoryInfo,
+}
+
+/// Битовые маски `AT_HWCAP`/`AT_HWCAP2` вспомогательного вектора процесса
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hwcaps {
+    /// Значение `AT_HWCAP`
+    pub hwcap: u64,
+    /// Значение `AT_HWCAP2` (0 на архитектурах, где он не используется)
+    pub hwcap2: u64,
+}
+
+/// Набор инструкций, исключенный из списка поддерживаемых
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuppressedFeature {
+    /// Набор инструкций
+    pub feature: CpuFeature,
+    /// Причина исключения
+    pub reason: String,
+}
+
+impl CpuInfo {
+    /// Возвращает наивысший уровень микроархитектуры x86-64,
+    /// все инструкции которого поддерживаются процессором
+    pub fn x86_64_level(&self) -> Option<X86_64Level> {
+        X86_64Level::classify(&self.features)
+    }
+
+    /// Возвращает наивысший профиль RISC-V, все расширения которого
+    /// поддерживаются процессором
+    pub fn riscv_profile(&self) -> Option<RiscvProfile> {
+        RiscvProfile::classify(&self.features)
+    }
+
+    /// Возвращает наивысший уровень процессора POWER, все инструкции
+    /// которого поддерживаются процессором
+    pub fn power_level(&self) -> Option<PowerLevel> {
+        PowerLevel::classify(&self.features)
+    }
+
+    /// Возвращает наивысшее поколение IBM Z, все инструкции которого
+    /// поддерживаются процессором
+    pub fn s390x_level(&self) -> Option<S390xLevel> {
+        S390xLevel::classify(&self.features)
+    }
+
+    /// Архитектура процессора; для профилей без поля `arch` - архитектура
+    /// текущей системы
+    pub fn effective_arch(&self) -> &str {
+        if self.arch.is_empty() {
+            env::consts::ARCH
+        } else {
+            &self.arch
+        }
+    }
+}
+
+/// Определяет архитектуру процессора и поддерживаемые инструкции
+pub fn detect_cpu() -> Result<CpuInfo, CpuDetectionError> {
+    // Профиль процессора описывает систему полностью
+    let mut cpu_info = match env::var("CPU_PROFILE") {
+        Ok(profile_path) => load_profile(Path::new(&profile_path))?,
+        Err(_) => detect_system()?,
+    };
+
+    // Отключение отдельных наборов инструкций поверх определения
+    if let Ok(disabled) = env::var("CPU_FEATURES_DISABLE") {
+        disable_features(&mut cpu_info, &disabled.parse()?, "CPU_FEATURES_DISABLE");
+    }
+
+    Ok(cpu_info)
+}
+
+/// Определяет параметры процессора и системы, на которой выполняется процесс
+fn detect_system() -> Result<CpuInfo, CpuDetectionError> {
+    // Воспроизведение дампа CPUID вместо инструкции CPUID
+    if let Ok(dump_path) = env::var("CPUID_REPLAY") {
+        let mut cpu_info = cpu_info_from_cpuid_dump(&CpuIdDump::load(Path::new(&dump_path))?)?;
+        cpu_info.topology = topology::detect_topology();
+        cpu_info.parallelism = parallelism::detect_parallelism(cpu_info.topology.logical_cpus);
+        cpu_info.virtualization.container = virtualization::detect_virtualization().container;
+        cpu_info.memory = memory::detect_memory();
+        return Ok(cpu_info);
+    }
+
+    // Проверка переопределения через переменные окружения
+    let mut cpu_info = match cpu_info_from_env()? {
+        Some(cpu_info) => cpu_info,
+        // Определение архитектуры процессора
+        None => match env::consts::ARCH {
+            #[cfg(target_arch = "x86_64")]
+            "x86_64" => detect_x86_64()?,
+            "aarch64" => aarch64::detect_aarch64()?,
+            "riscv64" => riscv64::detect_riscv64()?,
+            "powerpc64" => powerpc64::detect_powerpc64()?,
+            "s390x" => s390x::detect_s390x()?,
+            arch => {
+                return Err(CpuDetectionError(format!(
+                    "Неподдерживаемая архитектура: {}",
+                    arch
+                )))
+            },
+        },
+    };
+
+    // Архитектура, параметры кэшей, топология, доступный ресурс,
+    // виртуализация, частоты и память описывают реальную систему и не
+    // переопределяются
+    cpu_info.arch = env::consts::ARCH.to_string();
+    cpu_info.caches = detect_caches();
+    cpu_info.topology = topology::detect_topology();
+    cpu_info.parallelism = parallelism::detect_parallelism(cpu_info.topology.logical_cpus);
+    cpu_info.virtualization = virtualization::detect_virtualization();
+    cpu_info.frequency = frequency::detect_frequency();
+    cpu_info.memory = memory::detect_memory();
+
+    Ok(cpu_info)
+}
+
+/// Отключает наборы инструкций `disabled` вместе с зависящими от них на
+/// архитектуре процессора
+///
+/// Отключенные наборы переносятся в `suppressed_features`; `source`
+/// указывает, откуда получен список (переменная окружения или аргумент).
+pub fn disable_features(cpu_info: &mut CpuInfo, disabled: &FeatureSet, source: &str) {
+    let masked = cpu_info
+        .features
+        .intersection(&disabled.with_dependents(cpu_info.effective_arch()));
+
+    for feature in masked.iter() {
+        let reason = if disabled.contains(feature) {
+            format!("отключен через {}", source)
+        } else {
+            format!(
+                "зависит от набора инструкций, отключенного через {}",
+                source
+            )
+        };
+        warn!("Набор инструкций {} отключен: {}", feature, reason);
+
+        cpu_info.features.remove(feature);
+        cpu_info
+            .suppressed_features
+            .push(SuppressedFeature { feature, reason });
+    }
+}
+
+/// Загружает профиль процессора из JSON-файла
+pub fn load_profile(path: &Path) -> Result<CpuInfo, CpuDetectionError> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        CpuDetectionError(format!(
+            "Не удалось прочитать профиль процессора {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    parse_profile(&content).map_err(|e| {
+        CpuDetectionError(format!(
+            "Некорректный профиль процессора {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// Разбирает профиль процессора из JSON
+pub fn parse_profile(json: &str) -> Result<CpuInfo, serde_json::Error> {
+    serde_json::from_str(json)
+}
+
+/// Сохраняет профиль процессора в формате JSON
+pub fn export_profile(
+    cpu_info: &CpuInfo,
+    writer: impl std::io::Write,
+) -> Result<(), CpuDetectionError> {
+    serde_json::to_writer_pretty(writer, cpu_info)
+        .map_err(|e| CpuDetectionError(format!("Не удалось сохранить профиль процессора: {}", e)))
+}
+
+/// Формирует информацию о процессоре из переменных окружения
+/// `CPU_VENDOR`, `CPU_MODEL` и `CPU_FEATURES`
+fn cpu_info_from_env() -> Result<Option<CpuInfo>, CpuDetectionError> {
+    cpu_info_from_overrides(
+        env::var("CPU_VENDOR").ok().as_deref(),
+        env::var("CPU_MODEL").ok().as_deref(),
+        env::var("CPU_FEATURES").ok().as_deref(),
+    )
+}
+
+/// Формирует информацию о процессоре из значений `CPU_VENDOR`,
+/// `CPU_MODEL` и `CPU_FEATURES`
+///
+/// Без производителя или наборов инструкций переопределение не
+/// применяется; модель по умолчанию - `Unknown`.
+fn cpu_info_from_overrides(
+    vendor: Option<&str>,
+    model: Option<&str>,
+    features: Option<&str>,
+) -> Result<Option<CpuInfo>, CpuDetectionError> {
+    let (Some(vendor), Some(features)) = (vendor, features) else {
+        return Ok(None);
+    };
+
+    Ok(Some(CpuInfo {
+        vendor: vendor.to_string(),
+        model: model.unwrap_or("Unknown").to_string(),
+        features: features.parse()?,
+        ..CpuInfo::default()
+    }))
+}
+
+/// Определяет иерархию кэшей: из sysfs, а при его отсутствии через CPUID
+fn detect_caches() -> Vec<CacheInfo> {
+    if let Some(caches) = cache::read_sysfs_caches(Path::new(sysfs::SYSFS_ROOT)) {
+        return caches;
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    if let Some(caches) = cache::caches_from_cpuid(&CpuId::new()) {
+        return caches;
+    }
+
+    warn!("Не удалось определить параметры кэшей процессора");
+    Vec::new()
+}
+
+/// Определяет информацию о процессоре x86_64
+#[cfg(target_arch = "x86_64")]
+fn detect_x86_64() -> Result<CpuInfo, CpuDetectionError> {
+    let cpuid = CpuId::new();
+
+    // Проверка, что ОС сохраняет состояние расширенных регистров
+    let osxsave = cpuid
+        .get_feature_info()
+        .is_some_and(|info| info.has_oxsave());
+
+    cpu_info_from_cpuid(&cpuid, read_xcr0(osxsave))
+}
+
+/// Определяет информацию о процессоре по дампу CPUID
+///
+/// Кэши, частоты и гипервизор также берутся из дампа, поэтому результат
+/// не зависит от системы, на которой выполняется процесс.
+pub fn cpu_info_from_cpuid_dump(dump: &CpuIdDump) -> Result<CpuInfo, CpuDetectionError> {
+    let cpuid = CpuId::with_cpuid_reader(dump.clone());
+
+    let mut cpu_info = cpu_info_from_cpuid(&cpuid, dump.xcr0)?;
+    cpu_info.caches = cache::caches_from_cpuid(&cpuid).unwrap_or_default();
+    cpu_info.frequency = frequency::frequency_from_cpuid(&cpuid);
+    cpu_info.virtualization.hypervisor = virtualization::hypervisor_from_cpuid(&cpuid);
+
+    Ok(cpu_info)
+}
+
+/// Определяет производителя, модель и наборы инструкций x86_64 по CPUID
+///
+/// `xcr0` - значение регистра XCR0 или `None`, если ОС не включила XSAVE.
+fn cpu_info_from_cpuid<R: CpuIdReader>(
+    cpuid: &CpuId<R>,
+    xcr0: Option<u64>,
+) -> Result<CpuInfo, CpuDetectionError> {
+    // Получение информации о производителе
+    let vendor_info = cpuid.get_vendor_info().ok_or_else(|| {
+        CpuDetectionError("Не удалось получить информацию о производителе".to_string())
+    })?;
+
+    // Получение информации о модели процессора
+    let processor_info = cpuid.get_processor_brand_string().ok_or_else(|| {
+        CpuDetectionError("Не удалось получить информацию о модели процессора".to_string())
+    })?;
+
+    // Формирование списка поддерживаемых инструкций
+    let features = collect_x86_features(cpuid)?;
+
+    // Сигнатура процессора и микроархитектура
+    let signature = cpuid.get_feature_info().map(|info| CpuSignature {
+        family: info.family_id() as u32,
+        model: info.model_id() as u32,
+        stepping: info.stepping_id() as u32,
+    });
+    let microarchitecture =
+        signature.and_then(|signature| microarch::identify_x86(vendor_info.as_str(), signature));
+
+    // Исключение наборов инструкций, состояние которых ОС не сохраняет
+    let (features, suppressed_features) = filter_by_os_support(features, xcr0);
+
+    for suppressed in &suppressed_features {
+        warn!(
+            "Набор инструкций {} отключен: {}",
+            suppressed.feature, suppressed.reason
+        );
+    }
+
+    Ok(CpuInfo {
+        arch: "x86_64".to_string(),
+        vendor: vendor_info.as_str().to_string(),
+        model: processor_info.as_str().to_string(),
+        signature,
+        microarchitecture,
+        features,
+        suppressed_features,
+        ..CpuInfo::default()
+    })
+}
+
+/// Собирает полный список расширений набора инструкций x86_64.
+///
+/// Опрашивает листы CPUID 1, 7.0, 7.1, 0xD и 0x80000001.
+fn collect_x86_features<R: CpuIdReader>(cpuid: &CpuId<R>) -> Result<FeatureSet, CpuDetectionError> {
+    // Лист 1: базовые расширения
+    let feature_info = cpuid.get_feature_info().ok_or_else(|| {
+        CpuDetectionError("Не удалось получить информацию о поддерживаемых инструкциях".to_string())
+    })?;
+
+    // Лист 7 (подлисты 0 и 1): расширенные возможности
+    let extended_features = cpuid.get_extended_feature_info().ok_or_else(|| {
+        CpuDetectionError(
+            "Не удалось получить расширенную информацию о поддерживаемых инструкциях".to_string(),
+        )
+    })?;
+
+    let mut detected = vec![
+        // Лист 1, EDX
+        (CpuFeature::Cmov, feature_info.has_cmov()),
+        (CpuFeature::Cmpxchg8b, feature_info.has_cmpxchg8b()),
+        (CpuFeature::Fxsr, feature_info.has_fxsave_fxstor()),
+        (CpuFeature::Mmx, feature_info.has_mmx()),
+        (CpuFeature::Sse, feature_info.has_sse()),
+        (CpuFeature::Sse2, feature_info.has_sse2()),
+        // Лист 1, ECX
+        (CpuFeature::Sse3, feature_info.has_sse3()),
+        (CpuFeature::Pclmulqdq, feature_info.has_pclmulqdq()),
+        (CpuFeature::Ssse3, feature_info.has_ssse3()),
+        (CpuFeature::Fma, feature_info.has_fma()),
+        (CpuFeature::Cmpxchg16b, feature_info.has_cmpxchg16b()),
+        (CpuFeature::Sse41, feature_info.has_sse41()),
+        (CpuFeature::Sse42, feature_info.has_sse42()),
+        (CpuFeature::Movbe, feature_info.has_movbe()),
+        (CpuFeature::Popcnt, feature_info.has_popcnt()),
+        (CpuFeature::Aes, feature_info.has_aesni()),
+        (CpuFeature::Xsave, feature_info.has_xsave()),
+        (CpuFeature::Avx, feature_info.has_avx()),
+        (CpuFeature::F16c, feature_info.has_f16c()),
+        (CpuFeature::Rdrand, feature_info.has_rdrand()),
+        // Лист 7.0, EBX
+        (CpuFeature::Fsgsbase, extended_features.has_fsgsbase()),
+        (CpuFeature::Bmi1, extended_features.has_bmi1()),
+        (CpuFeature::Hle, extended_features.has_hle()),
+        (CpuFeature::Avx2, extended_features.has_avx2()),
+        (CpuFeature::Bmi2, extended_features.has_bmi2()),
+        (CpuFeature::Ermsb, extended_features.has_rep_movsb_stosb()),
+        (CpuFeature::Rtm, extended_features.has_rtm()),
+        (CpuFeature::Avx512f, extended_features.has_avx512f()),
+        (CpuFeature::Avx512dq, extended_features.has_avx512dq()),
+        (CpuFeature::Rdseed, extended_features.has_rdseed()),
+        (CpuFeature::Adx, extended_features.has_adx()),
+        (CpuFeature::Avx512ifma, extended_features.has_avx512_ifma()),
+        (CpuFeature::Clflushopt, extended_features.has_clflushopt()),
+        (CpuFeature::Clwb, extended_features.has_clwb()),
+        (CpuFeature::Avx512pf, extended_features.has_avx512pf()),
+        (CpuFeature::Avx512er, extended_features.has_avx512er()),
+        (CpuFeature::Avx512cd, extended_features.has_avx512cd()),
+        (CpuFeature::Sha, extended_features.has_sha()),
+        (CpuFeature::Avx512bw, extended_features.has_avx512bw()),
+        (CpuFeature::Avx512vl, extended_features.has_avx512vl()),
+        // Лист 7.0, ECX
+        (CpuFeature::Avx512vbmi, extended_features.has_avx512vbmi()),
+        (CpuFeature::Pku, extended_features.has_pku()),
+        (CpuFeature::Waitpkg, extended_features.has_waitpkg()),
+        (CpuFeature::Avx512vbmi2, extended_features.has_avx512vbmi2()),
+        (CpuFeature::Gfni, extended_features.has_gfni()),
+        (CpuFeature::Vaes, extended_features.has_vaes()),
+        (CpuFeature::Vpclmulqdq, extended_features.has_vpclmulqdq()),
+        (CpuFeature::Avx512vnni, extended_features.has_avx512vnni()),
+        (
+            CpuFeature::Avx512bitalg,
+            extended_features.has_avx512bitalg(),
+        ),
+        (
+            CpuFeature::Avx512vpopcntdq,
+            extended_features.has_avx512vpopcntdq(),
+        ),
+        (CpuFeature::Rdpid, extended_features.has_rdpid()),
+        // Лист 7.0, EDX
+        (
+            CpuFeature::Avx5124vnniw,
+            extended_features.has_avx512_4vnniw(),
+        ),
+        (
+            CpuFeature::Avx5124fmaps,
+            extended_features.has_avx512_4fmaps(),
+        ),
+        (
+            CpuFeature::Avx512vp2intersect,
+            extended_features.has_avx512_vp2intersect(),
+        ),
+        (CpuFeature::AmxBf16, extended_features.has_amx_bf16()),
+        (CpuFeature::Avx512fp16, extended_features.has_avx512_fp16()),
+        (CpuFeature::AmxTile, extended_features.has_amx_tile()),
+        (CpuFeature::AmxInt8, extended_features.has_amx_int8()),
+        // Лист 7.1, EAX и EDX
+        (CpuFeature::Avxvnni, extended_features.has_avx_vnni()),
+        (CpuFeature::Avx512bf16, extended_features.has_avx512_bf16()),
+        (CpuFeature::Avxifma, extended_features.has_avx_ifma()),
+        (
+            CpuFeature::Avxvnniint8,
+            extended_features.has_avx_vnni_int8(),
+        ),
+        (
+            CpuFeature::Avxneconvert,
+            extended_features.has_avx_ne_convert(),
+        ),
+        (
+            CpuFeature::Avxvnniint16,
+            extended_features.has_avx_vnni_int16(),
+        ),
+        (CpuFeature::Avx10, extended_features.has_avx10()),
+    ];
+
+    // Лист 0xD (подлист 1): расширения XSAVE
+    if let Some(state_info) = cpuid.get_extended_state_info() {
+        detected.extend([
+            (CpuFeature::Xsaveopt, state_info.has_xsaveopt()),
+            (CpuFeature::Xsavec, state_info.has_xsavec()),
+            (CpuFeature::Xsaves, state_info.has_xsaves_xrstors()),
+        ]);
+    }
+
+    // Лист 0x80000001: расширения AMD и общие расширенные инструкции
+    if let Some(ext_info) = cpuid.get_extended_processor_and_feature_identifiers() {
+        detected.extend([
+            (CpuFeature::Lahfsahf, ext_info.has_lahf_sahf()),
+            (CpuFeature::Lzcnt, ext_info.has_lzcnt()),
+            (CpuFeature::Sse4a, ext_info.has_sse4a()),
+            (CpuFeature::Prfchw, ext_info.has_prefetchw()),
+            (CpuFeature::Xop, ext_info.has_xop()),
+            (CpuFeature::Fma4, ext_info.has_fma4()),
+            (CpuFeature::Tbm, ext_info.has_tbm()),
+            (CpuFeature::Rdtscp, ext_info.has_rdtscp()),
+        ]);
+    }
+
+    Ok(detected
+        .into_iter()
+        .filter(|&(_, supported)| supported)
+        .map(|(feature, _)| feature)
+        .collect())
+}
+
+/// Бит XCR0: состояние регистров XMM (SSE)
+const XCR0_SSE: u64 = 1 << 1;
+/// Бит XCR0: старшие половины регистров YMM (AVX)
+const XCR0_AVX: u64 = 1 << 2;
+/// Биты XCR0: регистры масок, ZMM_Hi256 и Hi16_ZMM (AVX-512)
+const XCR0_AVX512: u64 = (1 << 5) | (1 << 6) | (1 << 7);
+/// Биты XCR0: XTILECFG и XTILEDATA (AMX)
+const XCR0_AMX: u64 = (1 << 17) | (1 << 18);
+
+/// Читает регистр XCR0, если ОС включила XSAVE (бит OSXSAVE)
+#[cfg_attr(not(target_arch = "x86_64"), allow(dead_code))]
+fn read_xcr0(osxsave: bool) -> Option<u64> {
+    if !osxsave {
+        return None;
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        // SAFETY: OSXSAVE означает, что ОС установила CR4.OSXSAVE,
+        // поэтому инструкция XGETBV доступна
+        Some(unsafe { std::arch::x86_64::_xgetbv(0) })
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        None
+    }
+}
+
+/// Возвращает биты XCR0, которые должны быть включены ОС для использования
+/// набора инструкций, и название соответствующего состояния регистров
+fn required_xcr0_state(feature: CpuFeature) -> Option<(u64, &'static str)> {
+    match feature {
+        CpuFeature::Avx
+        | CpuFeature::Avx2
+        | CpuFeature::Fma
+        | CpuFeature::F16c
+        | CpuFeature::Vaes
+        | CpuFeature::Vpclmulqdq
+        | CpuFeature::Avxvnni
+        | CpuFeature::Avxifma
+        | CpuFeature::Avxvnniint8
+        | CpuFeature::Avxneconvert
+        | CpuFeature::Avxvnniint16
+        | CpuFeature::Fma4
+        | CpuFeature::Xop => Some((XCR0_SSE | XCR0_AVX, "YMM")),
+        CpuFeature::Avx512f
+        | CpuFeature::Avx512dq
+        | CpuFeature::Avx512ifma
+        | CpuFeature::Avx512pf
+        | CpuFeature::Avx512er
+        | CpuFeature::Avx512cd
+        | CpuFeature::Avx512bw
+        | CpuFeature::Avx512vl
+        | CpuFeature::Avx512vbmi
+        | CpuFeature::Avx512vbmi2
+        | CpuFeature::Avx512vnni
+        | CpuFeature::Avx512bitalg
+        | CpuFeature::Avx512vpopcntdq
+        | CpuFeature::Avx5124vnniw
+        | CpuFeature::Avx5124fmaps
+        | CpuFeature::Avx512vp2intersect
+        | CpuFeature::Avx512fp16
+        | CpuFeature::Avx512bf16
+        | CpuFeature::Avx10 => Some((XCR0_SSE | XCR0_AVX | XCR0_AVX512, "opmask/ZMM")),
+        CpuFeature::AmxTile | CpuFeature::AmxInt8 | CpuFeature::AmxBf16 => {
+            Some((XCR0_AMX, "AMX TILE"))
+        },
+        _ => None,
+    }
+}
+
+/// Исключает наборы инструкций, для которых ОС не сохраняет состояние регистров
+///
+/// `xcr0` равен `None`, если ОС не включила XSAVE (OSXSAVE = 0): в этом
+/// случае недоступны также сами инструкции семейства XSAVE.
+fn filter_by_os_support(
+    features: FeatureSet,
+    xcr0: Option<u64>,
+) -> (FeatureSet, Vec<SuppressedFeature>) {
+    let mut supported = features;
+    let mut suppressed = Vec::new();
+
+    for feature in features.iter() {
+        let is_xsave_family = matches!(
+            feature,
+            CpuFeature::Xsave | CpuFeature::Xsaveopt | CpuFeature::Xsavec | CpuFeature::Xsaves
+        );
+
+        let reason = match (xcr0, required_xcr0_state(feature)) {
+            (None, Some((_, state))) => Some(format!(
+                "ОС не включила XSAVE (OSXSAVE = 0), состояние {} не сохраняется",
+                state
+            )),
+            (None, None) if is_xsave_family => {
+                Some("ОС не включила XSAVE (OSXSAVE = 0)".to_string())
+            },
+            (Some(xcr0), Some((mask, state))) if xcr0 & mask != mask => Some(format!(
+                "ОС не сохраняет состояние {} (XCR0 = {:#x})",
+                state, xcr0
+            )),
+            _ => None,
+        };
+
+        if let Some(reason) = reason {
+            supported.remove(feature);
+            suppressed.push(SuppressedFeature { feature, reason });
+        }
+    }
+
+    (supported, suppressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_override() {
+        // Значения переменных передаются явно, окружение процесса не
+        // меняется
+        let cpu_info = cpu_info_from_overrides(
+            Some("TestVendor"),
+            Some("TestModel"),
+            Some("avx2,avx,sse4_2"),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(cpu_info.vendor, "TestVendor");
+        assert_eq!(cpu_info.model, "TestModel");
+        assert_eq!(cpu_info.features.to_string(), "sse4.2,avx,avx2");
+
+        let cpu_info = cpu_info_from_overrides(Some("TestVendor"), None, Some(""))
+            .unwrap()
+            .unwrap();
+        assert_eq!(cpu_info.model, "Unknown");
+        assert!(cpu_info.features.is_empty());
+
+        // Без производителя или наборов инструкций процессор определяется
+        assert!(
+            cpu_info_from_overrides(None, Some("TestModel"), Some("avx2"))
+                .unwrap()
+                .is_none()
+        );
+        assert!(cpu_info_from_overrides(Some("TestVendor"), None, None)
+            .unwrap()
+            .is_none());
+
+        // Неизвестные имена наборов инструкций отклоняются
+        let err =
+            cpu_info_from_overrides(Some("TestVendor"), None, Some("avx2,feature1")).unwrap_err();
+        assert!(matches!(err, CpuDetectionError(_)));
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_x86_features_inventory() {
+        // SSE и SSE2 входят в базовый набор x86_64 и присутствуют всегда
+        let features = collect_x86_features(&CpuId::new()).unwrap();
+        assert!(features.contains(CpuFeature::Sse));
+        assert!(features.contains(CpuFeature::Sse2));
+        assert!(!features.contains(CpuFeature::Neon));
+    }
+
+    #[test]
+    fn test_filter_by_os_support() {
+        let features: FeatureSet = "sse4.2,xsave,avx,avx2,avx512f,amx-tile".parse().unwrap();
+
+        // ОС сохраняет все состояния: ничего не исключается
+        let all_state = XCR0_SSE | XCR0_AVX | XCR0_AVX512 | XCR0_AMX | 1;
+        let (supported, suppressed) = filter_by_os_support(features, Some(all_state));
+        assert_eq!(supported, features);
+        assert!(suppressed.is_empty());
+
+        // ОС сохраняет только YMM: исключаются AVX-512 и AMX
+        let (supported, suppressed) = filter_by_os_support(features, Some(XCR0_SSE | XCR0_AVX | 1));
+        assert_eq!(supported.to_string(), "sse4.2,xsave,avx,avx2");
+        let names: Vec<CpuFeature> = suppressed.iter().map(|s| s.feature).collect();
+        assert_eq!(names, vec![CpuFeature::Avx512f, CpuFeature::AmxTile]);
+        assert!(suppressed[0].reason.contains("opmask/ZMM"));
+
+        // OSXSAVE = 0: остается только SSE
+        let (supported, suppressed) = filter_by_os_support(features, None);
+        assert_eq!(supported.to_string(), "sse4.2");
+        assert_eq!(suppressed.len(), 5);
+        assert!(suppressed.iter().all(|s| s.reason.contains("OSXSAVE")));
+    }
+
+    #[test]
+    fn test_profile_round_trip() {
+        let cpu_info = CpuInfo {
+            vendor: "ARM".to_string(),
+            model: "Neoverse-V1".to_string(),
+            features: "neon,sve,dotprod".parse().unwrap(),
+            hwcaps: Some(Hwcaps {
+                hwcap: 0x0040_0000,
+                hwcap2: 0x2,
+            }),
+            topology: CpuTopology {
+                logical_cpus: 64,
+                ..CpuTopology::default()
+            },
+            virtualization: Virtualization {
+                hypervisor: Some(Hypervisor::Kvm),
+                ..Virtualization::default()
+            },
+            ..CpuInfo::default()
+        };
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("profile.json");
+        export_profile(&cpu_info, std::fs::File::create(&path).unwrap()).unwrap();
+
+        let loaded = load_profile(&path).unwrap();
+        assert_eq!(loaded.vendor, cpu_info.vendor);
+        assert_eq!(loaded.model, cpu_info.model);
+        assert_eq!(loaded.features, cpu_info.features);
+        assert_eq!(loaded.hwcaps, cpu_info.hwcaps);
+        assert_eq!(loaded.topology, cpu_info.topology);
+        assert_eq!(loaded.virtualization, cpu_info.virtualization);
+
+        // Отсутствующие поля принимают значения по умолчанию
+        std::fs::write(&path, r#"{"vendor": "GenuineIntel", "features": ["avx2"]}"#).unwrap();
+        let loaded = load_profile(&path).unwrap();
+        assert_eq!(loaded.features.to_string(), "avx2");
+        assert!(loaded.caches.is_empty());
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(matches!(load_profile(&path), Err(CpuDetectionError(_))));
+    }
+
+    #[test]
+    fn test_disable_features() {
+        let mut cpu_info = CpuInfo {
+            features: "sse4.2,avx,avx2,fma,bmi2".parse().unwrap(),
+            ..CpuInfo::default()
+        };
+
+        let disabled: FeatureSet = "avx,avx512f".parse().unwrap();
+        disable_features(&mut cpu_info, &disabled, "CPU_FEATURES_DISABLE");
+
+        assert_eq!(cpu_info.features.to_string(), "sse4.2,bmi2");
+        let suppressed: Vec<_> = cpu_info
+            .suppressed_features
+            .iter()
+            .map(|s| s.feature.name())
+            .collect();
+        assert_eq!(suppressed, ["fma", "avx", "avx2"]);
+        assert_eq!(
+            cpu_info.suppressed_features[1].reason,
+            "отключен через CPU_FEATURES_DISABLE"
+        );
+
+        // Отключение NEON на x86_64 не затрагивает AES-NI
+        let mut cpu_info = CpuInfo {
+            arch: "x86_64".to_string(),
+            features: "sse2,aes,pclmulqdq".parse().unwrap(),
+            ..CpuInfo::default()
+        };
+        disable_features(
+            &mut cpu_info,
+            &"neon".parse().unwrap(),
+            "--disable-features",
+        );
+        assert!(cpu_info.features.contains(CpuFeature::Aes));
+        assert!(cpu_info.suppressed_features.is_empty());
+
+        // Для профиля без архитектуры зависимости берутся по архитектуре
+        // текущей системы: на x86 отключение SSE2 отключает и AES-NI
+        let mut cpu_info = CpuInfo {
+            features: "sse2,aes".parse().unwrap(),
+            ..CpuInfo::default()
+        };
+        assert_eq!(cpu_info.effective_arch(), env::consts::ARCH);
+        disable_features(
+            &mut cpu_info,
+            &"sse2".parse().unwrap(),
+            "--disable-features",
+        );
+        assert_eq!(
+            cpu_info.features.contains(CpuFeature::Aes),
+            !matches!(env::consts::ARCH, "x86_64" | "x86")
+        );
+    }
+
+    #[test]
+    fn test_cpuid_dump_replay() {
+        let dump = CpuIdDump::from_json(include_str!(
+            "../tests/fixtures/cpuid/x86_64_sapphire_rapids_kvm.json"
+        ))
+        .unwrap();
+        let cpu_info = cpu_info_from_cpuid_dump(&dump).unwrap();
+
+        assert_eq!(cpu_info.vendor, "GenuineIntel");
+        assert_eq!(cpu_info.model, "Intel(R) Xeon(R) Processor");
+        assert_eq!(
+            cpu_info.microarchitecture.as_ref().map(|m| m.id.as_str()),
+            Some("sapphirerapids")
+        );
+        assert_eq!(cpu_info.x86_64_level(), Some(X86_64Level::V4));
+        for feature in [
+            CpuFeature::Avx512fp16,
+            CpuFeature::AmxTile,
+            CpuFeature::Avxvnni,
+        ] {
+            assert!(cpu_info.features.contains(feature), "{}", feature);
+        }
+        assert!(cpu_info.suppressed_features.is_empty());
+        assert_eq!(cpu_info.virtualization.hypervisor, Some(Hypervisor::Kvm));
+        assert_eq!(cpu_info.caches.len(), 4);
+
+        // Без XSAVE в ОС наборы AVX и AMX исключаются
+        let dump = CpuIdDump { xcr0: None, ..dump };
+        let cpu_info = cpu_info_from_cpuid_dump(&dump).unwrap();
+        assert!(!cpu_info.features.contains(CpuFeature::Avx));
+        assert_eq!(cpu_info.x86_64_level(), Some(X86_64Level::V2));
+
+        // Ivy Bridge: AVX без AVX2, FMA и MOVBE
+        let dump = CpuIdDump::from_json(include_str!(
+            "../tests/fixtures/cpuid/x86_64_ivybridge_ep.json"
+        ))
+        .unwrap();
+        let cpu_info = cpu_info_from_cpuid_dump(&dump).unwrap();
+        assert_eq!(
+            cpu_info.microarchitecture.as_ref().map(|m| m.id.as_str()),
+            Some("ivybridge")
+        );
+        assert_eq!(cpu_info.x86_64_level(), Some(X86_64Level::V2));
+        assert!(cpu_info.features.contains(CpuFeature::Avx));
+        for feature in [CpuFeature::Avx2, CpuFeature::Fma, CpuFeature::Movbe] {
+            assert!(!cpu_info.features.contains(feature), "{}", feature);
+        }
+        assert_eq!(cpu_info.virtualization.hypervisor, None);
+        assert_eq!(cpu_info.caches.len(), 4);
+
+        // Zen 2: кэши описаны расширенным листом 0x8000001D
+        let dump = CpuIdDump::from_json(include_str!(
+            "../tests/fixtures/cpuid/x86_64_zen2_rome.json"
+        ))
+        .unwrap();
+        let cpu_info = cpu_info_from_cpuid_dump(&dump).unwrap();
+        assert_eq!(cpu_info.vendor, "AuthenticAMD");
+        assert_eq!(cpu_info.model, "AMD EPYC 7742 64-Core Processor");
+        assert_eq!(
+            cpu_info.microarchitecture.as_ref().map(|m| m.id.as_str()),
+            Some("zen2")
+        );
+        assert_eq!(cpu_info.x86_64_level(), Some(X86_64Level::V3));
+        for feature in [CpuFeature::Sse4a, CpuFeature::Sha, CpuFeature::Bmi2] {
+            assert!(cpu_info.features.contains(feature), "{}", feature);
+        }
+        assert!(!cpu_info.features.contains(CpuFeature::Avx512f));
+        assert_eq!(cpu_info.caches.len(), 4);
+        assert_eq!(
+            cpu_info.caches.last().map(|cache| cache.size),
+            Some(16 * 1024 * 1024)
+        );
+
+        // Остальные поколения: микроархитектура и уровень x86-64
+        let cases = [
+            (
+                include_str!("../tests/fixtures/cpuid/x86_64_penryn.json"),
+                None,
+                X86_64Level::V1,
+            ),
+            (
+                include_str!("../tests/fixtures/cpuid/x86_64_westmere_ep.json"),
+                Some("westmere"),
+                X86_64Level::V2,
+            ),
+            (
+                include_str!("../tests/fixtures/cpuid/x86_64_haswell.json"),
+                Some("haswell"),
+                X86_64Level::V3,
+            ),
+            (
+                include_str!("../tests/fixtures/cpuid/x86_64_skylake_sp.json"),
+                Some("skylake-sp"),
+                X86_64Level::V4,
+            ),
+            (
+                include_str!("../tests/fixtures/cpuid/x86_64_alderlake.json"),
+                Some("alderlake"),
+                X86_64Level::V3,
+            ),
+            (
+                include_str!("../tests/fixtures/cpuid/x86_64_zen4_genoa.json"),
+                Some("zen4"),
+                X86_64Level::V4,
+            ),
+        ];
+        for (json, microarch, level) in cases {
+            let cpu_info = cpu_info_from_cpuid_dump(&CpuIdDump::from_json(json).unwrap()).unwrap();
+            assert_eq!(
+                cpu_info.microarchitecture.as_ref().map(|m| m.id.as_str()),
+                microarch,
+                "{}",
+                cpu_info.model
+            );
+            assert_eq!(cpu_info.x86_64_level(), Some(level), "{}", cpu_info.model);
+            assert!(
+                cpu_info.suppressed_features.is_empty(),
+                "{}",
+                cpu_info.model
+            );
+        }
+
+        // Penryn: SSE4.1 без SSE4.2 и POPCNT
+        let dump = CpuIdDump::from_json(include_str!("../tests/fixtures/cpuid/x86_64_penryn.json"))
+            .unwrap();
+        let cpu_info = cpu_info_from_cpuid_dump(&dump).unwrap();
+        assert!(cpu_info.features.contains(CpuFeature::Sse41));
+        for feature in [CpuFeature::Sse42, CpuFeature::Popcnt] {
+            assert!(!cpu_info.features.contains(feature), "{}", feature);
+        }
+        assert_eq!(cpu_info.caches.len(), 3);
+
+        // Westmere-EP: AES-NI без XSAVE, XCR0 в дампе отсутствует
+        let dump = CpuIdDump::from_json(include_str!(
+            "../tests/fixtures/cpuid/x86_64_westmere_ep.json"
+        ))
+        .unwrap();
+        assert_eq!(dump.xcr0, None);
+        let cpu_info = cpu_info_from_cpuid_dump(&dump).unwrap();
+        assert!(cpu_info.features.contains(CpuFeature::Aes));
+        assert!(!cpu_info.features.contains(CpuFeature::Avx));
+
+        // Alder Lake: гибридный процессор с AVX-VNNI, AVX-512 отключен
+        let dump = CpuIdDump::from_json(include_str!(
+            "../tests/fixtures/cpuid/x86_64_alderlake.json"
+        ))
+        .unwrap();
+        let cpu_info = cpu_info_from_cpuid_dump(&dump).unwrap();
+        assert!(cpu_info.features.contains(CpuFeature::Avxvnni));
+        assert!(!cpu_info.features.contains(CpuFeature::Avx512f));
+        assert_eq!(
+            cpu_info.caches.first().map(|cache| cache.size),
+            Some(48 * 1024)
+        );
+
+        // Zen 4: AVX-512 с BF16 и VNNI
+        let dump = CpuIdDump::from_json(include_str!(
+            "../tests/fixtures/cpuid/x86_64_zen4_genoa.json"
+        ))
+        .unwrap();
+        let cpu_info = cpu_info_from_cpuid_dump(&dump).unwrap();
+        for feature in [
+            CpuFeature::Avx512bf16,
+            CpuFeature::Avx512vnni,
+            CpuFeature::Sse4a,
+        ] {
+            assert!(cpu_info.features.contains(feature), "{}", feature);
+        }
+        assert_eq!(cpu_info.caches.len(), 4);
+    }
+}
@@ -0,0 +1,565 @@
+//! Определение процессоров aarch64.
+//!
+//! Наборы инструкций берутся из битовых масок `AT_HWCAP`/`AT_HWCAP2`,
+//! производитель и модель - из идентификаторов ядер в `/proc/cpuinfo`.
+
+use super::microarch::{self, Microarchitecture};
+use super::{CpuInfo, Hwcaps};
+use crate::error::CpuDetectionError;
+use crate::features::{CpuFeature, FeatureSet};
+
+/// Определяет информацию о процессоре aarch64
+pub fn detect_aarch64() -> Result<CpuInfo, CpuDetectionError> {
+    // На ARM архитектуре нет прямого аналога CPUID, поэтому используются
+    // данные ядра: вспомогательный вектор процесса и /proc/cpuinfo
+    #[cfg(target_os = "linux")]
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo")
+        .map(|text| parse_aarch64_cpuinfo(&text))
+        .unwrap_or_default();
+
+    #[cfg(not(target_os = "linux"))]
+    let cpuinfo = Aarch64CpuInfo::default();
+
+    // Наборы инструкций определяются по битовым маскам AT_HWCAP/AT_HWCAP2,
+    // а при их отсутствии - по строкам Features из /proc/cpuinfo
+    let hwcaps = read_aarch64_hwcaps();
+    let features = match hwcaps {
+        Some((hwcap, hwcap2)) => decode_aarch64_hwcaps(hwcap, hwcap2),
+        None if !cpuinfo.cores.is_empty() => cpuinfo.common_features(),
+        // NEON обязателен в ARMv8-A, поэтому доступен всегда
+        None => [CpuFeature::Neon].into_iter().collect(),
+    };
+
+    Ok(CpuInfo {
+        vendor: cpuinfo.vendor(),
+        model: cpuinfo.model(),
+        microarchitecture: cpuinfo.microarchitecture(),
+        features,
+        hwcaps: hwcaps.map(|(hwcap, hwcap2)| Hwcaps { hwcap, hwcap2 }),
+        ..CpuInfo::default()
+    })
+}
+
+/// Имена битов AT_HWCAP в порядке номеров (arch/arm64/include/uapi/asm/hwcap.h)
+///
+/// Совпадают с именами в строке `Features` файла `/proc/cpuinfo`.
+const AARCH64_HWCAP_NAMES: &[&str] = &[
+    "fp", "asimd", "evtstrm", "aes", "pmull", "sha1", "sha2", "crc32", "atomics", "fphp",
+    "asimdhp", "cpuid", "asimdrdm", "jscvt", "fcma", "lrcpc", "dcpop", "sha3", "sm3", "sm4",
+    "asimddp", "sha512", "sve", "asimdfhm", "dit", "uscat", "ilrcpc", "flagm", "ssbs", "sb",
+    "paca", "pacg",
+];
+
+/// Имена битов AT_HWCAP2 в порядке номеров
+const AARCH64_HWCAP2_NAMES: &[&str] = &[
+    "dcpodp",
+    "sve2",
+    "sveaes",
+    "svepmull",
+    "svebitperm",
+    "svesha3",
+    "svesm4",
+    "flagm2",
+    "frint",
+    "svei8mm",
+    "svef32mm",
+    "svef64mm",
+    "svebf16",
+    "i8mm",
+    "bf16",
+    "dgh",
+    "rng",
+    "bti",
+    "mte",
+    "ecv",
+    "afp",
+    "rpres",
+    "mte3",
+    "sme",
+];
+
+/// Биты AT_HWCAP для aarch64
+const AARCH64_HWCAP: &[(CpuFeature, u64)] = &[
+    (CpuFeature::Neon, 1 << 1),               // HWCAP_ASIMD
+    (CpuFeature::Aes, 1 << 3),                // HWCAP_AES
+    (CpuFeature::Pmull, 1 << 4),              // HWCAP_PMULL
+    (CpuFeature::Sha2, (1 << 5) | (1 << 6)),  // HWCAP_SHA1 | HWCAP_SHA2
+    (CpuFeature::Crc, 1 << 7),                // HWCAP_CRC32
+    (CpuFeature::Lse, 1 << 8),                // HWCAP_ATOMICS
+    (CpuFeature::Fp16, (1 << 9) | (1 << 10)), // HWCAP_FPHP | HWCAP_ASIMDHP
+    (CpuFeature::Rcpc, 1 << 15),              // HWCAP_LRCPC
+    (CpuFeature::Sha3, 1 << 17),              // HWCAP_SHA3
+    (CpuFeature::Dotprod, 1 << 20),           // HWCAP_ASIMDDP
+    (CpuFeature::Sve, 1 << 22),               // HWCAP_SVE
+];
+
+/// Биты AT_HWCAP2 для aarch64
+const AARCH64_HWCAP2: &[(CpuFeature, u64)] = &[
+    (CpuFeature::Sve2, 1 << 1),  // HWCAP2_SVE2
+    (CpuFeature::I8mm, 1 << 13), // HWCAP2_I8MM
+    (CpuFeature::Bf16, 1 << 14), // HWCAP2_BF16
+];
+
+/// Декодирует битовые маски AT_HWCAP и AT_HWCAP2 ядра Linux для aarch64
+///
+/// Набор инструкций считается поддерживаемым, если установлены все биты
+/// его маски.
+pub fn decode_aarch64_hwcaps(hwcap: u64, hwcap2: u64) -> FeatureSet {
+    let from_hwcap = AARCH64_HWCAP
+        .iter()
+        .filter(|&&(_, mask)| hwcap & mask == mask)
+        .map(|&(feature, _)| feature);
+    let from_hwcap2 = AARCH64_HWCAP2
+        .iter()
+        .filter(|&&(_, mask)| hwcap2 & mask == mask)
+        .map(|&(feature, _)| feature);
+
+    from_hwcap.chain(from_hwcap2).collect()
+}
+
+/// Переводит строку `Features` из /proc/cpuinfo в битовые маски
+/// AT_HWCAP и AT_HWCAP2; неизвестные имена пропускаются
+pub fn aarch64_features_to_hwcaps(features: &str) -> (u64, u64) {
+    let mut hwcap = 0;
+    let mut hwcap2 = 0;
+
+    for name in features.split_whitespace() {
+        if let Some(bit) = AARCH64_HWCAP_NAMES.iter().position(|&n| n == name) {
+            hwcap |= 1 << bit;
+        } else if let Some(bit) = AARCH64_HWCAP2_NAMES.iter().position(|&n| n == name) {
+            hwcap2 |= 1 << bit;
+        }
+    }
+
+    (hwcap, hwcap2)
+}
+
+/// Читает AT_HWCAP и AT_HWCAP2 из вспомогательного вектора процесса
+fn read_aarch64_hwcaps() -> Option<(u64, u64)> {
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    {
+        // SAFETY: getauxval только читает вспомогательный вектор процесса
+        let (hwcap, hwcap2) = unsafe {
+            (
+                libc::getauxval(libc::AT_HWCAP),
+                libc::getauxval(libc::AT_HWCAP2),
+            )
+        };
+        Some((hwcap as u64, hwcap2 as u64))
+    }
+
+    #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+    {
+        None
+    }
+}
+
+/// Описание одного ядра aarch64 из /proc/cpuinfo
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Aarch64Core {
+    /// Номер логического процессора
+    pub processor: u32,
+    /// Наборы инструкций из строки `Features`
+    pub features: FeatureSet,
+    /// Код производителя (`CPU implementer`)
+    pub implementer: u32,
+    /// Версия архитектуры (`CPU architecture`)
+    pub architecture: u32,
+    /// Вариант ядра (`CPU variant`)
+    pub variant: u32,
+    /// Код модели ядра (`CPU part`)
+    pub part: u32,
+    /// Ревизия ядра (`CPU revision`)
+    pub revision: u32,
+}
+
+impl Aarch64Core {
+    /// Название производителя ядра
+    pub fn implementer_name(&self) -> Option<&'static str> {
+        aarch64_implementer_name(self.implementer)
+    }
+
+    /// Название модели ядра
+    pub fn part_name(&self) -> Option<&'static str> {
+        aarch64_part_name(self.implementer, self.part)
+    }
+}
+
+/// Результат разбора /proc/cpuinfo на aarch64
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Aarch64CpuInfo {
+    /// Описания ядер в порядке номеров процессоров
+    pub cores: Vec<Aarch64Core>,
+    /// Значение поля `Hardware` (печатается некоторыми ядрами для SoC)
+    pub hardware: Option<String>,
+    /// Значение поля `Model` или `model name`
+    pub model_name: Option<String>,
+}
+
+impl Aarch64CpuInfo {
+    /// Наборы инструкций, поддерживаемые всеми ядрами
+    pub fn common_features(&self) -> FeatureSet {
+        let mut cores = self.cores.iter();
+        let first = cores.next().map(|core| core.features).unwrap_or_default();
+        cores.fold(first, |acc, core| acc.intersection(&core.features))
+    }
+
+    /// Производитель процессора
+    ///
+    /// Берется по коду `CPU implementer`, а если он неизвестен - из поля
+    /// `Hardware`.
+    pub fn vendor(&self) -> String {
+        self.cores
+            .iter()
+            .find_map(Aarch64Core::implementer_name)
+            .map(String::from)
+            .or_else(|| self.hardware.clone())
+            .unwrap_or_else(|| "ARM".to_string())
+    }
+
+    /// Микроархитектура старших ядер процессора
+    ///
+    /// На big.LITTLE старшие ядра обычно имеют наибольшие номера, поэтому
+    /// берется последнее ядро с известной моделью.
+    pub fn microarchitecture(&self) -> Option<Microarchitecture> {
+        self.cores
+            .iter()
+            .rev()
+            .find_map(Aarch64Core::part_name)
+            .map(microarch::identify_aarch64)
+    }
+
+    /// Модель процессора
+    ///
+    /// Для гетерогенных процессоров (big.LITTLE) перечисляются все типы
+    /// ядер с их количеством, начиная с самых многочисленных.
+    pub fn model(&self) -> String {
+        let mut parts: Vec<(String, usize)> = Vec::new();
+        for core in &self.cores {
+            let name = match core.part_name() {
+                Some(name) => name.to_string(),
+                None => format!("{:#x}:{:#05x}", core.implementer, core.part),
+            };
+            match parts.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, count)) => *count += 1,
+                None => parts.push((name, 1)),
+            }
+        }
+
+        let known = self.cores.iter().any(|core| core.part_name().is_some());
+        if !known {
+            if let Some(model_name) = &self.model_name {
+                return model_name.clone();
+            }
+        }
+
+        if parts.is_empty() {
+            return "Unknown ARM Processor".to_string();
+        }
+
+        if let [(name, _)] = parts.as_slice() {
+            return name.clone();
+        }
+
+        parts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        parts
+            .into_iter()
+            .map(|(name, count)| format!("{}x {}", count, name))
+            .collect::<Vec<_>>()
+            .join(" + ")
+    }
+}
+
+/// Разбирает содержимое /proc/cpuinfo ядра Linux для aarch64
+///
+/// Каждый блок, начинающийся со строки `processor`, описывает одно ядро.
+/// Поля вне таких блоков (`Hardware`, `Model`) относятся ко всей системе.
+pub fn parse_aarch64_cpuinfo(text: &str) -> Aarch64CpuInfo {
+    let mut info = Aarch64CpuInfo::default();
+    let mut current: Option<Aarch64Core> = None;
+
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim();
+        let value = value.trim();
+
+        match key {
+            "processor" => {
+                info.cores.extend(current.take());
+                current = Some(Aarch64Core {
+                    processor: parse_cpuinfo_number(value).unwrap_or_default(),
+                    ..Aarch64Core::default()
+                });
+            },
+            "Hardware" => info.hardware = Some(value.to_string()),
+            "Model" | "model name" => info.model_name = Some(value.to_string()),
+            _ => {
+                let Some(core) = current.as_mut() else {
+                    continue;
+                };
+                let number = parse_cpuinfo_number(value);
+                match key {
+                    "Features" => {
+                        let (hwcap, hwcap2) = aarch64_features_to_hwcaps(value);
+                        core.features = decode_aarch64_hwcaps(hwcap, hwcap2);
+                    },
+                    "CPU implementer" => core.implementer = number.unwrap_or_default(),
+                    "CPU architecture" => core.architecture = number.unwrap_or_default(),
+                    "CPU variant" => core.variant = number.unwrap_or_default(),
+                    "CPU part" => core.part = number.unwrap_or_default(),
+                    "CPU revision" => core.revision = number.unwrap_or_default(),
+                    _ => {},
+                }
+            },
+        }
+    }
+
+    info.cores.extend(current);
+    info
+}
+
+/// Разбирает десятичное или шестнадцатеричное (с префиксом 0x) число
+fn parse_cpuinfo_number(value: &str) -> Option<u32> {
+    match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// Название производителя по коду `CPU implementer` (регистр MIDR_EL1)
+pub fn aarch64_implementer_name(implementer: u32) -> Option<&'static str> {
+    let name = match implementer {
+        0x41 => "ARM",
+        0x42 => "Broadcom",
+        0x43 => "Cavium",
+        0x46 => "Fujitsu",
+        0x48 => "HiSilicon",
+        0x4e => "NVIDIA",
+        0x50 => "APM",
+        0x51 => "Qualcomm",
+        0x61 => "Apple",
+        0x6d => "Microsoft",
+        0xc0 => "Ampere",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Название модели ядра по кодам `CPU implementer` и `CPU part`
+pub fn aarch64_part_name(implementer: u32, part: u32) -> Option<&'static str> {
+    let name = match (implementer, part) {
+        // ARM
+        (0x41, 0xd03) => "Cortex-A53",
+        (0x41, 0xd04) => "Cortex-A35",
+        (0x41, 0xd05) => "Cortex-A55",
+        (0x41, 0xd07) => "Cortex-A57",
+        (0x41, 0xd08) => "Cortex-A72",
+        (0x41, 0xd09) => "Cortex-A73",
+        (0x41, 0xd0a) => "Cortex-A75",
+        (0x41, 0xd0b) => "Cortex-A76",
+        (0x41, 0xd0c) => "Neoverse-N1",
+        (0x41, 0xd0d) => "Cortex-A77",
+        (0x41, 0xd40) => "Neoverse-V1",
+        (0x41, 0xd41) => "Cortex-A78",
+        (0x41, 0xd44) => "Cortex-X1",
+        (0x41, 0xd46) => "Cortex-A510",
+        (0x41, 0xd47) => "Cortex-A710",
+        (0x41, 0xd48) => "Cortex-X2",
+        (0x41, 0xd49) => "Neoverse-N2",
+        (0x41, 0xd4a) => "Neoverse-E1",
+        (0x41, 0xd4d) => "Cortex-A715",
+        (0x41, 0xd4e) => "Cortex-X3",
+        (0x41, 0xd4f) => "Neoverse-V2",
+        (0x41, 0xd80) => "Cortex-A520",
+        (0x41, 0xd81) => "Cortex-A720",
+        (0x41, 0xd82) => "Cortex-X4",
+        (0x41, 0xd84) => "Neoverse-V3",
+        (0x41, 0xd8e) => "Neoverse-N3",
+        // Cavium
+        (0x43, 0x0a1) => "ThunderX",
+        (0x43, 0x0af) => "ThunderX2",
+        // Fujitsu
+        (0x46, 0x001) => "A64FX",
+        // HiSilicon
+        (0x48, 0xd01) => "TaiShan-v110",
+        // NVIDIA
+        (0x4e, 0x004) => "Carmel",
+        // Qualcomm
+        (0x51, 0x001) => "Oryon",
+        (0x51, 0x800) => "Kryo-2XX-Gold",
+        (0x51, 0x801) => "Kryo-2XX-Silver",
+        (0x51, 0x802) => "Kryo-3XX-Gold",
+        (0x51, 0x803) => "Kryo-3XX-Silver",
+        (0x51, 0x804) => "Kryo-4XX-Gold",
+        (0x51, 0x805) => "Kryo-4XX-Silver",
+        (0x51, 0xc00) => "Falkor",
+        // Apple
+        (0x61, 0x022) => "M1-Icestorm",
+        (0x61, 0x023) => "M1-Firestorm",
+        (0x61, 0x024) => "M1-Pro-Icestorm",
+        (0x61, 0x025) => "M1-Pro-Firestorm",
+        (0x61, 0x028) => "M1-Max-Icestorm",
+        (0x61, 0x029) => "M1-Max-Firestorm",
+        (0x61, 0x032) => "M2-Blizzard",
+        (0x61, 0x033) => "M2-Avalanche",
+        // Ampere
+        (0xc0, 0xac3) => "Ampere-1",
+        (0xc0, 0xac4) => "Ampere-1a",
+        _ => return None,
+    };
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_aarch64_hwcaps() {
+        // Cortex-A72 (Raspberry Pi 4): fp asimd evtstrm aes pmull sha1 sha2 crc32 cpuid
+        let features = decode_aarch64_hwcaps(0x8ff, 0);
+        assert_eq!(features.to_string(), "aes,neon,pmull,sha2,crc");
+
+        // Neoverse-N1 (AWS Graviton2)
+        let features = decode_aarch64_hwcaps(0x1011_9fff, 0);
+        assert_eq!(
+            features.to_string(),
+            "aes,neon,pmull,sha2,crc,lse,fp16,rcpc,dotprod"
+        );
+
+        // Neoverse-V1 (AWS Graviton3): SVE, I8MM и BF16, но без SVE2
+        let features = decode_aarch64_hwcaps(0xdfff_ffff, 0x1_f201);
+        assert!(features.contains(CpuFeature::Sve));
+        assert!(features.contains(CpuFeature::Sha3));
+        assert!(features.contains(CpuFeature::I8mm));
+        assert!(features.contains(CpuFeature::Bf16));
+        assert!(!features.contains(CpuFeature::Sve2));
+
+        // Neoverse-V2 (AWS Graviton4, NVIDIA Grace): все поддерживаемые наборы
+        let features = decode_aarch64_hwcaps(0xffff_ffff, 0x3_f3ff);
+        assert_eq!(features.len(), AARCH64_HWCAP.len() + AARCH64_HWCAP2.len());
+
+        // Половинная точность требует и скалярной, и векторной поддержки
+        let features = decode_aarch64_hwcaps((1 << 1) | (1 << 9), 0);
+        assert!(!features.contains(CpuFeature::Fp16));
+    }
+
+    #[test]
+    fn test_features_line_matches_hwcaps() {
+        // Строка Features и битовые маски Graviton3 описывают одно и то же
+        let text = include_str!("../../tests/fixtures/cpuinfo/aarch64_graviton3.txt");
+        let line = text.lines().find(|l| l.starts_with("Features")).unwrap();
+        let (_, features) = line.split_once(':').unwrap();
+        assert_eq!(
+            aarch64_features_to_hwcaps(features),
+            (0xdfff_ffff, 0x1_f201)
+        );
+    }
+
+    #[test]
+    fn test_parse_cpuinfo_graviton2() {
+        let text = include_str!("../../tests/fixtures/cpuinfo/aarch64_graviton2.txt");
+        let info = parse_aarch64_cpuinfo(text);
+
+        assert_eq!(info.cores.len(), 4);
+        let core = &info.cores[3];
+        assert_eq!(core.processor, 3);
+        assert_eq!(core.implementer, 0x41);
+        assert_eq!(core.architecture, 8);
+        assert_eq!(core.variant, 0x3);
+        assert_eq!(core.part, 0xd0c);
+        assert_eq!(core.revision, 1);
+
+        assert_eq!(info.vendor(), "ARM");
+        assert_eq!(info.model(), "Neoverse-N1");
+        assert_eq!(info.microarchitecture().unwrap().id, "neoverse-n1");
+        assert_eq!(
+            info.common_features(),
+            decode_aarch64_hwcaps(0x1011_9fff, 0)
+        );
+    }
+
+    #[test]
+    fn test_parse_cpuinfo_vendors() {
+        let cases = [
+            (
+                include_str!("../../tests/fixtures/cpuinfo/aarch64_graviton3.txt"),
+                "ARM",
+                "Neoverse-V1",
+            ),
+            (
+                include_str!("../../tests/fixtures/cpuinfo/aarch64_grace.txt"),
+                "ARM",
+                "Neoverse-V2",
+            ),
+            (
+                include_str!("../../tests/fixtures/cpuinfo/aarch64_ampere_one.txt"),
+                "Ampere",
+                "Ampere-1",
+            ),
+            (
+                include_str!("../../tests/fixtures/cpuinfo/aarch64_a64fx.txt"),
+                "Fujitsu",
+                "A64FX",
+            ),
+            (
+                include_str!("../../tests/fixtures/cpuinfo/aarch64_rpi4.txt"),
+                "ARM",
+                "Cortex-A72",
+            ),
+        ];
+
+        for (text, vendor, model) in cases {
+            let info = parse_aarch64_cpuinfo(text);
+            assert_eq!(info.vendor(), vendor);
+            assert_eq!(info.model(), model);
+        }
+    }
+
+    #[test]
+    fn test_parse_cpuinfo_board_fields() {
+        let text = include_str!("../../tests/fixtures/cpuinfo/aarch64_rpi4.txt");
+        let info = parse_aarch64_cpuinfo(text);
+
+        // Поле Revision платы не путается с CPU revision ядра
+        assert_eq!(info.cores.len(), 4);
+        assert_eq!(info.cores[3].revision, 3);
+        assert_eq!(info.hardware.as_deref(), Some("BCM2835"));
+        assert_eq!(
+            info.model_name.as_deref(),
+            Some("Raspberry Pi 4 Model B Rev 1.4")
+        );
+
+        // Cortex-A72 в Raspberry Pi 4 не имеет криптографических расширений
+        assert!(!info.common_features().contains(CpuFeature::Aes));
+        assert!(info.common_features().contains(CpuFeature::Crc));
+    }
+
+    #[test]
+    fn test_parse_cpuinfo_big_little() {
+        let text = include_str!("../../tests/fixtures/cpuinfo/aarch64_rk3588.txt");
+        let info = parse_aarch64_cpuinfo(text);
+
+        assert_eq!(info.cores.len(), 8);
+        assert_eq!(info.cores[0].part_name(), Some("Cortex-A55"));
+        assert_eq!(info.cores[7].part_name(), Some("Cortex-A76"));
+        assert_eq!(info.model(), "4x Cortex-A55 + 4x Cortex-A76");
+        assert_eq!(info.microarchitecture().unwrap().id, "cortex-a76");
+    }
+
+    #[test]
+    fn test_parse_cpuinfo_unknown_part() {
+        let text = "processor\t: 0\nCPU implementer\t: 0x41\nCPU part\t: 0xfff\n\n\
+                    model name\t: Custom SoC\n";
+        let info = parse_aarch64_cpuinfo(text);
+        assert_eq!(info.vendor(), "ARM");
+        assert_eq!(info.model(), "Custom SoC");
+        assert_eq!(info.microarchitecture(), None);
+
+        let info = parse_aarch64_cpuinfo("");
+        assert_eq!(info.vendor(), "ARM");
+        assert_eq!(info.model(), "Unknown ARM Processor");
+    }
+}
@@ -0,0 +1,321 @@
+//! Определение иерархии кэшей процессора.
+//!
+//! На Linux параметры кэшей читаются из
+//! `/sys/devices/system/cpu/cpu0/cache/index*` для любой архитектуры,
+//! на x86_64 при отсутствии sysfs используются листы CPUID 4 (Intel) и
+//! 0x8000001D (AMD).
+
+use super::sysfs;
+use raw_cpuid::{CpuId, CpuIdReader};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// Тип кэша
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheType {
+    /// Кэш данных
+    Data,
+    /// Кэш инструкций
+    Instruction,
+    /// Общий кэш данных и инструкций
+    Unified,
+}
+
+/// Параметры одного уровня кэша
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheInfo {
+    /// Уровень кэша (1, 2, 3, ...)
+    pub level: u8,
+    /// Тип кэша
+    pub cache_type: CacheType,
+    /// Размер в байтах
+    pub size: u64,
+    /// Размер строки кэша в байтах
+    pub line_size: u32,
+    /// Ассоциативность (0 - полностью ассоциативный кэш)
+    pub associativity: u32,
+    /// Количество наборов
+    pub sets: u32,
+    /// Количество логических процессоров, разделяющих кэш
+    pub shared_by: u32,
+}
+
+impl CacheInfo {
+    /// Короткое имя кэша: `L1d`, `L1i`, `L2`, `L3`
+    pub fn name(&self) -> String {
+        match self.cache_type {
+            CacheType::Data => format!("L{}d", self.level),
+            CacheType::Instruction => format!("L{}i", self.level),
+            CacheType::Unified => format!("L{}", self.level),
+        }
+    }
+}
+
+impl fmt::Display for CacheInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name(), format_size(self.size))?;
+        if self.associativity == 0 {
+            write!(f, ", полностью ассоциативный")?;
+        } else {
+            write!(f, ", {}-way", self.associativity)?;
+        }
+        write!(
+            f,
+            ", строка {} Б, общий для {} логических процессоров",
+            self.line_size, self.shared_by
+        )
+    }
+}
+
+/// Форматирует размер в байтах в КБ или МБ
+fn format_size(size: u64) -> String {
+    if size >= 1 << 20 && size.is_multiple_of(1 << 20) {
+        format!("{} МБ", size >> 20)
+    } else if size >= 1 << 10 {
+        format!("{} КБ", size >> 10)
+    } else {
+        format!("{} Б", size)
+    }
+}
+
+/// Сортирует кэши по уровню: сначала L1d, затем L1i, L2, L3
+fn sort_caches(caches: &mut [CacheInfo]) {
+    caches.sort_by_key(|cache| {
+        let type_order = match cache.cache_type {
+            CacheType::Data => 0,
+            CacheType::Instruction => 1,
+            CacheType::Unified => 2,
+        };
+        (cache.level, type_order)
+    });
+}
+
+/// Читает параметры кэшей процессора cpu0 из sysfs
+///
+/// `root` - корень sysfs (обычно `/sys`). Возвращает `None`, если каталог
+/// кэшей отсутствует или не содержит ни одного описания.
+pub fn read_sysfs_caches(root: &Path) -> Option<Vec<CacheInfo>> {
+    let cache_dir = root.join("devices/system/cpu/cpu0/cache");
+    let mut caches = Vec::new();
+
+    for entry in std::fs::read_dir(cache_dir).ok()?.flatten() {
+        let name = entry.file_name();
+        if !name.to_string_lossy().starts_with("index") {
+            continue;
+        }
+        if let Some(cache) = read_sysfs_cache_index(&entry.path()) {
+            caches.push(cache);
+        }
+    }
+
+    if caches.is_empty() {
+        return None;
+    }
+
+    sort_caches(&mut caches);
+    Some(caches)
+}
+
+/// Читает описание одного кэша из каталога `index*`
+fn read_sysfs_cache_index(dir: &Path) -> Option<CacheInfo> {
+    let cache_type = match sysfs::read_trimmed(&dir.join("type"))?.as_str() {
+        "Data" => CacheType::Data,
+        "Instruction" => CacheType::Instruction,
+        "Unified" => CacheType::Unified,
+        _ => return None,
+    };
+
+    // Некоторые ядра (например, в виртуальных машинах) не сообщают
+    // необязательные параметры: для них используется 0, а кэш без
+    // списка процессоров считается частным
+    let shared_by = sysfs::read_trimmed(&dir.join("shared_cpu_list"))
+        .and_then(|list| sysfs::parse_cpu_list(&list))
+        .map_or(1, |cpus| cpus.len().max(1) as u32);
+
+    Some(CacheInfo {
+        level: sysfs::read_number(&dir.join("level"))?,
+        cache_type,
+        size: sysfs::parse_size(&sysfs::read_trimmed(&dir.join("size"))?)?,
+        line_size: sysfs::read_number(&dir.join("coherency_line_size")).unwrap_or(0),
+        associativity: sysfs::read_number(&dir.join("ways_of_associativity")).unwrap_or(0),
+        sets: sysfs::read_number(&dir.join("number_of_sets")).unwrap_or(0),
+        shared_by,
+    })
+}
+
+/// Определяет параметры кэшей по листам CPUID 4 и 0x8000001D
+pub fn caches_from_cpuid<R: CpuIdReader>(cpuid: &CpuId<R>) -> Option<Vec<CacheInfo>> {
+    let mut caches: Vec<CacheInfo> = cpuid
+        .get_cache_parameters()?
+        .filter_map(|params| {
+            let cache_type = match params.cache_type() {
+                raw_cpuid::CacheType::Data => CacheType::Data,
+                raw_cpuid::CacheType::Instruction => CacheType::Instruction,
+                raw_cpuid::CacheType::Unified => CacheType::Unified,
+                _ => return None,
+            };
+
+            let line_size = params.coherency_line_size() as u64;
+            let partitions = params.physical_line_partitions() as u64;
+            let ways = params.associativity() as u64;
+            let sets = params.sets() as u64;
+
+            Some(CacheInfo {
+                level: params.level(),
+                cache_type,
+                size: ways * partitions * line_size * sets,
+                line_size: line_size as u32,
+                associativity: if params.is_fully_associative() {
+                    0
+                } else {
+                    ways as u32
+                },
+                sets: sets as u32,
+                shared_by: params.max_cores_for_cache() as u32,
+            })
+        })
+        .collect();
+
+    if caches.is_empty() {
+        return None;
+    }
+
+    sort_caches(&mut caches);
+    Some(caches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raw_cpuid::CpuIdResult;
+    use std::fs;
+    use tempfile::tempdir;
+
+    /// Создает каталог `index*` с заданными файлами
+    fn write_index(root: &Path, index: usize, files: &[(&str, &str)]) {
+        let dir = root.join(format!("devices/system/cpu/cpu0/cache/index{}", index));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, content) in files {
+            fs::write(dir.join(name), format!("{}\n", content)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_read_sysfs_caches() {
+        let root = tempdir().unwrap();
+        let common = [("coherency_line_size", "64"), ("number_of_sets", "64")];
+
+        // Порядок каталогов не совпадает с порядком уровней
+        write_index(
+            root.path(),
+            3,
+            &[
+                ("level", "3"),
+                ("type", "Unified"),
+                ("size", "107520K"),
+                ("ways_of_associativity", "15"),
+                ("shared_cpu_list", "0-111"),
+            ],
+        );
+        write_index(
+            root.path(),
+            0,
+            &[
+                ("level", "1"),
+                ("type", "Data"),
+                ("size", "48K"),
+                ("ways_of_associativity", "12"),
+                ("shared_cpu_list", "0,56"),
+                common[0],
+                common[1],
+            ],
+        );
+        write_index(
+            root.path(),
+            1,
+            &[
+                ("level", "1"),
+                ("type", "Instruction"),
+                ("size", "32K"),
+                ("shared_cpu_list", "0,56"),
+            ],
+        );
+        write_index(
+            root.path(),
+            2,
+            &[("level", "2"), ("type", "Unified"), ("size", "2048K")],
+        );
+
+        let caches = read_sysfs_caches(root.path()).unwrap();
+        let names: Vec<String> = caches.iter().map(CacheInfo::name).collect();
+        assert_eq!(names, vec!["L1d", "L1i", "L2", "L3"]);
+
+        assert_eq!(
+            caches[0],
+            CacheInfo {
+                level: 1,
+                cache_type: CacheType::Data,
+                size: 48 * 1024,
+                line_size: 64,
+                associativity: 12,
+                sets: 64,
+                shared_by: 2,
+            }
+        );
+        assert_eq!(caches[2].size, 2 * 1024 * 1024);
+        assert_eq!(caches[2].shared_by, 1);
+        assert_eq!(caches[3].shared_by, 112);
+        assert_eq!(
+            caches[0].to_string(),
+            "L1d: 48 КБ, 12-way, строка 64 Б, общий для 2 логических процессоров"
+        );
+        assert!(caches[3].to_string().starts_with("L3: 105 МБ"));
+
+        // Отсутствующий каталог
+        let empty = tempdir().unwrap();
+        assert_eq!(read_sysfs_caches(empty.path()), None);
+    }
+
+    #[test]
+    fn test_caches_from_cpuid() {
+        // Лист 4 процессора Intel: L1d 48 КБ и L2 2 МБ
+        let reader = |eax: u32, ecx: u32| match (eax, ecx) {
+            (0, _) => CpuIdResult {
+                eax: 0x20,
+                ebx: 0x756e_6547,
+                ecx: 0x6c65_746e,
+                edx: 0x4965_6e69,
+            },
+            (4, 0) => CpuIdResult {
+                eax: 0x0400_4121,
+                ebx: 0x02c0_003f,
+                ecx: 0x3f,
+                edx: 0,
+            },
+            (4, 1) => CpuIdResult {
+                eax: 0x0400_4143,
+                ebx: 0x03c0_003f,
+                ecx: 0x7ff,
+                edx: 0,
+            },
+            _ => CpuIdResult {
+                eax: 0,
+                ebx: 0,
+                ecx: 0,
+                edx: 0,
+            },
+        };
+
+        let caches = caches_from_cpuid(&CpuId::with_cpuid_reader(reader)).unwrap();
+        assert_eq!(caches.len(), 2);
+        assert_eq!(caches[0].name(), "L1d");
+        assert_eq!(caches[0].size, 48 * 1024);
+        assert_eq!(caches[0].associativity, 12);
+        assert_eq!(caches[0].shared_by, 2);
+        assert_eq!(caches[1].name(), "L2");
+        assert_eq!(caches[1].size, 2 * 1024 * 1024);
+        assert_eq!(caches[1].associativity, 16);
+    }
+}
@@ -0,0 +1,269 @@
+//! Запись и воспроизведение результатов инструкции CPUID.
+//!
+//! `CpuIdDump` хранит значения всех листов и подлистов CPUID, а также
+//! регистр XCR0. Дамп сохраняется в формате JSON и реализует
+//! `CpuIdReader`, поэтому на нем работает тот же код определения, что и
+//! на реальном процессоре.
+
+// Запись дампа доступна только на x86_64, воспроизведение - везде
+#![cfg_attr(not(target_arch = "x86_64"), allow(dead_code))]
+
+use crate::error::CpuDetectionError;
+use raw_cpuid::{CpuIdReader, CpuIdResult};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Начала диапазонов листов: базовые, гипервизора и расширенные
+const LEAF_RANGES: [u32; 3] = [0, 0x4000_0000, 0x8000_0000];
+
+/// Максимальное количество листов в одном диапазоне
+const MAX_LEAVES_PER_RANGE: u32 = 0x100;
+
+/// Максимальное количество подлистов одного листа
+const MAX_SUBLEAVES: u32 = 64;
+
+/// Бит Hypervisor в ECX листа 1
+const HYPERVISOR_BIT: u32 = 1 << 31;
+
+/// Результат CPUID для одного листа и подлиста
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CpuIdLeaf {
+    /// Номер листа (EAX)
+    pub leaf: u32,
+    /// Номер подлиста (ECX)
+    pub subleaf: u32,
+    /// Результат в EAX
+    pub eax: u32,
+    /// Результат в EBX
+    pub ebx: u32,
+    /// Результат в ECX
+    pub ecx: u32,
+    /// Результат в EDX
+    pub edx: u32,
+}
+
+impl CpuIdLeaf {
+    fn result(&self) -> CpuIdResult {
+        CpuIdResult {
+            eax: self.eax,
+            ebx: self.ebx,
+            ecx: self.ecx,
+            edx: self.edx,
+        }
+    }
+}
+
+/// Дамп CPUID, используемый вместо инструкции CPUID
+///
+/// Отсутствующие в дампе листы возвращают нули, как и неподдерживаемые
+/// листы реального процессора.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CpuIdDump {
+    /// Значение XCR0 или `None`, если ОС не включила XSAVE
+    pub xcr0: Option<u64>,
+    /// Листы, упорядоченные по номеру листа и подлиста
+    pub leaves: Vec<CpuIdLeaf>,
+}
+
+impl CpuIdDump {
+    /// Записывает все листы и подлисты CPUID, доступные через `reader`
+    ///
+    /// Подлист сохраняется, если он отличается от подлиста 0: листы без
+    /// подлистов возвращают одно и то же значение при любом ECX. Пустые
+    /// подлисты, кроме первого, не сохраняются. Листы гипервизора
+    /// записываются только при установленном бите Hypervisor.
+    pub fn record<R: CpuIdReader>(reader: &R, xcr0: Option<u64>) -> Self {
+        let hypervisor = reader.cpuid1(1).ecx & HYPERVISOR_BIT != 0;
+        let mut leaves = Vec::new();
+
+        for start in LEAF_RANGES {
+            if start == 0x4000_0000 && !hypervisor {
+                continue;
+            }
+
+            let max_leaf = reader.cpuid1(start).eax;
+            if max_leaf < start {
+                continue;
+            }
+
+            let end = max_leaf.min(start + MAX_LEAVES_PER_RANGE - 1);
+            for leaf in start..=end {
+                let first = reader.cpuid2(leaf, 0);
+                leaves.push(Self::entry(leaf, 0, first));
+
+                // Первый отличающийся подлист записывается даже если он
+                // пустой: по нему лист распознается как имеющий подлисты
+                let mut has_subleaves = false;
+                for subleaf in 1..MAX_SUBLEAVES {
+                    let result = reader.cpuid2(leaf, subleaf);
+                    if result != first && (!is_zero(result) || !has_subleaves) {
+                        leaves.push(Self::entry(leaf, subleaf, result));
+                        has_subleaves = true;
+                    }
+                }
+            }
+        }
+
+        CpuIdDump { xcr0, leaves }
+    }
+
+    /// Записывает CPUID текущего процессора
+    #[cfg(target_arch = "x86_64")]
+    pub fn record_native() -> Self {
+        let osxsave = raw_cpuid::CpuId::new()
+            .get_feature_info()
+            .is_some_and(|info| info.has_oxsave());
+        Self::record(&raw_cpuid::CpuIdReaderNative, super::read_xcr0(osxsave))
+    }
+
+    /// Загружает дамп из JSON-файла
+    pub fn load(path: &Path) -> Result<Self, CpuDetectionError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            CpuDetectionError(format!(
+                "Не удалось прочитать дамп CPUID {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        Self::from_json(&content).map_err(|e| {
+            CpuDetectionError(format!("Некорректный дамп CPUID {}: {}", path.display(), e))
+        })
+    }
+
+    /// Разбирает дамп из JSON
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        let mut dump: CpuIdDump = serde_json::from_str(json)?;
+        dump.leaves.sort_by_key(|entry| (entry.leaf, entry.subleaf));
+        Ok(dump)
+    }
+
+    /// Сохраняет дамп в формате JSON
+    pub fn export(&self, writer: impl std::io::Write) -> Result<(), CpuDetectionError> {
+        serde_json::to_writer_pretty(writer, self)
+            .map_err(|e| CpuDetectionError(format!("Не удалось сохранить дамп CPUID: {}", e)))
+    }
+
+    /// Проверяет, записаны ли для листа подлисты, кроме нулевого
+    fn has_subleaves(&self, leaf: u32) -> bool {
+        self.leaves
+            .iter()
+            .any(|entry| entry.leaf == leaf && entry.subleaf != 0)
+    }
+
+    fn entry(leaf: u32, subleaf: u32, result: CpuIdResult) -> CpuIdLeaf {
+        CpuIdLeaf {
+            leaf,
+            subleaf,
+            eax: result.eax,
+            ebx: result.ebx,
+            ecx: result.ecx,
+            edx: result.edx,
+        }
+    }
+}
+
+impl CpuIdReader for CpuIdDump {
+    fn cpuid2(&self, eax: u32, ecx: u32) -> CpuIdResult {
+        let find = |subleaf: u32| {
+            self.leaves
+                .binary_search_by_key(&(eax, subleaf), |entry| (entry.leaf, entry.subleaf))
+                .ok()
+                .map(|index| self.leaves[index].result())
+        };
+
+        // Листы без подлистов возвращают подлист 0 при любом ECX;
+        // незаписанные подлисты остальных листов пустые
+        match find(ecx) {
+            Some(result) => result,
+            None if ecx != 0 && !self.has_subleaves(eax) => find(0).unwrap_or(ZERO),
+            None => ZERO,
+        }
+    }
+}
+
+/// Результат CPUID для неподдерживаемого листа
+const ZERO: CpuIdResult = CpuIdResult {
+    eax: 0,
+    ebx: 0,
+    ecx: 0,
+    edx: 0,
+};
+
+fn is_zero(result: CpuIdResult) -> bool {
+    result == ZERO
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Процессор с листом 4 (подлисты) и листом 1 (без подлистов)
+    fn reader(eax: u32, ecx: u32) -> CpuIdResult {
+        let result = |eax, ebx, ecx, edx| CpuIdResult { eax, ebx, ecx, edx };
+        match (eax, ecx) {
+            (0, _) => result(7, 0x756e_6547, 0x6c65_746e, 0x4965_6e69),
+            (1, _) => result(0x000a_0671, 0, 0x0000_0001, 0x0000_0001),
+            (4, 0) => result(0x121, 0x01c0_003f, 0x3f, 0),
+            (4, 1) => result(0x143, 0x03c0_003f, 0x7ff, 0),
+            (7, 0) => result(0, 0x20, 0, 0),
+            (0x8000_0000, _) => result(0x8000_0001, 0, 0, 0),
+            (0x8000_0001, _) => result(0, 0, 0x21, 0),
+            _ => result(0, 0, 0, 0),
+        }
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let dump = CpuIdDump::record(&reader, Some(0xe7));
+
+        // Лист 1 без подлистов, лист 4 с двумя подлистами, лист 7 с пустым
+        // подлистом 1; листы гипервизора не записываются
+        let recorded: Vec<_> = dump
+            .leaves
+            .iter()
+            .map(|entry| (entry.leaf, entry.subleaf))
+            .collect();
+        assert_eq!(
+            recorded,
+            [
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (3, 0),
+                (4, 0),
+                (4, 1),
+                (5, 0),
+                (6, 0),
+                (7, 0),
+                (7, 1),
+                (0x8000_0000, 0),
+                (0x8000_0001, 0)
+            ]
+        );
+
+        let json = serde_json::to_string(&dump).unwrap();
+        let replay = CpuIdDump::from_json(&json).unwrap();
+        assert_eq!(replay, dump);
+
+        for (eax, ecx) in [
+            (0, 0),
+            (1, 5),
+            (4, 0),
+            (4, 1),
+            (4, 9),
+            (7, 0),
+            (7, 1),
+            (0x4000_0000, 0),
+        ] {
+            assert_eq!(
+                replay.cpuid2(eax, ecx),
+                reader(eax, ecx),
+                "{:#x}.{}",
+                eax,
+                ecx
+            );
+        }
+        assert_eq!(replay.xcr0, Some(0xe7));
+    }
+}
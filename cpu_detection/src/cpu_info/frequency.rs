@@ -0,0 +1,189 @@
+//! Определение частот процессора.
+//!
+//! На Linux частоты читаются из `/sys/devices/system/cpu/cpu*/cpufreq`,
+//! недостающие значения берутся из листа CPUID 0x16.
+
+use super::sysfs;
+use raw_cpuid::{CpuId, CpuIdReader};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// Частоты процессора в МГц
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CpuFrequency {
+    /// Базовая (номинальная) частота
+    pub base_mhz: Option<u32>,
+    /// Максимальная частота (с учетом Turbo Boost/Precision Boost)
+    pub max_mhz: Option<u32>,
+    /// Частота шины (опорная частота)
+    pub bus_mhz: Option<u32>,
+}
+
+impl CpuFrequency {
+    /// Дополняет неизвестные частоты значениями из другого источника
+    pub fn or(self, other: CpuFrequency) -> CpuFrequency {
+        CpuFrequency {
+            base_mhz: self.base_mhz.or(other.base_mhz),
+            max_mhz: self.max_mhz.or(other.max_mhz),
+            bus_mhz: self.bus_mhz.or(other.bus_mhz),
+        }
+    }
+}
+
+impl fmt::Display for CpuFrequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = |mhz: Option<u32>| {
+            mhz.map_or("неизвестна".to_string(), |mhz| {
+                format!("{} МГц", mhz)
+            })
+        };
+        write!(
+            f,
+            "базовая {}, максимальная {}, шины {}",
+            format(self.base_mhz),
+            format(self.max_mhz),
+            format(self.bus_mhz)
+        )
+    }
+}
+
+/// Определяет частоты процессора текущей системы
+pub fn detect_frequency() -> CpuFrequency {
+    let frequency = read_sysfs_frequency(Path::new(sysfs::SYSFS_ROOT));
+
+    #[cfg(target_arch = "x86_64")]
+    let frequency = frequency.or(frequency_from_cpuid(&CpuId::new()));
+
+    frequency
+}
+
+/// Читает частоты из sysfs
+///
+/// `root` - корень sysfs (обычно `/sys`). Базовая частота берется из
+/// `base_frequency` процессора cpu0 (драйвер intel_pstate), максимальная -
+/// наибольшая из `cpuinfo_max_freq` всех процессоров, так как на
+/// big.LITTLE процессор cpu0 обычно относится к младшим ядрам.
+pub fn read_sysfs_frequency(root: &Path) -> CpuFrequency {
+    let cpu_dir = root.join("devices/system/cpu");
+    let khz_to_mhz = |khz: u64| (khz / 1000) as u32;
+
+    let base_mhz = sysfs::read_number(&cpu_dir.join("cpu0/cpufreq/base_frequency")).map(khz_to_mhz);
+
+    let max_mhz = std::fs::read_dir(&cpu_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.strip_prefix("cpu")
+                .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .filter_map(|entry| sysfs::read_number(&entry.path().join("cpufreq/cpuinfo_max_freq")))
+        .max()
+        .map(khz_to_mhz);
+
+    CpuFrequency {
+        base_mhz,
+        max_mhz,
+        bus_mhz: None,
+    }
+}
+
+/// Определяет частоты по листу CPUID 0x16
+///
+/// Нулевые значения (лист не заполнен, что типично для виртуальных машин
+/// и процессоров AMD) считаются неизвестными.
+pub fn frequency_from_cpuid<R: CpuIdReader>(cpuid: &CpuId<R>) -> CpuFrequency {
+    let Some(info) = cpuid.get_processor_frequency_info() else {
+        return CpuFrequency::default();
+    };
+
+    let non_zero = |mhz: u16| (mhz != 0).then_some(mhz as u32);
+    CpuFrequency {
+        base_mhz: non_zero(info.processor_base_frequency()),
+        max_mhz: non_zero(info.processor_max_frequency()),
+        bus_mhz: non_zero(info.bus_frequency()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raw_cpuid::CpuIdResult;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_read_sysfs_frequency() {
+        let root = tempdir().unwrap();
+        let cpu_dir = root.path().join("devices/system/cpu");
+        for (cpu, max_khz) in [(0, "1800000"), (1, "1800000"), (4, "2400000")] {
+            let dir = cpu_dir.join(format!("cpu{}/cpufreq", cpu));
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("cpuinfo_max_freq"), format!("{}\n", max_khz)).unwrap();
+        }
+        fs::create_dir_all(cpu_dir.join("cpufreq")).unwrap();
+
+        let frequency = read_sysfs_frequency(root.path());
+        assert_eq!(frequency.base_mhz, None);
+        assert_eq!(frequency.max_mhz, Some(2400));
+
+        fs::write(cpu_dir.join("cpu0/cpufreq/base_frequency"), "2100000\n").unwrap();
+        assert_eq!(read_sysfs_frequency(root.path()).base_mhz, Some(2100));
+
+        // Без cpufreq частоты неизвестны
+        let empty = tempdir().unwrap();
+        assert_eq!(read_sysfs_frequency(empty.path()), CpuFrequency::default());
+    }
+
+    #[test]
+    fn test_frequency_from_cpuid() {
+        let reader = |eax: u32, _ecx: u32| match eax {
+            0 => CpuIdResult {
+                eax: 0x16,
+                ebx: 0x756e_6547,
+                ecx: 0x6c65_746e,
+                edx: 0x4965_6e69,
+            },
+            0x16 => CpuIdResult {
+                eax: 2100,
+                ebx: 3900,
+                ecx: 100,
+                edx: 0,
+            },
+            _ => CpuIdResult {
+                eax: 0,
+                ebx: 0,
+                ecx: 0,
+                edx: 0,
+            },
+        };
+
+        let from_cpuid = frequency_from_cpuid(&CpuId::with_cpuid_reader(reader));
+        assert_eq!(
+            from_cpuid,
+            CpuFrequency {
+                base_mhz: Some(2100),
+                max_mhz: Some(3900),
+                bus_mhz: Some(100),
+            }
+        );
+
+        // Значения sysfs имеют приоритет над CPUID
+        let from_sysfs = CpuFrequency {
+            base_mhz: None,
+            max_mhz: Some(4200),
+            bus_mhz: None,
+        };
+        let frequency = from_sysfs.or(from_cpuid);
+        assert_eq!(frequency.max_mhz, Some(4200));
+        assert_eq!(frequency.base_mhz, Some(2100));
+        assert_eq!(
+            frequency.to_string(),
+            "базовая 2100 МГц, максимальная 4200 МГц, шины 100 МГц"
+        );
+    }
+}
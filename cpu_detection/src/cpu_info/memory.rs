@@ -0,0 +1,356 @@
+//! Определение параметров подсистемы памяти.
+//!
+//! Объем памяти читается из `/proc/meminfo`, пулы hugetlb - из
+//! `/sys/kernel/mm/hugepages/hugepages-<размер>kB`, режимы прозрачных
+//! больших страниц (THP) - из `/sys/kernel/mm/transparent_hugepage`.
+
+use super::sysfs;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// Корень файловой системы procfs
+const PROCFS_ROOT: &str = "/proc";
+
+/// Параметры подсистемы памяти
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryInfo {
+    /// Общий объем оперативной памяти в байтах (`MemTotal`)
+    pub total_bytes: Option<u64>,
+    /// Доступный для новых процессов объем памяти в байтах (`MemAvailable`)
+    pub available_bytes: Option<u64>,
+    /// Пулы больших страниц hugetlb по размерам страниц (по возрастанию)
+    pub huge_pages: Vec<HugePagePool>,
+    /// Настройки прозрачных больших страниц, если ядро их поддерживает
+    pub transparent_hugepage: Option<TransparentHugePages>,
+}
+
+/// Пул больших страниц hugetlb одного размера
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HugePagePool {
+    /// Размер страницы в байтах
+    pub page_size: u64,
+    /// Количество выделенных страниц (`nr_hugepages`)
+    pub total: u64,
+    /// Количество свободных страниц (`free_hugepages`)
+    pub free: u64,
+}
+
+/// Настройки прозрачных больших страниц
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransparentHugePages {
+    /// Режим выделения (`enabled`)
+    pub enabled: ThpMode,
+    /// Режим дефрагментации (`defrag`)
+    pub defrag: ThpDefrag,
+    /// Размер прозрачной большой страницы в байтах (`hpage_pmd_size`)
+    pub page_size: Option<u64>,
+}
+
+/// Режим выделения прозрачных больших страниц
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThpMode {
+    /// Большие страницы выделяются для всех анонимных отображений
+    Always,
+    /// Только для областей, помеченных `madvise(MADV_HUGEPAGE)`
+    Madvise,
+    /// Прозрачные большие страницы отключены
+    Never,
+}
+
+/// Режим дефрагментации при выделении прозрачных больших страниц
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThpDefrag {
+    /// Синхронное уплотнение памяти при каждом выделении
+    #[serde(rename = "always")]
+    Always,
+    /// Фоновое уплотнение через kcompactd
+    #[serde(rename = "defer")]
+    Defer,
+    /// Синхронное для областей `madvise`, фоновое для остальных
+    #[serde(rename = "defer+madvise")]
+    DeferMadvise,
+    /// Синхронное уплотнение только для областей `madvise`
+    #[serde(rename = "madvise")]
+    Madvise,
+    /// Без уплотнения
+    #[serde(rename = "never")]
+    Never,
+}
+
+impl ThpMode {
+    /// Имя режима в `/sys/kernel/mm/transparent_hugepage/enabled`
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThpMode::Always => "always",
+            ThpMode::Madvise => "madvise",
+            ThpMode::Never => "never",
+        }
+    }
+
+    /// Определяет режим по имени
+    pub fn from_name(name: &str) -> Option<Self> {
+        [ThpMode::Always, ThpMode::Madvise, ThpMode::Never]
+            .into_iter()
+            .find(|mode| mode.name() == name)
+    }
+}
+
+impl ThpDefrag {
+    /// Имя режима в `/sys/kernel/mm/transparent_hugepage/defrag`
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThpDefrag::Always => "always",
+            ThpDefrag::Defer => "defer",
+            ThpDefrag::DeferMadvise => "defer+madvise",
+            ThpDefrag::Madvise => "madvise",
+            ThpDefrag::Never => "never",
+        }
+    }
+
+    /// Определяет режим по имени
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            ThpDefrag::Always,
+            ThpDefrag::Defer,
+            ThpDefrag::DeferMadvise,
+            ThpDefrag::Madvise,
+            ThpDefrag::Never,
+        ]
+        .into_iter()
+        .find(|mode| mode.name() == name)
+    }
+}
+
+impl fmt::Display for MemoryInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = |bytes: Option<u64>| {
+            bytes.map_or("неизвестно".to_string(), |bytes| {
+                format!("{} МБ", bytes >> 20)
+            })
+        };
+        write!(
+            f,
+            "всего {}, доступно {}",
+            format(self.total_bytes),
+            format(self.available_bytes)
+        )?;
+
+        for pool in &self.huge_pages {
+            write!(
+                f,
+                ", hugetlb {} КБ: {}/{}",
+                pool.page_size >> 10,
+                pool.free,
+                pool.total
+            )?;
+        }
+
+        match &self.transparent_hugepage {
+            Some(thp) => write!(
+                f,
+                ", THP: {} (defrag: {})",
+                thp.enabled.name(),
+                thp.defrag.name()
+            ),
+            None => write!(f, ", THP: нет"),
+        }
+    }
+}
+
+/// Определяет параметры подсистемы памяти текущей системы
+pub fn detect_memory() -> MemoryInfo {
+    read_memory_info(Path::new(PROCFS_ROOT), Path::new(sysfs::SYSFS_ROOT))
+}
+
+/// Читает параметры подсистемы памяти
+///
+/// `proc_root` - корень procfs (обычно `/proc`), `sys_root` - корень sysfs
+/// (обычно `/sys`).
+pub fn read_memory_info(proc_root: &Path, sys_root: &Path) -> MemoryInfo {
+    let meminfo = std::fs::read_to_string(proc_root.join("meminfo")).unwrap_or_default();
+    let mm_dir = sys_root.join("kernel/mm");
+
+    MemoryInfo {
+        total_bytes: meminfo_field(&meminfo, "MemTotal"),
+        available_bytes: meminfo_field(&meminfo, "MemAvailable"),
+        huge_pages: read_hugetlb_pools(&mm_dir.join("hugepages")),
+        transparent_hugepage: read_transparent_hugepage(&mm_dir.join("transparent_hugepage")),
+    }
+}
+
+/// Возвращает поле `/proc/meminfo` в байтах (формат: `MemTotal: 16384 kB`)
+fn meminfo_field(meminfo: &str, name: &str) -> Option<u64> {
+    meminfo.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        if key.trim() != name {
+            return None;
+        }
+
+        let mut fields = value.split_whitespace();
+        let number: u64 = fields.next()?.parse().ok()?;
+        match fields.next() {
+            Some("kB") => Some(number << 10),
+            None => Some(number),
+            Some(_) => None,
+        }
+    })
+}
+
+/// Читает пулы hugetlb из каталогов `hugepages-<размер>kB`
+fn read_hugetlb_pools(dir: &Path) -> Vec<HugePagePool> {
+    let mut pools: Vec<HugePagePool> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let size_kb: u64 = name
+                .to_str()?
+                .strip_prefix("hugepages-")?
+                .strip_suffix("kB")?
+                .parse()
+                .ok()?;
+
+            let path = entry.path();
+            Some(HugePagePool {
+                page_size: size_kb << 10,
+                total: sysfs::read_number(&path.join("nr_hugepages")).unwrap_or(0),
+                free: sysfs::read_number(&path.join("free_hugepages")).unwrap_or(0),
+            })
+        })
+        .collect();
+
+    pools.sort_by_key(|pool| pool.page_size);
+    pools
+}
+
+/// Читает настройки прозрачных больших страниц
+fn read_transparent_hugepage(dir: &Path) -> Option<TransparentHugePages> {
+    let enabled = sysfs::read_trimmed(&dir.join("enabled"))?;
+    let defrag = sysfs::read_trimmed(&dir.join("defrag"))
+        .and_then(|defrag| selected_mode(&defrag).and_then(ThpDefrag::from_name))
+        .unwrap_or(ThpDefrag::Madvise);
+
+    Some(TransparentHugePages {
+        enabled: selected_mode(&enabled).and_then(ThpMode::from_name)?,
+        defrag,
+        page_size: sysfs::read_number(&dir.join("hpage_pmd_size")),
+    })
+}
+
+/// Возвращает выбранный режим из списка вида `always [madvise] never`
+fn selected_mode(modes: &str) -> Option<&str> {
+    modes
+        .split_whitespace()
+        .find_map(|mode| mode.strip_prefix('[')?.strip_suffix(']'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    /// Создает файл с содержимым, включая родительские каталоги
+    fn write_file(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, format!("{}\n", content)).unwrap();
+    }
+
+    #[test]
+    fn test_read_memory_info() {
+        let root = tempdir().unwrap();
+        let proc_root = root.path().join("proc");
+        let sys_root = root.path().join("sys");
+
+        write_file(
+            &proc_root,
+            "meminfo",
+            "MemTotal:       65747808 kB\n\
+             MemFree:        12345678 kB\n\
+             MemAvailable:   50331648 kB\n\
+             HugePages_Total:       8",
+        );
+        for (size, total, free) in [("1048576", "2", "1"), ("2048", "512", "500")] {
+            let dir = format!("kernel/mm/hugepages/hugepages-{}kB", size);
+            write_file(&sys_root, &format!("{}/nr_hugepages", dir), total);
+            write_file(&sys_root, &format!("{}/free_hugepages", dir), free);
+        }
+        write_file(
+            &sys_root,
+            "kernel/mm/transparent_hugepage/enabled",
+            "always [madvise] never",
+        );
+        write_file(
+            &sys_root,
+            "kernel/mm/transparent_hugepage/defrag",
+            "always defer [defer+madvise] madvise never",
+        );
+        write_file(
+            &sys_root,
+            "kernel/mm/transparent_hugepage/hpage_pmd_size",
+            "2097152",
+        );
+
+        let memory = read_memory_info(&proc_root, &sys_root);
+        assert_eq!(memory.total_bytes, Some(65747808 << 10));
+        assert_eq!(memory.available_bytes, Some(48 << 30));
+        assert_eq!(
+            memory.huge_pages,
+            [
+                HugePagePool {
+                    page_size: 2 << 20,
+                    total: 512,
+                    free: 500
+                },
+                HugePagePool {
+                    page_size: 1 << 30,
+                    total: 2,
+                    free: 1
+                },
+            ]
+        );
+        assert_eq!(
+            memory.transparent_hugepage,
+            Some(TransparentHugePages {
+                enabled: ThpMode::Madvise,
+                defrag: ThpDefrag::DeferMadvise,
+                page_size: Some(2 << 20),
+            })
+        );
+        assert_eq!(
+            memory.to_string(),
+            "всего 64206 МБ, доступно 49152 МБ, hugetlb 2048 КБ: 500/512, \
+             hugetlb 1048576 КБ: 1/2, THP: madvise (defrag: defer+madvise)"
+        );
+
+        // Профиль сохраняет имена режимов ядра
+        let json = serde_json::to_string(&memory).unwrap();
+        assert!(json.contains(r#""defrag":"defer+madvise""#));
+        assert_eq!(serde_json::from_str::<MemoryInfo>(&json).unwrap(), memory);
+    }
+
+    #[test]
+    fn test_read_memory_info_missing() {
+        // Ядро без THP и hugetlb, meminfo недоступен
+        let root = tempdir().unwrap();
+        let memory = read_memory_info(&root.path().join("proc"), &root.path().join("sys"));
+        assert_eq!(memory, MemoryInfo::default());
+        assert_eq!(
+            memory.to_string(),
+            "всего неизвестно, доступно неизвестно, THP: нет"
+        );
+
+        assert_eq!(
+            meminfo_field("MemTotal: 1024 kB", "MemTotal"),
+            Some(1 << 20)
+        );
+        assert_eq!(meminfo_field("MemTotal: 1024 MB", "MemTotal"), None);
+        assert_eq!(selected_mode("[always] madvise never"), Some("always"));
+        assert_eq!(selected_mode("always madvise never"), None);
+    }
+}
@@ -0,0 +1,234 @@
+//! Определение микроархитектуры процессора.
+//!
+//! На x86_64 микроархитектура определяется по производителю и сигнатуре
+//! (семейство, модель, степпинг) из листа CPUID 1, на aarch64 - по кодам
+//! производителя и модели ядра (MIDR), на riscv64 - по полю `uarch`, на
+//! powerpc64 и s390x - по названию процессора и типу машины.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Сигнатура процессора x86 из листа CPUID 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CpuSignature {
+    /// Семейство (с учетом расширенного семейства)
+    pub family: u32,
+    /// Модель (с учетом расширенной модели)
+    pub model: u32,
+    /// Степпинг
+    pub stepping: u32,
+}
+
+impl fmt::Display for CpuSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "семейство {:#x}, модель {:#x}, степпинг {}",
+            self.family, self.model, self.stepping
+        )
+    }
+}
+
+/// Микроархитектура процессора
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Microarchitecture {
+    /// Машинное имя для политик выбора и отчетов (`zen3`, `skylake-sp`,
+    /// `neoverse-v1`)
+    pub id: String,
+    /// Название для вывода пользователю (`Zen 3`, `Skylake-SP`)
+    pub name: String,
+}
+
+impl Microarchitecture {
+    /// Создает описание микроархитектуры
+    fn new(id: &str, name: &str) -> Self {
+        Microarchitecture {
+            id: id.to_string(),
+            name: name.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Microarchitecture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.id)
+    }
+}
+
+/// Определяет микроархитектуру x86 по производителю и сигнатуре
+pub fn identify_x86(vendor: &str, signature: CpuSignature) -> Option<Microarchitecture> {
+    let (id, name) = match vendor {
+        "GenuineIntel" => identify_intel(signature)?,
+        "AuthenticAMD" | "HygonGenuine" => identify_amd(signature)?,
+        _ => return None,
+    };
+    Some(Microarchitecture::new(id, name))
+}
+
+/// Микроархитектуры Intel семейства 6
+fn identify_intel(signature: CpuSignature) -> Option<(&'static str, &'static str)> {
+    if signature.family != 6 {
+        return None;
+    }
+
+    let microarch = match signature.model {
+        0x1c | 0x26 | 0x27 | 0x35 | 0x36 => ("bonnell", "Bonnell"),
+        0x1a | 0x1e | 0x1f | 0x2e => ("nehalem", "Nehalem"),
+        0x25 | 0x2c | 0x2f => ("westmere", "Westmere"),
+        0x2a | 0x2d => ("sandybridge", "Sandy Bridge"),
+        0x3a | 0x3e => ("ivybridge", "Ivy Bridge"),
+        0x37 | 0x4a | 0x4c | 0x4d | 0x5a | 0x5d => ("silvermont", "Silvermont"),
+        0x3c | 0x3f | 0x45 | 0x46 => ("haswell", "Haswell"),
+        0x3d | 0x47 | 0x4f | 0x56 => ("broadwell", "Broadwell"),
+        0x4e | 0x5e => ("skylake", "Skylake"),
+        // Skylake-SP, Cascade Lake и Cooper Lake различаются степпингом
+        0x55 => match signature.stepping {
+            0..=4 => ("skylake-sp", "Skylake-SP"),
+            5..=7 => ("cascadelake", "Cascade Lake"),
+            _ => ("cooperlake", "Cooper Lake"),
+        },
+        0x57 => ("knl", "Knights Landing"),
+        0x85 => ("knm", "Knights Mill"),
+        0x5c | 0x5f => ("goldmont", "Goldmont"),
+        0x7a => ("goldmont-plus", "Goldmont Plus"),
+        0x86 | 0x96 | 0x9c => ("tremont", "Tremont"),
+        0x8e | 0x9e => ("kabylake", "Kaby Lake"),
+        0xa5 | 0xa6 => ("cometlake", "Comet Lake"),
+        0x66 => ("cannonlake", "Cannon Lake"),
+        0x7d | 0x7e => ("icelake", "Ice Lake"),
+        0x6a | 0x6c => ("icelake-sp", "Ice Lake-SP"),
+        0x8c | 0x8d => ("tigerlake", "Tiger Lake"),
+        0xa7 => ("rocketlake", "Rocket Lake"),
+        0x97 | 0x9a | 0xbe => ("alderlake", "Alder Lake"),
+        0xb7 | 0xba | 0xbf => ("raptorlake", "Raptor Lake"),
+        0xaa | 0xac => ("meteorlake", "Meteor Lake"),
+        0xbd => ("lunarlake", "Lunar Lake"),
+        0xc5 | 0xc6 => ("arrowlake", "Arrow Lake"),
+        0x8f => ("sapphirerapids", "Sapphire Rapids"),
+        0xcf => ("emeraldrapids", "Emerald Rapids"),
+        0xad | 0xae => ("graniterapids", "Granite Rapids"),
+        0xaf => ("sierraforest", "Sierra Forest"),
+        _ => return None,
+    };
+    Some(microarch)
+}
+
+/// Микроархитектуры AMD и Hygon
+fn identify_amd(signature: CpuSignature) -> Option<(&'static str, &'static str)> {
+    let microarch = match (signature.family, signature.model) {
+        (0x10, _) => ("k10", "K10"),
+        (0x14, _) => ("bobcat", "Bobcat"),
+        (0x15, 0x00..=0x0f) => ("bdver1", "Bulldozer"),
+        (0x15, 0x10..=0x1f) => ("bdver2", "Piledriver"),
+        (0x15, 0x30..=0x3f) => ("bdver3", "Steamroller"),
+        (0x15, 0x60..=0x7f) => ("bdver4", "Excavator"),
+        (0x16, _) => ("jaguar", "Jaguar"),
+        (0x17, 0x08 | 0x18) => ("zen+", "Zen+"),
+        (0x17, 0x00..=0x2f) => ("zen", "Zen"),
+        (0x17, _) => ("zen2", "Zen 2"),
+        // Hygon Dhyana основан на Zen
+        (0x18, _) => ("zen", "Zen"),
+        (0x19, 0x10..=0x1f | 0x60..=0x7f | 0xa0..=0xaf) => ("zen4", "Zen 4"),
+        (0x19, _) => ("zen3", "Zen 3"),
+        (0x1a, _) => ("zen5", "Zen 5"),
+        _ => return None,
+    };
+    Some(microarch)
+}
+
+/// Определяет микроархитектуру ядра aarch64 по его названию
+///
+/// Машинное имя совпадает с названием ядра в нижнем регистре
+/// (`Neoverse-V1` - `neoverse-v1`).
+pub fn identify_aarch64(part_name: &str) -> Microarchitecture {
+    Microarchitecture::new(&part_name.to_lowercase(), part_name)
+}
+
+/// Определяет микроархитектуру процессора POWER по названию (`POWER9`)
+pub fn identify_power(cpu_name: &str) -> Microarchitecture {
+    Microarchitecture::new(&cpu_name.to_lowercase(), cpu_name)
+}
+
+/// Определяет поколение IBM Z по типу машины из /proc/cpuinfo
+pub fn identify_s390x(machine_type: u32) -> Option<Microarchitecture> {
+    let (id, name) = match machine_type {
+        2964 | 2965 => ("z13", "IBM z13"),
+        3906 | 3907 => ("z14", "IBM z14"),
+        8561 | 8562 => ("z15", "IBM z15"),
+        3931 | 3932 => ("z16", "IBM z16"),
+        9175 => ("z17", "IBM z17"),
+        _ => return None,
+    };
+    Some(Microarchitecture::new(id, name))
+}
+
+/// Определяет микроархитектуру ядра riscv64 по полю `uarch`
+///
+/// Машинное имя - часть после производителя (`sifive,u74-mc` - `u74-mc`).
+pub fn identify_riscv(uarch: &str) -> Microarchitecture {
+    let id = uarch.split_once(',').map_or(uarch, |(_, core)| core);
+    Microarchitecture::new(id, uarch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Возвращает машинное имя микроархитектуры для сигнатуры
+    fn id(vendor: &str, family: u32, model: u32, stepping: u32) -> Option<String> {
+        let signature = CpuSignature {
+            family,
+            model,
+            stepping,
+        };
+        identify_x86(vendor, signature).map(|microarch| microarch.id)
+    }
+
+    #[test]
+    fn test_identify_intel() {
+        let intel = "GenuineIntel";
+        assert_eq!(id(intel, 6, 0x3f, 2).as_deref(), Some("haswell"));
+        assert_eq!(id(intel, 6, 0x55, 4).as_deref(), Some("skylake-sp"));
+        assert_eq!(id(intel, 6, 0x55, 7).as_deref(), Some("cascadelake"));
+        assert_eq!(id(intel, 6, 0x55, 11).as_deref(), Some("cooperlake"));
+        assert_eq!(id(intel, 6, 0x6a, 6).as_deref(), Some("icelake-sp"));
+        assert_eq!(id(intel, 6, 0x97, 2).as_deref(), Some("alderlake"));
+        assert_eq!(id(intel, 6, 0x8f, 8).as_deref(), Some("sapphirerapids"));
+        assert_eq!(id(intel, 6, 0x01, 0), None);
+        assert_eq!(id(intel, 0xf, 0x06, 5), None);
+
+        let signature = CpuSignature {
+            family: 6,
+            model: 0x8f,
+            stepping: 8,
+        };
+        assert_eq!(
+            identify_x86(intel, signature).unwrap().to_string(),
+            "Sapphire Rapids (sapphirerapids)"
+        );
+    }
+
+    #[test]
+    fn test_identify_amd() {
+        let amd = "AuthenticAMD";
+        assert_eq!(id(amd, 0x15, 0x02, 0).as_deref(), Some("bdver1"));
+        assert_eq!(id(amd, 0x17, 0x01, 2).as_deref(), Some("zen"));
+        assert_eq!(id(amd, 0x17, 0x08, 2).as_deref(), Some("zen+"));
+        assert_eq!(id(amd, 0x17, 0x31, 0).as_deref(), Some("zen2"));
+        assert_eq!(id(amd, 0x17, 0x71, 0).as_deref(), Some("zen2"));
+        assert_eq!(id(amd, 0x19, 0x01, 1).as_deref(), Some("zen3"));
+        assert_eq!(id(amd, 0x19, 0x21, 0).as_deref(), Some("zen3"));
+        assert_eq!(id(amd, 0x19, 0x11, 1).as_deref(), Some("zen4"));
+        assert_eq!(id(amd, 0x19, 0x61, 2).as_deref(), Some("zen4"));
+        assert_eq!(id(amd, 0x1a, 0x44, 0).as_deref(), Some("zen5"));
+        assert_eq!(id("HygonGenuine", 0x18, 0x01, 1).as_deref(), Some("zen"));
+        assert_eq!(id("VIA VIA VIA ", 6, 0x0f, 0), None);
+    }
+
+    #[test]
+    fn test_identify_aarch64() {
+        let microarch = identify_aarch64("Neoverse-V1");
+        assert_eq!(microarch.id, "neoverse-v1");
+        assert_eq!(microarch.name, "Neoverse-V1");
+    }
+}
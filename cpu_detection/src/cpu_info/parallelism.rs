@@ -0,0 +1,275 @@
+//! Определение доступного процессу вычислительного ресурса.
+//!
+//! Учитывает привязку процесса к процессорам (`sched_getaffinity`) и
+//! ограничения cgroup: `cpu.max` в cgroup v2 и
+//! `cpu.cfs_quota_us`/`cpu.cfs_period_us` в cgroup v1.
+
+use super::sysfs;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Корень файловой системы cgroup
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Доступный процессу вычислительный ресурс
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Parallelism {
+    /// Количество процессоров, к которым привязан процесс
+    pub affinity_cpus: Option<u32>,
+    /// Квота процессорного времени cgroup в процессорах (например, 1.5)
+    pub cgroup_cpu_quota: Option<f64>,
+    /// Количество процессоров, которое процесс может эффективно занять
+    pub effective_cpus: u32,
+}
+
+impl Parallelism {
+    /// Вычисляет эффективное количество процессоров
+    ///
+    /// Дробная квота округляется вверх: квота 1.5 позволяет занять два
+    /// процессора на 75%.
+    pub fn new(
+        logical_cpus: u32,
+        affinity_cpus: Option<u32>,
+        cgroup_cpu_quota: Option<f64>,
+    ) -> Self {
+        let mut effective_cpus = affinity_cpus
+            .unwrap_or(logical_cpus)
+            .min(logical_cpus.max(1));
+        if let Some(quota) = cgroup_cpu_quota {
+            effective_cpus = effective_cpus.min(quota.ceil() as u32);
+        }
+
+        Parallelism {
+            affinity_cpus,
+            cgroup_cpu_quota,
+            effective_cpus: effective_cpus.max(1),
+        }
+    }
+}
+
+impl fmt::Display for Parallelism {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.effective_cpus)?;
+        if let Some(affinity) = self.affinity_cpus {
+            write!(f, " (привязка: {}", affinity)?;
+        } else {
+            write!(f, " (привязка: неизвестна")?;
+        }
+        match self.cgroup_cpu_quota {
+            Some(quota) => write!(f, ", квота cgroup: {})", quota),
+            None => write!(f, ", квота cgroup: нет)"),
+        }
+    }
+}
+
+/// Определяет доступный процессу вычислительный ресурс
+pub fn detect_parallelism(logical_cpus: u32) -> Parallelism {
+    let cgroup_cpu_quota = std::fs::read_to_string("/proc/self/cgroup")
+        .ok()
+        .and_then(|self_cgroup| read_cgroup_cpu_quota(Path::new(CGROUP_ROOT), &self_cgroup));
+
+    Parallelism::new(logical_cpus, read_affinity_cpus(), cgroup_cpu_quota)
+}
+
+/// Возвращает количество процессоров, к которым привязан процесс
+#[cfg(target_os = "linux")]
+fn read_affinity_cpus() -> Option<u32> {
+    // SAFETY: cpu_set_t инициализируется нулями и заполняется ядром
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return None;
+        }
+        Some(libc::CPU_COUNT(&set) as u32)
+    }
+}
+
+/// Возвращает количество процессоров, к которым привязан процесс
+#[cfg(not(target_os = "linux"))]
+fn read_affinity_cpus() -> Option<u32> {
+    None
+}
+
+/// Читает квоту процессорного времени cgroup в процессорах
+///
+/// `cgroup_root` - точка монтирования cgroup (обычно `/sys/fs/cgroup`),
+/// `self_cgroup` - содержимое `/proc/self/cgroup`. Учитываются ограничения
+/// всех родительских групп; возвращается наименьшее из них.
+pub fn read_cgroup_cpu_quota(cgroup_root: &Path, self_cgroup: &str) -> Option<f64> {
+    let mut quotas = Vec::new();
+
+    for line in self_cgroup.lines() {
+        // Формат строки: <id>:<контроллеры>:<путь>
+        let mut parts = line.splitn(3, ':');
+        let (Some(id), Some(controllers), Some(path)) = (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+
+        if id == "0" && controllers.is_empty() {
+            // cgroup v2: единая иерархия
+            quotas.extend(
+                cgroup_dirs(cgroup_root, path)
+                    .iter()
+                    .filter_map(|dir| read_cgroup_v2_quota(dir)),
+            );
+        } else if controllers.split(',').any(|c| c == "cpu") {
+            // cgroup v1: иерархия контроллера cpu
+            let mount_dirs = ["cpu", "cpu,cpuacct", "cpuacct,cpu", controllers];
+            if let Some(mount) = mount_dirs
+                .iter()
+                .map(|dir| cgroup_root.join(dir))
+                .find(|dir| dir.is_dir())
+            {
+                quotas.extend(
+                    cgroup_dirs(&mount, path)
+                        .iter()
+                        .filter_map(|dir| read_cgroup_v1_quota(dir)),
+                );
+            }
+        }
+    }
+
+    quotas.into_iter().reduce(f64::min)
+}
+
+/// Возвращает каталоги группы и всех ее родителей внутри точки монтирования
+///
+/// В контейнере с отдельным пространством имен cgroup путь из
+/// `/proc/self/cgroup` равен `/`, а ограничения контейнера находятся в
+/// корне точки монтирования.
+fn cgroup_dirs(mount: &Path, path: &str) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let mut current = PathBuf::from(path.trim_start_matches('/'));
+
+    loop {
+        let dir = mount.join(&current);
+        if dir.is_dir() {
+            dirs.push(dir);
+        }
+        if !current.pop() {
+            break;
+        }
+    }
+
+    dirs
+}
+
+/// Читает квоту из `cpu.max` (cgroup v2): `<квота> <период>` или `max <период>`
+fn read_cgroup_v2_quota(dir: &Path) -> Option<f64> {
+    let content = sysfs::read_trimmed(&dir.join("cpu.max"))?;
+    let mut fields = content.split_whitespace();
+    let quota = fields.next()?;
+    let period: f64 = fields.next()?.parse().ok()?;
+
+    if quota == "max" {
+        return None;
+    }
+    cpu_quota(quota.parse().ok()?, period)
+}
+
+/// Читает квоту из `cpu.cfs_quota_us` и `cpu.cfs_period_us` (cgroup v1)
+fn read_cgroup_v1_quota(dir: &Path) -> Option<f64> {
+    let quota: f64 = sysfs::read_number(&dir.join("cpu.cfs_quota_us"))?;
+    let period: f64 = sysfs::read_number(&dir.join("cpu.cfs_period_us"))?;
+    cpu_quota(quota, period)
+}
+
+/// Переводит квоту и период в количество процессоров; отрицательная
+/// квота (-1 в cgroup v1) означает отсутствие ограничения
+fn cpu_quota(quota: f64, period: f64) -> Option<f64> {
+    (quota > 0.0 && period > 0.0).then(|| quota / period)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    /// Создает файл с содержимым, включая родительские каталоги
+    fn write_file(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, format!("{}\n", content)).unwrap();
+    }
+
+    #[test]
+    fn test_cgroup_v2_quota() {
+        let root = tempdir().unwrap();
+        let self_cgroup = "0::/kubepods/pod1/container\n";
+
+        // Без ограничений
+        write_file(root.path(), "cpu.max", "max 100000");
+        write_file(root.path(), "kubepods/pod1/container/cpu.max", "max 100000");
+        assert_eq!(read_cgroup_cpu_quota(root.path(), self_cgroup), None);
+
+        // Ограничение на уровне пода действует на контейнер
+        write_file(root.path(), "kubepods/pod1/cpu.max", "250000 100000");
+        assert_eq!(read_cgroup_cpu_quota(root.path(), self_cgroup), Some(2.5));
+
+        // Берется наименьшее ограничение
+        write_file(
+            root.path(),
+            "kubepods/pod1/container/cpu.max",
+            "50000 100000",
+        );
+        assert_eq!(read_cgroup_cpu_quota(root.path(), self_cgroup), Some(0.5));
+
+        // Пространство имен cgroup: ограничение в корне
+        let root = tempdir().unwrap();
+        write_file(root.path(), "cpu.max", "400000 100000");
+        assert_eq!(read_cgroup_cpu_quota(root.path(), "0::/\n"), Some(4.0));
+    }
+
+    #[test]
+    fn test_cgroup_v1_quota() {
+        let root = tempdir().unwrap();
+        let self_cgroup = "12:memory:/docker/abc\n\
+                           4:cpu,cpuacct:/docker/abc\n\
+                           1:name=systemd:/docker/abc\n";
+
+        write_file(root.path(), "cpu,cpuacct/cpu.cfs_quota_us", "-1");
+        write_file(root.path(), "cpu,cpuacct/cpu.cfs_period_us", "100000");
+        write_file(root.path(), "cpu,cpuacct/docker/abc/cpu.cfs_quota_us", "-1");
+        write_file(
+            root.path(),
+            "cpu,cpuacct/docker/abc/cpu.cfs_period_us",
+            "100000",
+        );
+        assert_eq!(read_cgroup_cpu_quota(root.path(), self_cgroup), None);
+
+        write_file(
+            root.path(),
+            "cpu,cpuacct/docker/abc/cpu.cfs_quota_us",
+            "150000",
+        );
+        assert_eq!(read_cgroup_cpu_quota(root.path(), self_cgroup), Some(1.5));
+
+        // Нет смонтированной иерархии cpu
+        let empty = tempdir().unwrap();
+        assert_eq!(read_cgroup_cpu_quota(empty.path(), self_cgroup), None);
+    }
+
+    #[test]
+    fn test_parallelism() {
+        // Без ограничений используются все логические процессоры
+        assert_eq!(Parallelism::new(64, None, None).effective_cpus, 64);
+
+        // Привязка к части процессоров
+        assert_eq!(Parallelism::new(64, Some(8), None).effective_cpus, 8);
+
+        // Дробная квота округляется вверх
+        let parallelism = Parallelism::new(64, Some(64), Some(2.5));
+        assert_eq!(parallelism.effective_cpus, 3);
+        assert_eq!(
+            parallelism.to_string(),
+            "3 (привязка: 64, квота cgroup: 2.5)"
+        );
+
+        // Квота меньше одного процессора
+        assert_eq!(Parallelism::new(64, Some(64), Some(0.2)).effective_cpus, 1);
+    }
+}
@@ -0,0 +1,272 @@
+//! Определение процессоров powerpc64 (POWER8 и новее, ppc64le).
+//!
+//! Наборы инструкций берутся из битовых масок `AT_HWCAP`/`AT_HWCAP2`,
+//! модель процессора - из поля `cpu` файла `/proc/cpuinfo`.
+
+use super::microarch::{self, Microarchitecture};
+use super::{CpuInfo, Hwcaps};
+use crate::error::CpuDetectionError;
+use crate::features::{CpuFeature, FeatureSet};
+
+/// Определяет информацию о процессоре powerpc64
+pub fn detect_powerpc64() -> Result<CpuInfo, CpuDetectionError> {
+    #[cfg(target_os = "linux")]
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo")
+        .map(|text| parse_powerpc64_cpuinfo(&text))
+        .unwrap_or_default();
+
+    #[cfg(not(target_os = "linux"))]
+    let cpuinfo = PowerCpuInfo::default();
+
+    // ELFv2 ABI (ppc64le) требует как минимум POWER8, поэтому без
+    // AT_HWCAP используется его набор инструкций
+    let hwcaps = read_powerpc64_hwcaps();
+    let features = match hwcaps {
+        Some((hwcap, hwcap2)) => decode_powerpc64_hwcaps(hwcap, hwcap2),
+        None => PowerLevel::Power8.required_features(),
+    };
+
+    Ok(CpuInfo {
+        vendor: "IBM".to_string(),
+        model: cpuinfo.model(),
+        microarchitecture: cpuinfo.microarchitecture(),
+        features,
+        hwcaps: hwcaps.map(|(hwcap, hwcap2)| Hwcaps { hwcap, hwcap2 }),
+        ..CpuInfo::default()
+    })
+}
+
+/// Уровни процессоров POWER
+///
+/// Соответствуют значениям `-C target-cpu` в rustc: `pwr8`, `pwr9` и
+/// `pwr10`. Каждый следующий уровень включает все инструкции предыдущего.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PowerLevel {
+    /// POWER8 (ISA 2.07): AltiVec, VSX
+    Power8,
+    /// POWER9 (ISA 3.0)
+    Power9,
+    /// POWER10 (ISA 3.1)
+    Power10,
+}
+
+impl PowerLevel {
+    /// Все уровни в порядке возрастания
+    pub const ALL: [PowerLevel; 3] = [PowerLevel::Power8, PowerLevel::Power9, PowerLevel::Power10];
+
+    /// Инструкции, которые добавляет данный уровень к предыдущему
+    pub fn own_features(self) -> &'static [CpuFeature] {
+        match self {
+            PowerLevel::Power8 => &[
+                CpuFeature::Altivec,
+                CpuFeature::Vsx,
+                CpuFeature::Power8Vector,
+            ],
+            PowerLevel::Power9 => &[CpuFeature::Power9Vector],
+            PowerLevel::Power10 => &[CpuFeature::Power10Vector],
+        }
+    }
+
+    /// Полный набор инструкций, необходимых для данного уровня
+    pub fn required_features(self) -> FeatureSet {
+        PowerLevel::ALL
+            .into_iter()
+            .filter(|&level| level <= self)
+            .flat_map(|level| level.own_features().iter().copied())
+            .collect()
+    }
+
+    /// Имя уровня в формате `-C target-cpu`
+    pub fn target_cpu(self) -> &'static str {
+        match self {
+            PowerLevel::Power8 => "pwr8",
+            PowerLevel::Power9 => "pwr9",
+            PowerLevel::Power10 => "pwr10",
+        }
+    }
+
+    /// Определяет уровень по имени в формате `-C target-cpu`
+    pub fn from_target_cpu(name: &str) -> Option<Self> {
+        PowerLevel::ALL
+            .into_iter()
+            .find(|level| level.target_cpu() == name)
+    }
+
+    /// Вычисляет наивысший уровень, все инструкции которого есть в списке
+    pub fn classify(features: &FeatureSet) -> Option<Self> {
+        PowerLevel::ALL
+            .into_iter()
+            .rev()
+            .find(|level| features.is_superset(&level.required_features()))
+    }
+}
+
+/// Биты AT_HWCAP (arch/powerpc/include/uapi/asm/cputable.h)
+const PPC_FEATURE_HAS_ALTIVEC: u64 = 0x1000_0000;
+const PPC_FEATURE_HAS_VSX: u64 = 0x0000_0080;
+
+/// Биты AT_HWCAP2
+const PPC_FEATURE2_ARCH_2_07: u64 = 0x8000_0000;
+const PPC_FEATURE2_ARCH_3_00: u64 = 0x0080_0000;
+const PPC_FEATURE2_ARCH_3_1: u64 = 0x0004_0000;
+const PPC_FEATURE2_MMA: u64 = 0x0002_0000;
+
+/// Наборы инструкций и биты AT_HWCAP и AT_HWCAP2, которые должны быть
+/// установлены одновременно: векторные инструкции уровня ISA доступны
+/// только вместе с VSX
+const POWERPC64_HWCAPS: &[(CpuFeature, u64, u64)] = &[
+    (CpuFeature::Altivec, PPC_FEATURE_HAS_ALTIVEC, 0),
+    (CpuFeature::Vsx, PPC_FEATURE_HAS_VSX, 0),
+    (
+        CpuFeature::Power8Vector,
+        PPC_FEATURE_HAS_VSX,
+        PPC_FEATURE2_ARCH_2_07,
+    ),
+    (
+        CpuFeature::Power9Vector,
+        PPC_FEATURE_HAS_VSX,
+        PPC_FEATURE2_ARCH_3_00,
+    ),
+    (
+        CpuFeature::Power10Vector,
+        PPC_FEATURE_HAS_VSX,
+        PPC_FEATURE2_ARCH_3_1,
+    ),
+    (CpuFeature::Mma, 0, PPC_FEATURE2_MMA),
+];
+
+/// Декодирует битовые маски AT_HWCAP и AT_HWCAP2 ядра Linux для powerpc64
+pub fn decode_powerpc64_hwcaps(hwcap: u64, hwcap2: u64) -> FeatureSet {
+    POWERPC64_HWCAPS
+        .iter()
+        .filter(|&&(_, mask, mask2)| hwcap & mask == mask && hwcap2 & mask2 == mask2)
+        .map(|&(feature, _, _)| feature)
+        .collect()
+}
+
+/// Читает AT_HWCAP и AT_HWCAP2 из вспомогательного вектора процесса
+fn read_powerpc64_hwcaps() -> Option<(u64, u64)> {
+    #[cfg(all(target_os = "linux", target_arch = "powerpc64"))]
+    {
+        // SAFETY: getauxval только читает вспомогательный вектор процесса
+        let (hwcap, hwcap2) = unsafe {
+            (
+                libc::getauxval(libc::AT_HWCAP),
+                libc::getauxval(libc::AT_HWCAP2),
+            )
+        };
+        Some((hwcap as u64, hwcap2 as u64))
+    }
+
+    #[cfg(not(all(target_os = "linux", target_arch = "powerpc64")))]
+    {
+        None
+    }
+}
+
+/// Результат разбора /proc/cpuinfo на powerpc64
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PowerCpuInfo {
+    /// Значение поля `cpu` первого процессора (`POWER9 (raw), altivec supported`)
+    pub cpu: Option<String>,
+    /// Значение поля `machine` (`PowerNV 9006-22P`)
+    pub machine: Option<String>,
+}
+
+impl PowerCpuInfo {
+    /// Название процессора без уточнений в скобках (`POWER9`)
+    fn cpu_name(&self) -> Option<&str> {
+        let cpu = self.cpu.as_deref()?;
+        let name = cpu.split(['(', ',']).next().unwrap_or(cpu).trim();
+        (!name.is_empty()).then_some(name)
+    }
+
+    /// Модель процессора
+    pub fn model(&self) -> String {
+        match (self.cpu_name(), &self.machine) {
+            (Some(cpu), Some(machine)) => format!("{} ({})", cpu, machine),
+            (Some(cpu), None) => cpu.to_string(),
+            (None, _) => "Unknown POWER Processor".to_string(),
+        }
+    }
+
+    /// Микроархитектура (`power9`, `power10`)
+    pub fn microarchitecture(&self) -> Option<Microarchitecture> {
+        self.cpu_name().map(microarch::identify_power)
+    }
+}
+
+/// Разбирает содержимое /proc/cpuinfo ядра Linux для powerpc64
+pub fn parse_powerpc64_cpuinfo(text: &str) -> PowerCpuInfo {
+    let mut info = PowerCpuInfo::default();
+
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().to_string();
+        match key.trim() {
+            "cpu" if info.cpu.is_none() => info.cpu = Some(value),
+            "machine" => info.machine = Some(value),
+            _ => {},
+        }
+    }
+
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_powerpc64_hwcaps() {
+        // POWER9 (PowerNV): ppc32 ppc64 altivec fpu mmu dfp vsx ...,
+        // arch_2_07 htm isel vcrypto darn ieee128 arch_3_00
+        let features = decode_powerpc64_hwcaps(0xdc00_6180, 0xbee0_0000);
+        assert_eq!(
+            features.to_string(),
+            "altivec,vsx,power8-vector,power9-vector"
+        );
+        assert_eq!(PowerLevel::classify(&features), Some(PowerLevel::Power9));
+
+        // POWER10: arch_3_1 и mma
+        let features = decode_powerpc64_hwcaps(0xdc00_6180, 0xbee6_0000);
+        assert!(features.contains(CpuFeature::Power10Vector));
+        assert!(features.contains(CpuFeature::Mma));
+        assert_eq!(PowerLevel::classify(&features), Some(PowerLevel::Power10));
+
+        // Без VSX векторные инструкции уровня ISA недоступны
+        let features = decode_powerpc64_hwcaps(PPC_FEATURE_HAS_ALTIVEC, 0xbee0_0000);
+        assert_eq!(features.to_string(), "altivec");
+        assert_eq!(PowerLevel::classify(&features), None);
+
+        for level in PowerLevel::ALL {
+            assert_eq!(PowerLevel::from_target_cpu(level.target_cpu()), Some(level));
+        }
+    }
+
+    #[test]
+    fn test_parse_powerpc64_cpuinfo() {
+        let text = "processor\t: 0\n\
+                    cpu\t\t: POWER9 (raw), altivec supported\n\
+                    clock\t\t: 3800.000000MHz\n\
+                    revision\t: 2.3 (pvr 004e 1203)\n\n\
+                    timebase\t: 512000000\n\
+                    platform\t: PowerNV\n\
+                    model\t\t: 9006-22P\n\
+                    machine\t\t: PowerNV 9006-22P\n";
+        let info = parse_powerpc64_cpuinfo(text);
+        assert_eq!(info.model(), "POWER9 (PowerNV 9006-22P)");
+        assert_eq!(info.microarchitecture().unwrap().id, "power9");
+
+        let info = parse_powerpc64_cpuinfo("cpu\t\t: POWER10 (architected), altivec supported\n");
+        assert_eq!(info.model(), "POWER10");
+        assert_eq!(info.microarchitecture().unwrap().id, "power10");
+
+        assert_eq!(
+            parse_powerpc64_cpuinfo("").model(),
+            "Unknown POWER Processor"
+        );
+    }
+}
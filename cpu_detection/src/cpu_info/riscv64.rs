@@ -0,0 +1,612 @@
+//! Определение процессоров riscv64.
+//!
+//! Наборы инструкций берутся из строки `isa` файла `/proc/cpuinfo`,
+//! битовой маски `AT_HWCAP` и системного вызова `riscv_hwprobe`,
+//! производитель и модель - из полей `mvendorid` и `uarch`.
+
+use super::microarch::{self, Microarchitecture};
+use super::{CpuInfo, Hwcaps};
+use crate::error::CpuDetectionError;
+use crate::features::{CpuFeature, FeatureSet};
+
+/// Определяет информацию о процессоре riscv64
+pub fn detect_riscv64() -> Result<CpuInfo, CpuDetectionError> {
+    #[cfg(target_os = "linux")]
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo")
+        .map(|text| parse_riscv_cpuinfo(&text))
+        .unwrap_or_default();
+
+    #[cfg(not(target_os = "linux"))]
+    let cpuinfo = RiscvCpuInfo::default();
+
+    // Строка isa, AT_HWCAP и riscv_hwprobe дополняют друг друга: старые
+    // ядра не поддерживают hwprobe, а AT_HWCAP описывает только
+    // однобуквенные расширения
+    let mut features = cpuinfo.common_features();
+    let hwcap = read_riscv_hwcap();
+    if let Some(hwcap) = hwcap {
+        features = features.union(&decode_riscv_hwcap(hwcap));
+    }
+    if let Some((base_behavior, ima_ext_0)) = read_riscv_hwprobe() {
+        features = features.union(&decode_riscv_hwprobe(base_behavior, ima_ext_0));
+    }
+
+    Ok(CpuInfo {
+        vendor: cpuinfo.vendor(),
+        model: cpuinfo.model(),
+        microarchitecture: cpuinfo.microarchitecture(),
+        features,
+        hwcaps: hwcap.map(|hwcap| Hwcaps { hwcap, hwcap2: 0 }),
+        ..CpuInfo::default()
+    })
+}
+
+/// Профили приложений RISC-V (RVA22U64, RVA23U64)
+///
+/// Профиль проверяется только по отслеживаемым расширениям; остальные
+/// обязательные расширения профиля (Zicsr, Zihintpause, Zfhmin и др.) на
+/// выбор библиотеки не влияют. Каждый следующий профиль включает все
+/// расширения предыдущего.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RiscvProfile {
+    /// RVA22U64: RV64GC, Zba, Zbb, Zbs, Zicbom, Zicboz
+    Rva22,
+    /// RVA23U64: RVA22U64, V, Zvbb
+    Rva23,
+}
+
+impl RiscvProfile {
+    /// Все профили в порядке возрастания
+    pub const ALL: [RiscvProfile; 2] = [RiscvProfile::Rva22, RiscvProfile::Rva23];
+
+    /// Расширения, которые добавляет данный профиль к предыдущему
+    pub fn own_features(self) -> &'static [CpuFeature] {
+        match self {
+            RiscvProfile::Rva22 => &[
+                CpuFeature::M,
+                CpuFeature::A,
+                CpuFeature::F,
+                CpuFeature::D,
+                CpuFeature::C,
+                CpuFeature::Zba,
+                CpuFeature::Zbb,
+                CpuFeature::Zbs,
+                CpuFeature::Zicbom,
+                CpuFeature::Zicboz,
+            ],
+            RiscvProfile::Rva23 => &[CpuFeature::V, CpuFeature::Zvbb],
+        }
+    }
+
+    /// Полный набор расширений, необходимых для данного профиля
+    pub fn required_features(self) -> FeatureSet {
+        RiscvProfile::ALL
+            .into_iter()
+            .filter(|&profile| profile <= self)
+            .flat_map(|profile| profile.own_features().iter().copied())
+            .collect()
+    }
+
+    /// Имя профиля в именах вариантов библиотеки
+    pub fn name(self) -> &'static str {
+        match self {
+            RiscvProfile::Rva22 => "rva22",
+            RiscvProfile::Rva23 => "rva23",
+        }
+    }
+
+    /// Определяет профиль по имени
+    pub fn from_name(name: &str) -> Option<Self> {
+        RiscvProfile::ALL
+            .into_iter()
+            .find(|profile| profile.name() == name)
+    }
+
+    /// Вычисляет наивысший профиль, все расширения которого есть в списке
+    pub fn classify(features: &FeatureSet) -> Option<Self> {
+        RiscvProfile::ALL
+            .into_iter()
+            .rev()
+            .find(|profile| features.is_superset(&profile.required_features()))
+    }
+}
+
+/// Отслеживаемые многобуквенные расширения по именам в строке ISA
+const RISCV_EXTENSIONS: &[(&str, CpuFeature)] = &[
+    ("zba", CpuFeature::Zba),
+    ("zbb", CpuFeature::Zbb),
+    ("zbs", CpuFeature::Zbs),
+    ("zicbom", CpuFeature::Zicbom),
+    ("zicboz", CpuFeature::Zicboz),
+    ("zvbb", CpuFeature::Zvbb),
+];
+
+/// Отслеживаемые однобуквенные расширения
+const RISCV_LETTERS: &[(char, CpuFeature)] = &[
+    ('m', CpuFeature::M),
+    ('a', CpuFeature::A),
+    ('f', CpuFeature::F),
+    ('d', CpuFeature::D),
+    ('c', CpuFeature::C),
+    ('v', CpuFeature::V),
+];
+
+/// Результат разбора строки ISA (`rv64imafdcv_zba_zbb`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RiscvIsa {
+    /// Разрядность базового набора (32 или 64)
+    pub xlen: u32,
+    /// Имена расширений в нижнем регистре без номеров версий; `g` и `b`
+    /// раскрываются в составляющие их расширения
+    pub extensions: Vec<String>,
+}
+
+impl RiscvIsa {
+    /// Проверяет наличие расширения
+    pub fn has(&self, extension: &str) -> bool {
+        self.extensions.iter().any(|ext| ext == extension)
+    }
+
+    /// Отслеживаемые наборы инструкций
+    pub fn features(&self) -> FeatureSet {
+        let letters = RISCV_LETTERS
+            .iter()
+            .filter(|(letter, _)| self.has(&letter.to_string()))
+            .map(|&(_, feature)| feature);
+        let extensions = RISCV_EXTENSIONS
+            .iter()
+            .filter(|(name, _)| self.has(name))
+            .map(|&(_, feature)| feature);
+
+        letters.chain(extensions).collect()
+    }
+
+    fn push(&mut self, extension: &str) {
+        if !self.has(extension) {
+            self.extensions.push(extension.to_string());
+        }
+    }
+
+    /// Добавляет однобуквенное расширение, раскрывая составные
+    fn push_letter(&mut self, letter: char) {
+        match letter {
+            'g' => {
+                for ext in ["i", "m", "a", "f", "d", "zicsr", "zifencei"] {
+                    self.push(ext);
+                }
+            },
+            'b' => {
+                for ext in ["b", "zba", "zbb", "zbs"] {
+                    self.push(ext);
+                }
+            },
+            letter => self.push(&letter.to_string()),
+        }
+    }
+}
+
+/// Разбирает строку ISA RISC-V
+///
+/// Поддерживаются форматы ядра Linux (`rv64imafdc_zicsr_zba`) и
+/// device tree с номерами версий (`rv64i2p1_m2p0_zba1p0`). Возвращает
+/// `None`, если строка не начинается с `rv32`/`rv64`.
+pub fn parse_riscv_isa(isa: &str) -> Option<RiscvIsa> {
+    let isa = isa.trim().to_ascii_lowercase();
+    let (xlen, rest) = if let Some(rest) = isa.strip_prefix("rv64") {
+        (64, rest)
+    } else if let Some(rest) = isa.strip_prefix("rv32") {
+        (32, rest)
+    } else {
+        return None;
+    };
+
+    let mut result = RiscvIsa {
+        xlen,
+        extensions: Vec::new(),
+    };
+
+    for token in rest.split('_').filter(|token| !token.is_empty()) {
+        if token.starts_with(['z', 's', 'x']) {
+            // Многобуквенное расширение, возможно с версией (zba1p0)
+            result.push(strip_version(token));
+        } else {
+            // Последовательность однобуквенных расширений, возможно с
+            // версиями после каждой буквы (i2p1m2p0); `p` между цифрами
+            // разделяет номера версии, а не обозначает расширение P
+            let chars: Vec<char> = token.chars().collect();
+            for (i, &letter) in chars.iter().enumerate() {
+                let is_version_separator = letter == 'p'
+                    && i > 0
+                    && chars[i - 1].is_ascii_digit()
+                    && chars.get(i + 1).is_some_and(char::is_ascii_digit);
+                if letter.is_ascii_alphabetic() && !is_version_separator {
+                    result.push_letter(letter);
+                }
+            }
+        }
+    }
+
+    Some(result)
+}
+
+/// Отбрасывает номер версии вида `1p0` в конце имени расширения
+fn strip_version(token: &str) -> &str {
+    let without_minor = match token.rsplit_once('p') {
+        Some((head, minor))
+            if !minor.is_empty()
+                && minor.bytes().all(|b| b.is_ascii_digit())
+                && head.ends_with(|c: char| c.is_ascii_digit()) =>
+        {
+            head
+        },
+        _ => return token,
+    };
+    without_minor.trim_end_matches(|c: char| c.is_ascii_digit())
+}
+
+/// Декодирует битовую маску AT_HWCAP ядра Linux для riscv64
+///
+/// Бит с номером `n` соответствует однобуквенному расширению `'a' + n`.
+pub fn decode_riscv_hwcap(hwcap: u64) -> FeatureSet {
+    RISCV_LETTERS
+        .iter()
+        .filter(|&&(letter, _)| hwcap & (1 << (letter as u8 - b'a')) != 0)
+        .map(|&(_, feature)| feature)
+        .collect()
+}
+
+/// Бит `RISCV_HWPROBE_BASE_BEHAVIOR_IMA`: базовый набор RV64IMA
+const HWPROBE_BASE_BEHAVIOR_IMA: u64 = 1 << 0;
+
+/// Биты ключа `RISCV_HWPROBE_KEY_IMA_EXT_0` (arch/riscv/include/uapi/asm/hwprobe.h)
+const RISCV_HWPROBE_IMA_EXT_0: &[(CpuFeature, u64)] = &[
+    (CpuFeature::F, 1 << 0),       // RISCV_HWPROBE_IMA_FD
+    (CpuFeature::D, 1 << 0),       // RISCV_HWPROBE_IMA_FD
+    (CpuFeature::C, 1 << 1),       // RISCV_HWPROBE_IMA_C
+    (CpuFeature::V, 1 << 2),       // RISCV_HWPROBE_IMA_V
+    (CpuFeature::Zba, 1 << 3),     // RISCV_HWPROBE_EXT_ZBA
+    (CpuFeature::Zbb, 1 << 4),     // RISCV_HWPROBE_EXT_ZBB
+    (CpuFeature::Zbs, 1 << 5),     // RISCV_HWPROBE_EXT_ZBS
+    (CpuFeature::Zicboz, 1 << 6),  // RISCV_HWPROBE_EXT_ZICBOZ
+    (CpuFeature::Zvbb, 1 << 17),   // RISCV_HWPROBE_EXT_ZVBB
+    (CpuFeature::Zicbom, 1 << 55), // RISCV_HWPROBE_EXT_ZICBOM
+];
+
+/// Декодирует значения ключей `RISCV_HWPROBE_KEY_BASE_BEHAVIOR` и
+/// `RISCV_HWPROBE_KEY_IMA_EXT_0` системного вызова `riscv_hwprobe`
+pub fn decode_riscv_hwprobe(base_behavior: u64, ima_ext_0: u64) -> FeatureSet {
+    let mut features: FeatureSet = RISCV_HWPROBE_IMA_EXT_0
+        .iter()
+        .filter(|&&(_, mask)| ima_ext_0 & mask == mask)
+        .map(|&(feature, _)| feature)
+        .collect();
+
+    if base_behavior & HWPROBE_BASE_BEHAVIOR_IMA != 0 {
+        features.insert(CpuFeature::M);
+        features.insert(CpuFeature::A);
+    }
+
+    features
+}
+
+/// Читает AT_HWCAP из вспомогательного вектора процесса
+fn read_riscv_hwcap() -> Option<u64> {
+    #[cfg(all(target_os = "linux", target_arch = "riscv64"))]
+    {
+        // SAFETY: getauxval только читает вспомогательный вектор процесса
+        Some(unsafe { libc::getauxval(libc::AT_HWCAP) } as u64)
+    }
+
+    #[cfg(not(all(target_os = "linux", target_arch = "riscv64")))]
+    {
+        None
+    }
+}
+
+/// Запрашивает у ядра базовое поведение и расширения, общие для всех
+/// процессоров (`riscv_hwprobe`, Linux 6.4+)
+fn read_riscv_hwprobe() -> Option<(u64, u64)> {
+    #[cfg(all(target_os = "linux", target_arch = "riscv64"))]
+    {
+        /// Пара ключ-значение `struct riscv_hwprobe`
+        #[repr(C)]
+        struct RiscvHwprobe {
+            key: i64,
+            value: u64,
+        }
+
+        const SYS_RISCV_HWPROBE: libc::c_long = 258;
+        const KEY_BASE_BEHAVIOR: i64 = 3;
+        const KEY_IMA_EXT_0: i64 = 4;
+
+        let mut pairs = [
+            RiscvHwprobe {
+                key: KEY_BASE_BEHAVIOR,
+                value: 0,
+            },
+            RiscvHwprobe {
+                key: KEY_IMA_EXT_0,
+                value: 0,
+            },
+        ];
+
+        // SAFETY: ядро записывает значения только в переданный массив;
+        // пустое множество процессоров означает все процессоры
+        let ret = unsafe {
+            libc::syscall(
+                SYS_RISCV_HWPROBE,
+                pairs.as_mut_ptr(),
+                pairs.len(),
+                0usize,
+                std::ptr::null_mut::<libc::c_void>(),
+                0u32,
+            )
+        };
+
+        // Неизвестные ядру ключи заменяются на -1
+        if ret != 0 || pairs.iter().any(|pair| pair.key < 0) {
+            return None;
+        }
+        Some((pairs[0].value, pairs[1].value))
+    }
+
+    #[cfg(not(all(target_os = "linux", target_arch = "riscv64")))]
+    {
+        None
+    }
+}
+
+/// Описание одного процессора (hart) riscv64 из /proc/cpuinfo
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RiscvHart {
+    /// Номер логического процессора
+    pub processor: u32,
+    /// Разобранная строка `isa`
+    pub isa: RiscvIsa,
+    /// Модель ядра (`uarch`, например `sifive,u74-mc`)
+    pub uarch: Option<String>,
+    /// Код производителя JEDEC (`mvendorid`)
+    pub mvendorid: u64,
+    /// Код микроархитектуры (`marchid`)
+    pub marchid: u64,
+}
+
+/// Результат разбора /proc/cpuinfo на riscv64
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RiscvCpuInfo {
+    /// Описания процессоров в порядке номеров
+    pub harts: Vec<RiscvHart>,
+}
+
+impl RiscvCpuInfo {
+    /// Наборы инструкций, поддерживаемые всеми процессорами
+    pub fn common_features(&self) -> FeatureSet {
+        let mut harts = self.harts.iter();
+        let first = harts
+            .next()
+            .map(|hart| hart.isa.features())
+            .unwrap_or_default();
+        harts.fold(first, |acc, hart| acc.intersection(&hart.isa.features()))
+    }
+
+    /// Производитель процессора
+    ///
+    /// Берется по коду `mvendorid`, а если он неизвестен - из префикса
+    /// `uarch` (`sifive,u74-mc` - `sifive`).
+    pub fn vendor(&self) -> String {
+        self.harts
+            .iter()
+            .find_map(|hart| riscv_vendor_name(hart.mvendorid))
+            .map(String::from)
+            .or_else(|| {
+                self.harts
+                    .iter()
+                    .find_map(|hart| hart.uarch.as_deref()?.split_once(','))
+                    .map(|(vendor, _)| vendor.to_string())
+            })
+            .unwrap_or_else(|| "RISC-V".to_string())
+    }
+
+    /// Микроархитектура ядер процессора
+    pub fn microarchitecture(&self) -> Option<Microarchitecture> {
+        self.harts
+            .iter()
+            .find_map(|hart| hart.uarch.as_deref())
+            .map(microarch::identify_riscv)
+    }
+
+    /// Модель процессора: различные значения `uarch` через ` + `
+    pub fn model(&self) -> String {
+        let mut uarchs: Vec<&str> = Vec::new();
+        for uarch in self.harts.iter().filter_map(|hart| hart.uarch.as_deref()) {
+            if !uarchs.contains(&uarch) {
+                uarchs.push(uarch);
+            }
+        }
+
+        if uarchs.is_empty() {
+            return "Unknown RISC-V Processor".to_string();
+        }
+        uarchs.join(" + ")
+    }
+}
+
+/// Разбирает содержимое /proc/cpuinfo ядра Linux для riscv64
+///
+/// Каждый блок, начинающийся со строки `processor`, описывает один
+/// процессор. Строка `hart isa` (Linux 6.8+) описывает расширения
+/// конкретного процессора и имеет приоритет над общей строкой `isa`.
+pub fn parse_riscv_cpuinfo(text: &str) -> RiscvCpuInfo {
+    let mut info = RiscvCpuInfo::default();
+    let mut current: Option<RiscvHart> = None;
+
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim();
+        let value = value.trim();
+
+        if key == "processor" {
+            info.harts.extend(current.take());
+            current = Some(RiscvHart {
+                processor: value.parse().unwrap_or_default(),
+                ..RiscvHart::default()
+            });
+            continue;
+        }
+
+        let Some(hart) = current.as_mut() else {
+            continue;
+        };
+        match key {
+            "isa" if hart.isa.xlen == 0 => hart.isa = parse_riscv_isa(value).unwrap_or_default(),
+            "hart isa" => {
+                if let Some(isa) = parse_riscv_isa(value) {
+                    hart.isa = isa;
+                }
+            },
+            "uarch" => hart.uarch = Some(value.to_string()),
+            "mvendorid" => hart.mvendorid = parse_hex(value).unwrap_or_default(),
+            "marchid" => hart.marchid = parse_hex(value).unwrap_or_default(),
+            _ => {},
+        }
+    }
+
+    info.harts.extend(current);
+    info
+}
+
+/// Разбирает шестнадцатеричное число с префиксом 0x
+fn parse_hex(value: &str) -> Option<u64> {
+    u64::from_str_radix(value.strip_prefix("0x")?, 16).ok()
+}
+
+/// Название производителя по коду JEDEC из `mvendorid`
+pub fn riscv_vendor_name(mvendorid: u64) -> Option<&'static str> {
+    let name = match mvendorid {
+        0x31e => "Andes",
+        0x489 => "SiFive",
+        0x5b7 => "T-Head",
+        0x710 => "SpacemiT",
+        _ => return None,
+    };
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_riscv_isa() {
+        let isa = parse_riscv_isa("rv64imafdcv_zicbom_zicboz_zba_zbb_zbs_zvbb").unwrap();
+        assert_eq!(isa.xlen, 64);
+        assert_eq!(
+            isa.features().to_string(),
+            "m,a,f,d,c,v,zba,zbb,zbs,zicbom,zicboz,zvbb"
+        );
+
+        // G раскрывается в IMAFD_Zicsr_Zifencei, B - в Zba, Zbb и Zbs
+        let isa = parse_riscv_isa("RV64GCB").unwrap();
+        assert!(isa.has("zicsr"));
+        assert!(isa.has("zifencei"));
+        assert_eq!(isa.features().to_string(), "m,a,f,d,c,zba,zbb,zbs");
+
+        // Формат device tree с номерами версий
+        let isa = parse_riscv_isa("rv64i2p1_m2p0_a2p1_f2p2_d2p2_c2p0_zicsr2p0_zba1p0").unwrap();
+        assert_eq!(
+            isa.extensions,
+            ["i", "m", "a", "f", "d", "c", "zicsr", "zba"]
+        );
+
+        // Имена с цифрами без версии не изменяются
+        let isa = parse_riscv_isa("rv64imac_zve64x_zvl128b_sv39").unwrap();
+        assert!(isa.has("zve64x"));
+        assert!(isa.has("zvl128b"));
+        assert!(isa.has("sv39"));
+
+        // Ssha (гипервизор) и Xtheadvector не путаются с SHA и V
+        let isa = parse_riscv_isa("rv64imafdc_sha_xtheadvector").unwrap();
+        assert_eq!(isa.features().to_string(), "m,a,f,d,c");
+
+        assert_eq!(parse_riscv_isa("rv32imc").unwrap().xlen, 32);
+        assert_eq!(parse_riscv_isa("x86_64"), None);
+        assert_eq!(parse_riscv_isa(""), None);
+    }
+
+    #[test]
+    fn test_decode_riscv_hwcap_and_hwprobe() {
+        // AT_HWCAP для rv64imafdcv: биты букв a, c, d, f, i, m, v
+        let hwcap = [b'a', b'c', b'd', b'f', b'i', b'm', b'v']
+            .iter()
+            .fold(0u64, |acc, &letter| acc | (1 << (letter - b'a')));
+        assert_eq!(decode_riscv_hwcap(hwcap).to_string(), "m,a,f,d,c,v");
+
+        let features = decode_riscv_hwprobe(1, (1 << 0) | (1 << 1) | (1 << 3) | (1 << 55));
+        assert_eq!(features.to_string(), "m,a,f,d,c,zba,zicbom");
+
+        assert!(decode_riscv_hwprobe(0, 0).is_empty());
+    }
+
+    #[test]
+    fn test_riscv_profile_classify() {
+        for profile in RiscvProfile::ALL {
+            assert_eq!(
+                RiscvProfile::classify(&profile.required_features()),
+                Some(profile)
+            );
+            assert_eq!(RiscvProfile::from_name(profile.name()), Some(profile));
+        }
+
+        let mut features = RiscvProfile::Rva23.required_features();
+        features.remove(CpuFeature::Zicbom);
+        assert_eq!(RiscvProfile::classify(&features), None);
+    }
+
+    #[test]
+    fn test_parse_riscv_cpuinfo() {
+        let cases = [
+            (
+                include_str!("../../tests/fixtures/cpuinfo/riscv64_visionfive2.txt"),
+                4,
+                "SiFive",
+                "sifive,u74-mc",
+                "u74-mc",
+                "m,a,f,d,c,zba,zbb",
+                None,
+            ),
+            (
+                include_str!("../../tests/fixtures/cpuinfo/riscv64_bpi_f3.txt"),
+                8,
+                "SpacemiT",
+                "spacemit,x60",
+                "x60",
+                "m,a,f,d,c,v,zba,zbb,zbs,zicbom,zicboz",
+                Some(RiscvProfile::Rva22),
+            ),
+            (
+                include_str!("../../tests/fixtures/cpuinfo/riscv64_qemu_rva23.txt"),
+                2,
+                "RISC-V",
+                "Unknown RISC-V Processor",
+                "",
+                "m,a,f,d,c,v,zba,zbb,zbs,zicbom,zicboz,zvbb",
+                Some(RiscvProfile::Rva23),
+            ),
+        ];
+
+        for (text, harts, vendor, model, microarch, features, profile) in cases {
+            let info = parse_riscv_cpuinfo(text);
+            assert_eq!(info.harts.len(), harts, "{}", model);
+            assert_eq!(info.vendor(), vendor);
+            assert_eq!(info.model(), model);
+            assert_eq!(
+                info.microarchitecture().map(|m| m.id).unwrap_or_default(),
+                microarch
+            );
+            assert_eq!(info.common_features().to_string(), features);
+            assert_eq!(RiscvProfile::classify(&info.common_features()), profile);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Определение процессоров s390x (IBM Z).
+//!
+//! Наборы инструкций берутся из битовой маски `AT_HWCAP`, поколение
+//! процессора - из типа машины в `/proc/cpuinfo`.
+
+use super::microarch::{self, Microarchitecture};
+use super::{CpuInfo, Hwcaps};
+use crate::error::CpuDetectionError;
+use crate::features::{CpuFeature, FeatureSet};
+
+/// Определяет информацию о процессоре s390x
+pub fn detect_s390x() -> Result<CpuInfo, CpuDetectionError> {
+    #[cfg(target_os = "linux")]
+    let machine_type = std::fs::read_to_string("/proc/cpuinfo")
+        .ok()
+        .and_then(|text| parse_s390x_machine_type(&text));
+
+    #[cfg(not(target_os = "linux"))]
+    let machine_type = None;
+
+    let hwcap = read_s390x_hwcap();
+    let microarchitecture = machine_type.and_then(microarch::identify_s390x);
+
+    Ok(CpuInfo {
+        vendor: "IBM".to_string(),
+        model: s390x_model(machine_type, microarchitecture.as_ref()),
+        microarchitecture,
+        features: hwcap.map(decode_s390x_hwcap).unwrap_or_default(),
+        hwcaps: hwcap.map(|hwcap| Hwcaps { hwcap, hwcap2: 0 }),
+        ..CpuInfo::default()
+    })
+}
+
+/// Поколения процессоров IBM Z
+///
+/// Соответствуют значениям `-C target-cpu` в rustc: `z13`, `z14` и `z15`.
+/// Каждое следующее поколение включает все инструкции предыдущего.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum S390xLevel {
+    /// z13: векторное расширение
+    Z13,
+    /// z14: Vector Enhancements Facility 1
+    Z14,
+    /// z15: Vector Enhancements Facility 2
+    Z15,
+}
+
+impl S390xLevel {
+    /// Все поколения в порядке возрастания
+    pub const ALL: [S390xLevel; 3] = [S390xLevel::Z13, S390xLevel::Z14, S390xLevel::Z15];
+
+    /// Инструкции, которые добавляет данное поколение к предыдущему
+    pub fn own_features(self) -> &'static [CpuFeature] {
+        match self {
+            S390xLevel::Z13 => &[CpuFeature::Vector],
+            S390xLevel::Z14 => &[CpuFeature::VectorEnhancements1],
+            S390xLevel::Z15 => &[CpuFeature::VectorEnhancements2],
+        }
+    }
+
+    /// Полный набор инструкций, необходимых для данного поколения
+    pub fn required_features(self) -> FeatureSet {
+        S390xLevel::ALL
+            .into_iter()
+            .filter(|&level| level <= self)
+            .flat_map(|level| level.own_features().iter().copied())
+            .collect()
+    }
+
+    /// Имя поколения в формате `-C target-cpu`
+    pub fn target_cpu(self) -> &'static str {
+        match self {
+            S390xLevel::Z13 => "z13",
+            S390xLevel::Z14 => "z14",
+            S390xLevel::Z15 => "z15",
+        }
+    }
+
+    /// Определяет поколение по имени в формате `-C target-cpu`
+    pub fn from_target_cpu(name: &str) -> Option<Self> {
+        S390xLevel::ALL
+            .into_iter()
+            .find(|level| level.target_cpu() == name)
+    }
+
+    /// Вычисляет наивысшее поколение, все инструкции которого есть в списке
+    pub fn classify(features: &FeatureSet) -> Option<Self> {
+        S390xLevel::ALL
+            .into_iter()
+            .rev()
+            .find(|level| features.is_superset(&level.required_features()))
+    }
+}
+
+/// Биты AT_HWCAP для s390x (arch/s390/include/asm/elf.h)
+const S390X_HWCAP: &[(CpuFeature, u64)] = &[
+    (CpuFeature::Vector, 1 << 11),              // HWCAP_S390_VXRS
+    (CpuFeature::VectorEnhancements1, 1 << 13), // HWCAP_S390_VXRS_EXT
+    (CpuFeature::VectorEnhancements2, 1 << 15), // HWCAP_S390_VXRS_EXT2
+];
+
+/// Декодирует битовую маску AT_HWCAP ядра Linux для s390x
+pub fn decode_s390x_hwcap(hwcap: u64) -> FeatureSet {
+    S390X_HWCAP
+        .iter()
+        .filter(|&&(_, mask)| hwcap & mask == mask)
+        .map(|&(feature, _)| feature)
+        .collect()
+}
+
+/// Читает AT_HWCAP из вспомогательного вектора процесса
+fn read_s390x_hwcap() -> Option<u64> {
+    #[cfg(all(target_os = "linux", target_arch = "s390x"))]
+    {
+        // SAFETY: getauxval только читает вспомогательный вектор процесса
+        Some(unsafe { libc::getauxval(libc::AT_HWCAP) } as u64)
+    }
+
+    #[cfg(not(all(target_os = "linux", target_arch = "s390x")))]
+    {
+        None
+    }
+}
+
+/// Извлекает тип машины из строк `processor N: ... machine = 8561`
+pub fn parse_s390x_machine_type(text: &str) -> Option<u32> {
+    text.lines()
+        .filter(|line| line.starts_with("processor"))
+        .find_map(|line| {
+            let (_, machine) = line.split_once("machine =")?;
+            machine.trim().parse().ok()
+        })
+}
+
+/// Модель процессора: поколение и тип машины
+fn s390x_model(machine_type: Option<u32>, microarchitecture: Option<&Microarchitecture>) -> String {
+    match (microarchitecture, machine_type) {
+        (Some(microarchitecture), Some(machine_type)) => {
+            format!("{} ({})", microarchitecture.name, machine_type)
+        },
+        (None, Some(machine_type)) => format!("IBM Z ({})", machine_type),
+        (_, None) => "Unknown IBM Z Processor".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_s390x_hwcap() {
+        // z15: esan3 zarch stfle msa ldisp eimm dfp edat etf3eh highgprs te
+        // vx vxd vxe gs vxe2 vxp sort dflt
+        let features = decode_s390x_hwcap(0x7_ffff);
+        assert_eq!(
+            features.to_string(),
+            "vector,vector-enhancements-1,vector-enhancements-2"
+        );
+        assert_eq!(S390xLevel::classify(&features), Some(S390xLevel::Z15));
+
+        // z13: только векторное расширение
+        let features = decode_s390x_hwcap(0x1fff);
+        assert_eq!(S390xLevel::classify(&features), Some(S390xLevel::Z13));
+
+        // zEC12: векторного расширения нет
+        assert_eq!(S390xLevel::classify(&decode_s390x_hwcap(0x7ff)), None);
+
+        for level in S390xLevel::ALL {
+            assert_eq!(S390xLevel::from_target_cpu(level.target_cpu()), Some(level));
+        }
+    }
+
+    #[test]
+    fn test_parse_s390x_machine_type() {
+        let text = "vendor_id       : IBM/S390\n\
+                    # processors    : 2\n\
+                    bogomips per cpu: 3241.00\n\
+                    features\t: esan3 zarch stfle msa ldisp eimm dfp edat etf3eh highgprs te vx vxd vxe gs vxe2 vxp sort dflt\n\
+                    processor 0: version = 00,  identification = 0133E8,  machine = 8561\n\
+                    processor 1: version = 00,  identification = 0133E8,  machine = 8561\n";
+        let machine_type = parse_s390x_machine_type(text);
+        assert_eq!(machine_type, Some(8561));
+
+        let microarchitecture = machine_type.and_then(microarch::identify_s390x);
+        assert_eq!(
+            s390x_model(machine_type, microarchitecture.as_ref()),
+            "IBM z15 (8561)"
+        );
+        assert_eq!(s390x_model(Some(2827), None), "IBM Z (2827)");
+        assert_eq!(parse_s390x_machine_type("vendor_id : IBM/S390\n"), None);
+    }
+}
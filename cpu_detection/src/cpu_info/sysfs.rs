@@ -0,0 +1,82 @@
+//! Вспомогательные функции для чтения файлов sysfs и procfs.
+//!
+//! Все функции принимают путь явно, поэтому их можно проверять на
+//! поддельных деревьях каталогов.
+
+use std::fs;
+use std::path::Path;
+
+/// Корень файловой системы sysfs
+pub const SYSFS_ROOT: &str = "/sys";
+
+/// Читает файл и возвращает его содержимое без пробельных символов по краям
+pub fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|content| content.trim().to_string())
+}
+
+/// Читает файл и разбирает его содержимое как число
+pub fn read_number<T: std::str::FromStr>(path: &Path) -> Option<T> {
+    read_trimmed(path)?.parse().ok()
+}
+
+/// Разбирает список процессоров в формате ядра Linux (`0-3,8,10-11`)
+pub fn parse_cpu_list(list: &str) -> Option<Vec<u32>> {
+    let mut cpus = Vec::new();
+
+    for range in list.trim().split(',').filter(|r| !r.is_empty()) {
+        match range.split_once('-') {
+            Some((start, end)) => {
+                let start: u32 = start.trim().parse().ok()?;
+                let end: u32 = end.trim().parse().ok()?;
+                if end < start {
+                    return None;
+                }
+                cpus.extend(start..=end);
+            },
+            None => cpus.push(range.trim().parse().ok()?),
+        }
+    }
+
+    Some(cpus)
+}
+
+/// Разбирает размер с суффиксом `K`, `M` или `G` (например, `48K`) в байты
+pub fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let (number, multiplier) = match size.chars().last()? {
+        'K' | 'k' => (&size[..size.len() - 1], 1 << 10),
+        'M' | 'm' => (&size[..size.len() - 1], 1 << 20),
+        'G' | 'g' => (&size[..size.len() - 1], 1 << 30),
+        _ => (size, 1),
+    };
+
+    number.trim().parse::<u64>().ok().map(|n| n * multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(parse_cpu_list("0"), Some(vec![0]));
+        assert_eq!(parse_cpu_list("0-3"), Some(vec![0, 1, 2, 3]));
+        assert_eq!(parse_cpu_list("0,56\n"), Some(vec![0, 56]));
+        assert_eq!(parse_cpu_list("0-1,8-9,12"), Some(vec![0, 1, 8, 9, 12]));
+        assert_eq!(parse_cpu_list(""), Some(vec![]));
+        assert_eq!(parse_cpu_list("3-1"), None);
+        assert_eq!(parse_cpu_list("a-b"), None);
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("48K"), Some(48 * 1024));
+        assert_eq!(parse_size("2048K\n"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_size("32M"), Some(32 * 1024 * 1024));
+        assert_eq!(parse_size("64"), Some(64));
+        assert_eq!(parse_size("K"), None);
+        assert_eq!(parse_size(""), None);
+    }
+}
@@ -0,0 +1,405 @@
+//! Определение топологии процессора: ядра, потоки SMT, сокеты, узлы NUMA
+//! и типы ядер гибридных процессоров (P-ядра и E-ядра).
+//!
+//! На Linux топология читается из `/sys/devices/system/cpu/cpu*/topology`
+//! и `/sys/devices/system/node`. Типы ядер берутся из списков
+//! `/sys/devices/cpu_core/cpus` и `/sys/devices/cpu_atom/cpus`, а при их
+//! отсутствии - из листа CPUID 0x1A каждого логического процессора.
+
+use super::sysfs;
+use raw_cpuid::{CpuIdReader, CpuIdResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+/// Тип ядра гибридного процессора
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoreType {
+    /// Производительное ядро (P-core, Intel Core)
+    Performance,
+    /// Энергоэффективное ядро (E-core, Intel Atom)
+    Efficiency,
+}
+
+/// Узел NUMA
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NumaNode {
+    /// Номер узла
+    pub id: u32,
+    /// Логические процессоры узла
+    pub cpus: Vec<u32>,
+}
+
+/// Топология процессора
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CpuTopology {
+    /// Количество логических процессоров
+    pub logical_cpus: u32,
+    /// Количество физических ядер
+    pub physical_cores: u32,
+    /// Количество физических процессоров (сокетов)
+    pub packages: u32,
+    /// Максимальное количество потоков SMT на одно ядро
+    pub threads_per_core: u32,
+    /// Узлы NUMA (пустой список, если ядро не сообщает о NUMA)
+    pub numa_nodes: Vec<NumaNode>,
+    /// Логические процессоры на производительных ядрах (только для
+    /// гибридных процессоров)
+    pub performance_cpus: Vec<u32>,
+    /// Логические процессоры на энергоэффективных ядрах (только для
+    /// гибридных процессоров)
+    pub efficiency_cpus: Vec<u32>,
+}
+
+impl CpuTopology {
+    /// Топология без сведений о ядрах: каждый логический процессор
+    /// считается отдельным ядром одного сокета
+    pub fn flat(logical_cpus: u32) -> Self {
+        CpuTopology {
+            logical_cpus,
+            physical_cores: logical_cpus,
+            packages: 1,
+            threads_per_core: 1,
+            ..CpuTopology::default()
+        }
+    }
+
+    /// Является ли процессор гибридным (есть и P-ядра, и E-ядра)
+    pub fn is_hybrid(&self) -> bool {
+        !self.performance_cpus.is_empty() && !self.efficiency_cpus.is_empty()
+    }
+
+    /// Тип ядра логического процессора (только для гибридных процессоров)
+    #[allow(dead_code)]
+    pub fn core_type(&self, cpu: u32) -> Option<CoreType> {
+        if self.performance_cpus.contains(&cpu) {
+            Some(CoreType::Performance)
+        } else if self.efficiency_cpus.contains(&cpu) {
+            Some(CoreType::Efficiency)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for CpuTopology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ядер: {}, логических процессоров: {}, сокетов: {}, узлов NUMA: {}",
+            self.physical_cores,
+            self.logical_cpus,
+            self.packages,
+            self.numa_nodes.len().max(1)
+        )?;
+        if self.is_hybrid() {
+            write!(
+                f,
+                ", на P-ядрах: {}, на E-ядрах: {}",
+                self.performance_cpus.len(),
+                self.efficiency_cpus.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Определяет топологию процессора текущей системы
+pub fn detect_topology() -> CpuTopology {
+    let logical_cpus = std::thread::available_parallelism().map_or(1, |n| n.get() as u32);
+    let mut topology = read_sysfs_topology(Path::new(sysfs::SYSFS_ROOT))
+        .unwrap_or_else(|| CpuTopology::flat(logical_cpus));
+
+    if topology.performance_cpus.is_empty() && topology.efficiency_cpus.is_empty() {
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        for (cpu, core_type) in read_core_types_by_affinity(topology.logical_cpus) {
+            match core_type {
+                CoreType::Performance => topology.performance_cpus.push(cpu),
+                CoreType::Efficiency => topology.efficiency_cpus.push(cpu),
+            }
+        }
+    }
+
+    topology
+}
+
+/// Читает топологию процессора из sysfs
+///
+/// `root` - корень sysfs (обычно `/sys`). Возвращает `None`, если список
+/// процессоров или их топология недоступны.
+pub fn read_sysfs_topology(root: &Path) -> Option<CpuTopology> {
+    let cpu_dir = root.join("devices/system/cpu");
+    let online = sysfs::parse_cpu_list(&sysfs::read_trimmed(&cpu_dir.join("online"))?)?;
+    if online.is_empty() {
+        return None;
+    }
+
+    // Ядро идентифицируется сокетом, кристаллом и номером ядра:
+    // core_id уникален только в пределах кристалла
+    let mut cores: BTreeMap<(u32, u32, u32), u32> = BTreeMap::new();
+    let mut packages = BTreeSet::new();
+
+    for &cpu in &online {
+        let topology_dir = cpu_dir.join(format!("cpu{}/topology", cpu));
+        let package: u32 = sysfs::read_number(&topology_dir.join("physical_package_id"))?;
+        let die: u32 = sysfs::read_number(&topology_dir.join("die_id")).unwrap_or(0);
+        let core: u32 = sysfs::read_number(&topology_dir.join("core_id"))?;
+
+        packages.insert(package);
+        *cores.entry((package, die, core)).or_default() += 1;
+    }
+
+    let mut topology = CpuTopology {
+        logical_cpus: online.len() as u32,
+        physical_cores: cores.len() as u32,
+        packages: packages.len() as u32,
+        threads_per_core: cores.values().copied().max().unwrap_or(1),
+        numa_nodes: read_sysfs_numa_nodes(root),
+        ..CpuTopology::default()
+    };
+
+    // Списки процессоров PMU гибридных процессоров Intel
+    let devices_dir = root.join("devices");
+    let read_pmu_cpus = |pmu: &str| {
+        sysfs::read_trimmed(&devices_dir.join(pmu).join("cpus"))
+            .and_then(|list| sysfs::parse_cpu_list(&list))
+    };
+    if let (Some(performance), Some(efficiency)) =
+        (read_pmu_cpus("cpu_core"), read_pmu_cpus("cpu_atom"))
+    {
+        topology.performance_cpus = performance;
+        topology.efficiency_cpus = efficiency;
+    }
+
+    Some(topology)
+}
+
+/// Читает узлы NUMA из `/sys/devices/system/node/node*/cpulist`
+fn read_sysfs_numa_nodes(root: &Path) -> Vec<NumaNode> {
+    let Ok(entries) = std::fs::read_dir(root.join("devices/system/node")) else {
+        return Vec::new();
+    };
+
+    let mut nodes: Vec<NumaNode> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let id = entry
+                .file_name()
+                .to_string_lossy()
+                .strip_prefix("node")?
+                .parse()
+                .ok()?;
+            let cpus = sysfs::parse_cpu_list(&sysfs::read_trimmed(&entry.path().join("cpulist"))?)?;
+            Some(NumaNode { id, cpus })
+        })
+        .collect();
+
+    nodes.sort_by_key(|node| node.id);
+    nodes
+}
+
+/// Определяет тип ядра, на котором выполняется текущий поток, по листу
+/// CPUID 0x1A
+///
+/// Возвращает `None`, если процессор не гибридный (бит Hybrid в листе 7.0
+/// EDX сброшен) или лист 0x1A не поддерживается.
+#[cfg_attr(not(target_arch = "x86_64"), allow(dead_code))]
+pub fn core_type_from_cpuid<R: CpuIdReader>(reader: &R) -> Option<CoreType> {
+    let max_leaf = reader.cpuid1(0).eax;
+    if max_leaf < 0x1A {
+        return None;
+    }
+
+    let is_hybrid = reader.cpuid2(7, 0).edx & (1 << 15) != 0;
+    if !is_hybrid {
+        return None;
+    }
+
+    decode_core_type(reader.cpuid2(0x1A, 0))
+}
+
+/// Декодирует тип ядра из EAX[31:24] листа CPUID 0x1A
+fn decode_core_type(result: CpuIdResult) -> Option<CoreType> {
+    match result.eax >> 24 {
+        0x20 => Some(CoreType::Efficiency),
+        0x40 => Some(CoreType::Performance),
+        _ => None,
+    }
+}
+
+/// Определяет типы ядер, поочередно привязывая поток к каждому логическому
+/// процессору и выполняя CPUID 0x1A
+///
+/// Привязка выполняется в отдельном потоке, чтобы не изменять привязку
+/// вызывающего потока.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn read_core_types_by_affinity(logical_cpus: u32) -> Vec<(u32, CoreType)> {
+    use raw_cpuid::CpuIdReaderNative;
+
+    // Проверка на текущем процессоре: на негибридных процессорах
+    // привязка не нужна
+    if core_type_from_cpuid(&CpuIdReaderNative).is_none() {
+        return Vec::new();
+    }
+
+    std::thread::spawn(move || {
+        let mut core_types = Vec::new();
+        for cpu in 0..logical_cpus.min(libc::CPU_SETSIZE as u32) {
+            // SAFETY: cpu_set_t инициализируется нулями и заполняется
+            // макросами libc; sched_setaffinity(0) меняет привязку только
+            // текущего потока
+            let pinned = unsafe {
+                let mut set: libc::cpu_set_t = std::mem::zeroed();
+                libc::CPU_SET(cpu as usize, &mut set);
+                libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+            };
+            if !pinned {
+                continue;
+            }
+            if let Some(core_type) = core_type_from_cpuid(&CpuIdReaderNative) {
+                core_types.push((cpu, core_type));
+            }
+        }
+        core_types
+    })
+    .join()
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    /// Создает файл с содержимым, включая родительские каталоги
+    fn write_file(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, format!("{}\n", content)).unwrap();
+    }
+
+    /// Описывает логический процессор в поддельном sysfs
+    fn write_cpu(root: &Path, cpu: u32, package: u32, core: u32) {
+        let dir = format!("devices/system/cpu/cpu{}/topology", cpu);
+        write_file(
+            root,
+            &format!("{}/physical_package_id", dir),
+            &package.to_string(),
+        );
+        write_file(root, &format!("{}/core_id", dir), &core.to_string());
+    }
+
+    #[test]
+    fn test_read_sysfs_topology_smp() {
+        // Два сокета по два ядра с SMT, два узла NUMA
+        let root = tempdir().unwrap();
+        write_file(root.path(), "devices/system/cpu/online", "0-7");
+        for cpu in 0..8 {
+            write_cpu(root.path(), cpu, cpu / 4, cpu % 2);
+        }
+        write_file(root.path(), "devices/system/node/node0/cpulist", "0-3");
+        write_file(root.path(), "devices/system/node/node1/cpulist", "4-7");
+        write_file(root.path(), "devices/system/node/online", "0-1");
+
+        let topology = read_sysfs_topology(root.path()).unwrap();
+        assert_eq!(topology.logical_cpus, 8);
+        assert_eq!(topology.physical_cores, 4);
+        assert_eq!(topology.packages, 2);
+        assert_eq!(topology.threads_per_core, 2);
+        assert_eq!(
+            topology.numa_nodes,
+            vec![
+                NumaNode {
+                    id: 0,
+                    cpus: vec![0, 1, 2, 3]
+                },
+                NumaNode {
+                    id: 1,
+                    cpus: vec![4, 5, 6, 7]
+                },
+            ]
+        );
+        assert!(!topology.is_hybrid());
+
+        // Без описания топологии процессоров
+        let empty = tempdir().unwrap();
+        write_file(empty.path(), "devices/system/cpu/online", "0-3");
+        assert_eq!(read_sysfs_topology(empty.path()), None);
+    }
+
+    #[test]
+    fn test_read_sysfs_topology_hybrid() {
+        // Alder Lake: 2 P-ядра с SMT (cpu0-3) и 4 E-ядра (cpu4-7)
+        let root = tempdir().unwrap();
+        write_file(root.path(), "devices/system/cpu/online", "0-7");
+        for cpu in 0..4 {
+            write_cpu(root.path(), cpu, 0, cpu / 2 * 4);
+        }
+        for cpu in 4..8 {
+            write_cpu(root.path(), cpu, 0, cpu + 4);
+        }
+        write_file(root.path(), "devices/cpu_core/cpus", "0-3");
+        write_file(root.path(), "devices/cpu_atom/cpus", "4-7");
+
+        let topology = read_sysfs_topology(root.path()).unwrap();
+        assert_eq!(topology.logical_cpus, 8);
+        assert_eq!(topology.physical_cores, 6);
+        assert_eq!(topology.threads_per_core, 2);
+        assert!(topology.numa_nodes.is_empty());
+        assert!(topology.is_hybrid());
+        assert_eq!(topology.core_type(1), Some(CoreType::Performance));
+        assert_eq!(topology.core_type(6), Some(CoreType::Efficiency));
+        assert_eq!(topology.core_type(9), None);
+        assert_eq!(
+            topology.to_string(),
+            "ядер: 6, логических процессоров: 8, сокетов: 1, узлов NUMA: 1, \
+             на P-ядрах: 4, на E-ядрах: 4"
+        );
+    }
+
+    #[test]
+    fn test_core_type_from_cpuid() {
+        let reader = |hybrid: bool, core_type: u32| {
+            move |eax: u32, _ecx: u32| match eax {
+                0 => CpuIdResult {
+                    eax: 0x20,
+                    ebx: 0,
+                    ecx: 0,
+                    edx: 0,
+                },
+                7 => CpuIdResult {
+                    eax: 0,
+                    ebx: 0,
+                    ecx: 0,
+                    edx: if hybrid { 1 << 15 } else { 0 },
+                },
+                0x1A => CpuIdResult {
+                    eax: core_type << 24 | 1,
+                    ebx: 0,
+                    ecx: 0,
+                    edx: 0,
+                },
+                _ => CpuIdResult {
+                    eax: 0,
+                    ebx: 0,
+                    ecx: 0,
+                    edx: 0,
+                },
+            }
+        };
+
+        assert_eq!(
+            core_type_from_cpuid(&reader(true, 0x40)),
+            Some(CoreType::Performance)
+        );
+        assert_eq!(
+            core_type_from_cpuid(&reader(true, 0x20)),
+            Some(CoreType::Efficiency)
+        );
+        assert_eq!(core_type_from_cpuid(&reader(false, 0x40)), None);
+    }
+}
@@ -0,0 +1,332 @@
+//! Определение гипервизора и среды контейнеризации.
+//!
+//! Гипервизор определяется по биту Hypervisor листа CPUID 1 и строке
+//! производителя листа 0x40000000, на остальных архитектурах - по
+//! `/sys/hypervisor/type`. Контейнер определяется по `/.dockerenv`,
+//! `/run/.containerenv` и `/proc/1/cgroup`.
+
+use super::sysfs;
+use raw_cpuid::{CpuId, CpuIdReader};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::path::Path;
+
+/// Гипервизор
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hypervisor {
+    /// KVM
+    Kvm,
+    /// VMware
+    Vmware,
+    /// Microsoft Hyper-V
+    HyperV,
+    /// Xen
+    Xen,
+    /// QEMU без аппаратного ускорения (эмуляция TCG)
+    QemuTcg,
+    /// Oracle VirtualBox
+    VirtualBox,
+    /// bhyve
+    Bhyve,
+    /// Другой гипервизор (строка производителя или `unknown`)
+    Other(String),
+}
+
+impl Hypervisor {
+    /// Имя гипервизора: `kvm`, `vmware`, `hyper-v`, `xen`, `qemu-tcg`, ...
+    pub fn name(&self) -> &str {
+        match self {
+            Hypervisor::Kvm => "kvm",
+            Hypervisor::Vmware => "vmware",
+            Hypervisor::HyperV => "hyper-v",
+            Hypervisor::Xen => "xen",
+            Hypervisor::QemuTcg => "qemu-tcg",
+            Hypervisor::VirtualBox => "virtualbox",
+            Hypervisor::Bhyve => "bhyve",
+            Hypervisor::Other(name) => name,
+        }
+    }
+
+    /// Определяет гипервизор по имени, возвращаемому `name`
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "kvm" => Hypervisor::Kvm,
+            "vmware" => Hypervisor::Vmware,
+            "hyper-v" => Hypervisor::HyperV,
+            "xen" => Hypervisor::Xen,
+            "qemu-tcg" => Hypervisor::QemuTcg,
+            "virtualbox" => Hypervisor::VirtualBox,
+            "bhyve" => Hypervisor::Bhyve,
+            other => Hypervisor::Other(other.to_string()),
+        }
+    }
+
+    /// Определяет гипервизор по строке производителя листа 0x40000000
+    pub fn from_vendor_id(vendor_id: &str) -> Self {
+        match vendor_id.trim_end_matches('\0') {
+            "KVMKVMKVM" => Hypervisor::Kvm,
+            "VMwareVMware" => Hypervisor::Vmware,
+            "Microsoft Hv" => Hypervisor::HyperV,
+            "XenVMMXenVMM" => Hypervisor::Xen,
+            "TCGTCGTCGTCG" => Hypervisor::QemuTcg,
+            "VBoxVBoxVBox" => Hypervisor::VirtualBox,
+            "bhyve bhyve " | "BHyVE BHyVE " => Hypervisor::Bhyve,
+            "" => Hypervisor::Other("unknown".to_string()),
+            other => Hypervisor::Other(other.trim().to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Hypervisor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Serialize for Hypervisor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Hypervisor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Hypervisor::from_name(&name))
+    }
+}
+
+/// Среда контейнеризации
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerRuntime {
+    /// Docker
+    Docker,
+    /// Podman
+    Podman,
+    /// Kubernetes (под)
+    Kubernetes,
+    /// LXC/LXD
+    Lxc,
+    /// containerd
+    Containerd,
+}
+
+impl fmt::Display for ContainerRuntime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ContainerRuntime::Docker => "Docker",
+            ContainerRuntime::Podman => "Podman",
+            ContainerRuntime::Kubernetes => "Kubernetes",
+            ContainerRuntime::Lxc => "LXC",
+            ContainerRuntime::Containerd => "containerd",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Сведения о виртуализации
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Virtualization {
+    /// Гипервизор, если процесс выполняется в виртуальной машине
+    pub hypervisor: Option<Hypervisor>,
+    /// Среда контейнеризации, если процесс выполняется в контейнере
+    pub container: Option<ContainerRuntime>,
+}
+
+impl fmt::Display for Virtualization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.hypervisor {
+            Some(hypervisor) => write!(f, "гипервизор: {}", hypervisor)?,
+            None => write!(f, "гипервизор: нет")?,
+        }
+        match &self.container {
+            Some(container) => write!(f, ", контейнер: {}", container),
+            None => write!(f, ", контейнер: нет"),
+        }
+    }
+}
+
+/// Определяет гипервизор и среду контейнеризации текущей системы
+pub fn detect_virtualization() -> Virtualization {
+    #[cfg(target_arch = "x86_64")]
+    let hypervisor = hypervisor_from_cpuid(&CpuId::new());
+
+    #[cfg(not(target_arch = "x86_64"))]
+    let hypervisor = read_sysfs_hypervisor(Path::new(sysfs::SYSFS_ROOT));
+
+    Virtualization {
+        hypervisor,
+        container: detect_container(Path::new("/")),
+    }
+}
+
+/// Определяет гипервизор по листам CPUID 1 и 0x40000000
+///
+/// Бит Hypervisor без листа 0x40000000 означает неизвестный гипервизор.
+pub fn hypervisor_from_cpuid<R: CpuIdReader>(cpuid: &CpuId<R>) -> Option<Hypervisor> {
+    if !cpuid.get_feature_info()?.has_hypervisor() {
+        return None;
+    }
+
+    let Some(info) = cpuid.get_hypervisor_info() else {
+        return Some(Hypervisor::Other("unknown".to_string()));
+    };
+
+    Some(match info.identify() {
+        raw_cpuid::Hypervisor::KVM => Hypervisor::Kvm,
+        raw_cpuid::Hypervisor::VMware => Hypervisor::Vmware,
+        raw_cpuid::Hypervisor::HyperV => Hypervisor::HyperV,
+        raw_cpuid::Hypervisor::Xen => Hypervisor::Xen,
+        raw_cpuid::Hypervisor::QEMU => Hypervisor::QemuTcg,
+        raw_cpuid::Hypervisor::Bhyve => Hypervisor::Bhyve,
+        raw_cpuid::Hypervisor::Unknown(ebx, ecx, edx) => {
+            let bytes: Vec<u8> = [ebx, ecx, edx]
+                .iter()
+                .flat_map(|register| register.to_le_bytes())
+                .collect();
+            Hypervisor::from_vendor_id(&String::from_utf8_lossy(&bytes))
+        },
+        other => Hypervisor::Other(format!("{:?}", other).to_lowercase()),
+    })
+}
+
+/// Определяет гипервизор по `/sys/hypervisor/type` (Xen на любой архитектуре)
+#[cfg_attr(target_arch = "x86_64", allow(dead_code))]
+fn read_sysfs_hypervisor(root: &Path) -> Option<Hypervisor> {
+    match sysfs::read_trimmed(&root.join("hypervisor/type"))?.as_str() {
+        "" => None,
+        "xen" => Some(Hypervisor::Xen),
+        other => Some(Hypervisor::Other(other.to_string())),
+    }
+}
+
+/// Определяет среду контейнеризации
+///
+/// `root` - корень файловой системы (обычно `/`).
+pub fn detect_container(root: &Path) -> Option<ContainerRuntime> {
+    let init_cgroup = std::fs::read_to_string(root.join("proc/1/cgroup")).unwrap_or_default();
+
+    // Под Kubernetes файлы-маркеры принадлежат среде выполнения
+    // контейнеров, поэтому путь cgroup проверяется первым
+    if init_cgroup.contains("kubepods") {
+        return Some(ContainerRuntime::Kubernetes);
+    }
+    if root.join(".dockerenv").exists() {
+        return Some(ContainerRuntime::Docker);
+    }
+    if root.join("run/.containerenv").exists() {
+        return Some(ContainerRuntime::Podman);
+    }
+
+    [
+        ("docker", ContainerRuntime::Docker),
+        ("libpod", ContainerRuntime::Podman),
+        ("containerd", ContainerRuntime::Containerd),
+        ("lxc", ContainerRuntime::Lxc),
+    ]
+    .into_iter()
+    .find(|(marker, _)| init_cgroup.contains(marker))
+    .map(|(_, runtime)| runtime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raw_cpuid::CpuIdResult;
+    use std::fs;
+    use tempfile::tempdir;
+
+    /// Формирует ответ CPUID для виртуальной машины с заданной строкой
+    /// производителя гипервизора
+    fn hypervisor_reader(
+        hypervisor_bit: bool,
+        vendor_id: &'static [u8; 12],
+    ) -> impl Fn(u32, u32) -> CpuIdResult + Clone {
+        move |eax: u32, _ecx: u32| {
+            let register = |i: usize| u32::from_le_bytes(vendor_id[i..i + 4].try_into().unwrap());
+            match eax {
+                0 => CpuIdResult {
+                    eax: 1,
+                    ebx: 0,
+                    ecx: 0,
+                    edx: 0,
+                },
+                1 => CpuIdResult {
+                    eax: 0,
+                    ebx: 0,
+                    ecx: if hypervisor_bit { 1 << 31 } else { 0 },
+                    edx: 0,
+                },
+                0x4000_0000 => CpuIdResult {
+                    eax: 0x4000_0001,
+                    ebx: register(0),
+                    ecx: register(4),
+                    edx: register(8),
+                },
+                _ => CpuIdResult {
+                    eax: 0,
+                    ebx: 0,
+                    ecx: 0,
+                    edx: 0,
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn test_hypervisor_from_cpuid() {
+        let detect = |bit: bool, vendor_id: &'static [u8; 12]| {
+            hypervisor_from_cpuid(&CpuId::with_cpuid_reader(hypervisor_reader(bit, vendor_id)))
+        };
+
+        assert_eq!(detect(true, b"KVMKVMKVM\0\0\0"), Some(Hypervisor::Kvm));
+        assert_eq!(detect(true, b"VMwareVMware"), Some(Hypervisor::Vmware));
+        assert_eq!(detect(true, b"Microsoft Hv"), Some(Hypervisor::HyperV));
+        assert_eq!(detect(true, b"XenVMMXenVMM"), Some(Hypervisor::Xen));
+        assert_eq!(detect(true, b"TCGTCGTCGTCG"), Some(Hypervisor::QemuTcg));
+        assert_eq!(detect(true, b"VBoxVBoxVBox"), Some(Hypervisor::VirtualBox));
+        assert_eq!(
+            detect(true, b"ExampleHv\0\0\0"),
+            Some(Hypervisor::Other("ExampleHv".to_string()))
+        );
+
+        // Без бита Hypervisor лист 0x40000000 не учитывается
+        assert_eq!(detect(false, b"KVMKVMKVM\0\0\0"), None);
+    }
+
+    #[test]
+    fn test_detect_container() {
+        let root = tempdir().unwrap();
+        fs::create_dir_all(root.path().join("proc/1")).unwrap();
+        let cgroup = root.path().join("proc/1/cgroup");
+
+        fs::write(&cgroup, "0::/init.scope\n").unwrap();
+        assert_eq!(detect_container(root.path()), None);
+
+        fs::write(&cgroup, "0::/system.slice/containerd.service\n").unwrap();
+        assert_eq!(
+            detect_container(root.path()),
+            Some(ContainerRuntime::Containerd)
+        );
+
+        // Пространство имен cgroup скрывает путь, остается файл-маркер
+        fs::write(&cgroup, "0::/\n").unwrap();
+        fs::write(root.path().join(".dockerenv"), "").unwrap();
+        assert_eq!(
+            detect_container(root.path()),
+            Some(ContainerRuntime::Docker)
+        );
+
+        fs::write(
+            &cgroup,
+            "12:cpu,cpuacct:/kubepods/burstable/pod1/docker-abc.scope\n",
+        )
+        .unwrap();
+        assert_eq!(
+            detect_container(root.path()),
+            Some(ContainerRuntime::Kubernetes)
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Раскладка описания сборки `core_lib` (символ `core_lib_describe`).
+//!
+//! Библиотека экспортирует описание как статические данные, а приложение
+//! читает их после загрузки библиотеки, не выполняя ее код. Константы и
+//! раскладка определены здесь, чтобы обе стороны использовали одно и то
+//! же определение. Модуль не зависит от стандартной библиотеки.
+
+use core::slice;
+
+/// Сигнатура описания
+pub const DESCRIPTOR_MAGIC: [u8; 8] = *b"CORELIB\0";
+
+/// Версия формата описания
+pub const DESCRIPTOR_VERSION: u32 = 1;
+
+/// Версия интерфейса между приложением и библиотекой (`set_host_info`,
+/// `run`); библиотеки с другой версией не загружаются
+pub const ABI_VERSION: u32 = 1;
+
+/// Описание сборки библиотеки
+///
+/// Раскладка памяти зафиксирована (`repr(C)`). Поле `json` указывает на
+/// `json_len` байт JSON в кодировке UTF-8 (без завершающего нуля).
+#[repr(C)]
+pub struct CoreLibDescriptor {
+    /// Сигнатура `CORELIB\0`
+    pub magic: [u8; 8],
+    /// Версия формата описания
+    pub descriptor_version: u32,
+    /// Версия интерфейса с приложением
+    pub abi_version: u32,
+    /// Длина JSON в байтах
+    pub json_len: usize,
+    /// Указатель на JSON
+    pub json: *const u8,
+}
+
+// SAFETY: описание неизменяемо и указывает на статические данные
+unsafe impl Sync for CoreLibDescriptor {}
+
+impl CoreLibDescriptor {
+    /// Описание текущих версий формата и ABI с JSON `json`
+    pub const fn new(json: &'static str) -> Self {
+        Self {
+            magic: DESCRIPTOR_MAGIC,
+            descriptor_version: DESCRIPTOR_VERSION,
+            abi_version: ABI_VERSION,
+            json_len: json.len(),
+            json: json.as_ptr(),
+        }
+    }
+
+    /// Проверяет сигнатуру и версию формата
+    pub fn is_supported(&self) -> bool {
+        self.magic == DESCRIPTOR_MAGIC && self.descriptor_version == DESCRIPTOR_VERSION
+    }
+
+    /// Байты JSON
+    ///
+    /// # Safety
+    ///
+    /// `json` должен указывать на `json_len` байт, доступных, пока
+    /// существует описание; для описания из загруженной библиотеки это
+    /// выполняется, пока библиотека загружена.
+    pub unsafe fn json_bytes(&self) -> &[u8] {
+        slice::from_raw_parts(self.json, self.json_len)
+    }
+}
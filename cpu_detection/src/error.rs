@@ -0,0 +1,10 @@
+//! Тип ошибки библиотеки определения процессора.
+
+use alloc::string::String;
+use thiserror::Error;
+
+/// Ошибка определения процессора, разбора профиля или имени набора
+/// инструкций
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{0}")]
+pub struct CpuDetectionError(pub String);
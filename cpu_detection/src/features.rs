@@ -0,0 +1,840 @@
+//! Модуль с типизированным описанием наборов инструкций процессора.
+//!
+//! Определяет перечисление `CpuFeature` с каноническими именами и
+//! синонимами, битовое множество `FeatureSet` и уровни микроархитектуры
+//! x86-64. Модуль не зависит от стандартной библиотеки.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+use crate::error::CpuDetectionError;
+
+/// Объявляет перечисление `CpuFeature` вместе с каноническими именами
+/// и синонимами каждого набора инструкций
+macro_rules! cpu_features {
+    ($($(#[$doc:meta])* $variant:ident => $name:literal $(| $alias:literal)*,)*) => {
+        /// Набор инструкций (расширение ISA) процессора
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum CpuFeature {
+            $($(#[$doc])* $variant,)*
+        }
+
+        impl CpuFeature {
+            /// Все известные наборы инструкций в порядке объявления
+            pub const ALL: &'static [CpuFeature] = &[$(CpuFeature::$variant,)*];
+
+            /// Каноническое имя (совпадает с именем `target_feature` в rustc)
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(CpuFeature::$variant => $name,)*
+                }
+            }
+
+            /// Дополнительные имена, под которыми встречается набор инструкций
+            fn aliases(self) -> &'static [&'static str] {
+                match self {
+                    $(CpuFeature::$variant => &[$($alias),*],)*
+                }
+            }
+        }
+    };
+}
+
+cpu_features! {
+    // x86_64, лист CPUID 1
+    /// Условные пересылки CMOVcc
+    Cmov => "cmov",
+    /// Инструкция CMPXCHG8B
+    Cmpxchg8b => "cmpxchg8b" | "cx8",
+    /// Инструкции FXSAVE/FXRSTOR
+    Fxsr => "fxsr" | "fxsave",
+    /// MMX
+    Mmx => "mmx",
+    /// SSE
+    Sse => "sse",
+    /// SSE2
+    Sse2 => "sse2",
+    /// SSE3
+    Sse3 => "sse3" | "pni",
+    /// Умножение без переноса PCLMULQDQ
+    Pclmulqdq => "pclmulqdq" | "pclmul",
+    /// SSSE3
+    Ssse3 => "ssse3",
+    /// FMA3
+    Fma => "fma" | "fma3",
+    /// Инструкция CMPXCHG16B
+    Cmpxchg16b => "cmpxchg16b" | "cx16",
+    /// SSE4.1
+    Sse41 => "sse4.1",
+    /// SSE4.2
+    Sse42 => "sse4.2",
+    /// Инструкция MOVBE
+    Movbe => "movbe",
+    /// Инструкция POPCNT
+    Popcnt => "popcnt",
+    /// AES-NI на x86_64, инструкции AES на aarch64
+    Aes => "aes" | "aesni",
+    /// Инструкции XSAVE/XRSTOR
+    Xsave => "xsave",
+    /// AVX
+    Avx => "avx",
+    /// Преобразования половинной точности F16C
+    F16c => "f16c",
+    /// Инструкция RDRAND
+    Rdrand => "rdrand",
+    // x86_64, лист CPUID 7.0
+    /// Инструкции RDFSBASE/WRFSBASE
+    Fsgsbase => "fsgsbase",
+    /// BMI1
+    Bmi1 => "bmi1" | "bmi",
+    /// Аппаратная элизия блокировок HLE
+    Hle => "hle",
+    /// AVX2
+    Avx2 => "avx2",
+    /// BMI2
+    Bmi2 => "bmi2",
+    /// Улучшенные REP MOVSB/STOSB
+    Ermsb => "ermsb" | "erms",
+    /// Транзакционная память RTM
+    Rtm => "rtm",
+    /// AVX-512 Foundation
+    Avx512f => "avx512f",
+    /// AVX-512 Doubleword and Quadword
+    Avx512dq => "avx512dq",
+    /// Инструкция RDSEED
+    Rdseed => "rdseed",
+    /// Сложение с переносом ADCX/ADOX
+    Adx => "adx",
+    /// AVX-512 Integer Fused Multiply-Add
+    Avx512ifma => "avx512ifma",
+    /// Инструкция CLFLUSHOPT
+    Clflushopt => "clflushopt",
+    /// Инструкция CLWB
+    Clwb => "clwb",
+    /// AVX-512 Prefetch
+    Avx512pf => "avx512pf",
+    /// AVX-512 Exponential and Reciprocal
+    Avx512er => "avx512er",
+    /// AVX-512 Conflict Detection
+    Avx512cd => "avx512cd",
+    /// SHA
+    Sha => "sha" | "sha_ni",
+    /// AVX-512 Byte and Word
+    Avx512bw => "avx512bw",
+    /// AVX-512 Vector Length
+    Avx512vl => "avx512vl",
+    /// AVX-512 Vector Byte Manipulation
+    Avx512vbmi => "avx512vbmi",
+    /// Ключи защиты памяти PKU
+    Pku => "pku",
+    /// Инструкции UMONITOR/UMWAIT/TPAUSE
+    Waitpkg => "waitpkg",
+    /// AVX-512 Vector Byte Manipulation 2
+    Avx512vbmi2 => "avx512vbmi2",
+    /// Арифметика в поле Галуа GFNI
+    Gfni => "gfni",
+    /// Векторный AES
+    Vaes => "vaes",
+    /// Векторный PCLMULQDQ
+    Vpclmulqdq => "vpclmulqdq",
+    /// AVX-512 Vector Neural Network Instructions
+    Avx512vnni => "avx512vnni",
+    /// AVX-512 Bit Algorithms
+    Avx512bitalg => "avx512bitalg",
+    /// AVX-512 VPOPCNTD/VPOPCNTQ
+    Avx512vpopcntdq => "avx512vpopcntdq",
+    /// Инструкция RDPID
+    Rdpid => "rdpid",
+    /// AVX-512 4-iteration Vector Neural Network Instructions
+    Avx5124vnniw => "avx5124vnniw",
+    /// AVX-512 4-iteration Fused Multiply-Add
+    Avx5124fmaps => "avx5124fmaps",
+    /// AVX-512 VP2INTERSECT
+    Avx512vp2intersect => "avx512vp2intersect",
+    /// AMX BF16
+    AmxBf16 => "amx-bf16",
+    /// AVX-512 FP16
+    Avx512fp16 => "avx512fp16",
+    /// AMX TILE
+    AmxTile => "amx-tile",
+    /// AMX INT8
+    AmxInt8 => "amx-int8",
+    // x86_64, лист CPUID 7.1
+    /// AVX-VNNI (VEX-кодирование)
+    Avxvnni => "avxvnni",
+    /// AVX-512 BF16
+    Avx512bf16 => "avx512bf16",
+    /// AVX-IFMA
+    Avxifma => "avxifma",
+    /// AVX-VNNI-INT8
+    Avxvnniint8 => "avxvnniint8",
+    /// AVX-NE-CONVERT
+    Avxneconvert => "avxneconvert",
+    /// AVX-VNNI-INT16
+    Avxvnniint16 => "avxvnniint16",
+    /// AVX10
+    Avx10 => "avx10",
+    // x86_64, лист CPUID 0xD
+    /// Инструкция XSAVEOPT
+    Xsaveopt => "xsaveopt",
+    /// Инструкция XSAVEC
+    Xsavec => "xsavec",
+    /// Инструкции XSAVES/XRSTORS
+    Xsaves => "xsaves",
+    // x86_64, лист CPUID 0x80000001
+    /// LAHF/SAHF в 64-битном режиме
+    Lahfsahf => "lahfsahf" | "lahf_lm",
+    /// Инструкция LZCNT
+    Lzcnt => "lzcnt" | "abm",
+    /// SSE4a (AMD)
+    Sse4a => "sse4a",
+    /// Инструкции PREFETCHW
+    Prfchw => "prfchw" | "prefetchw" | "3dnowprefetch",
+    /// XOP (AMD)
+    Xop => "xop",
+    /// FMA4 (AMD)
+    Fma4 => "fma4",
+    /// Trailing Bit Manipulation (AMD)
+    Tbm => "tbm",
+    /// Инструкция RDTSCP
+    Rdtscp => "rdtscp",
+    // aarch64
+    /// Advanced SIMD (NEON)
+    Neon => "neon" | "asimd",
+    /// Полиномиальное умножение PMULL/PMULL2
+    Pmull => "pmull",
+    /// SHA-1 и SHA-256
+    Sha2 => "sha2",
+    /// SHA-512 и SHA-3
+    Sha3 => "sha3",
+    /// Контрольные суммы CRC32
+    Crc => "crc" | "crc32",
+    /// Атомарные операции LSE
+    Lse => "lse" | "atomics",
+    /// Арифметика половинной точности (скалярная и векторная)
+    Fp16 => "fp16" | "fphp" | "asimdhp",
+    /// Загрузка с семантикой release consistent (LDAPR)
+    Rcpc => "rcpc" | "lrcpc",
+    /// Скалярное произведение SDOT/UDOT
+    Dotprod => "dotprod" | "asimddp",
+    /// Scalable Vector Extension
+    Sve => "sve",
+    /// Scalable Vector Extension 2
+    Sve2 => "sve2",
+    /// Умножение матриц INT8
+    I8mm => "i8mm",
+    /// Формат BFloat16
+    Bf16 => "bf16",
+    // riscv64
+    /// Целочисленное умножение и деление (M)
+    M => "m",
+    /// Атомарные операции (A)
+    A => "a",
+    /// Числа с плавающей точкой одинарной точности (F)
+    F => "f",
+    /// Числа с плавающей точкой двойной точности (D)
+    D => "d",
+    /// Сжатые инструкции (C)
+    C => "c",
+    /// Векторное расширение RVV 1.0 (V)
+    V => "v" | "rvv",
+    /// Сложение со сдвигом для адресной арифметики (Zba)
+    Zba => "zba",
+    /// Базовые битовые операции (Zbb)
+    Zbb => "zbb",
+    /// Операции с отдельными битами (Zbs)
+    Zbs => "zbs",
+    /// Управление кэшем: очистка и инвалидация блоков (Zicbom)
+    Zicbom => "zicbom",
+    /// Обнуление блоков кэша (Zicboz)
+    Zicboz => "zicboz",
+    /// Векторные битовые операции (Zvbb)
+    Zvbb => "zvbb",
+    // powerpc64
+    /// Векторное расширение AltiVec (VMX)
+    Altivec => "altivec" | "vmx",
+    /// Vector-Scalar Extension
+    Vsx => "vsx",
+    /// Векторные инструкции ISA 2.07 (POWER8)
+    Power8Vector => "power8-vector" | "arch_2_07",
+    /// Векторные инструкции ISA 3.0 (POWER9)
+    Power9Vector => "power9-vector" | "arch_3_00",
+    /// Векторные инструкции ISA 3.1 (POWER10)
+    Power10Vector => "power10-vector" | "arch_3_1",
+    /// Matrix-Multiply Assist (POWER10)
+    Mma => "mma",
+    // s390x
+    /// Векторное расширение z/Architecture (z13)
+    Vector => "vector" | "vx" | "vxrs",
+    /// Vector Enhancements Facility 1 (z14)
+    VectorEnhancements1 => "vector-enhancements-1" | "vxe" | "vxrs_ext",
+    /// Vector Enhancements Facility 2 (z15)
+    VectorEnhancements2 => "vector-enhancements-2" | "vxe2" | "vxrs_ext2",
+}
+
+impl CpuFeature {
+    /// Ищет набор инструкций по имени с учетом синонимов
+    ///
+    /// Сравнение выполняется без учета регистра и разделителей `.`, `_`
+    /// и `-`, поэтому "sse4.2", "sse4_2" и "SSE42" считаются одним именем.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let normalized = normalize_name(name);
+        if normalized.is_empty() {
+            return None;
+        }
+
+        CpuFeature::ALL.iter().copied().find(|feature| {
+            normalize_name(feature.name()) == normalized
+                || feature
+                    .aliases()
+                    .iter()
+                    .any(|alias| normalize_name(alias) == normalized)
+        })
+    }
+
+    /// Наборы инструкций, без которых данный набор не может использоваться
+    /// на архитектуре `arch`
+    ///
+    /// Отключение любого из них отключает и этот набор. Зависимости AES,
+    /// общего для x86_64 и aarch64, различаются: SSE2 на x86_64 и NEON на
+    /// aarch64; на неизвестной архитектуре у AES зависимостей нет.
+    pub fn requires(self, arch: &str) -> &'static [CpuFeature] {
+        use CpuFeature::*;
+
+        match self {
+            Aes => match arch {
+                "x86_64" | "x86" => &[Sse2],
+                "aarch64" | "arm" => &[Neon],
+                _ => &[],
+            },
+            Sse2 => &[Sse],
+            Sse3 | Pclmulqdq | Gfni | Sha => &[Sse2],
+            Ssse3 | Sse4a => &[Sse3],
+            Sse41 => &[Ssse3],
+            Sse42 => &[Sse41],
+            Avx => &[Sse42, Xsave],
+            Avx2 | Fma | F16c => &[Avx],
+            Vaes => &[Avx, Aes],
+            Vpclmulqdq => &[Avx, Pclmulqdq],
+            Fma4 => &[Avx, Sse4a],
+            Xop => &[Fma4],
+            Avxvnni | Avxifma | Avxvnniint8 | Avxneconvert | Avxvnniint16 => &[Avx2],
+            Avx512f => &[Avx2, Fma, F16c],
+            Avx512dq | Avx512ifma | Avx512pf | Avx512er | Avx512cd | Avx512bw | Avx512vl
+            | Avx512vnni | Avx512vpopcntdq | Avx5124vnniw | Avx5124fmaps | Avx512vp2intersect
+            | Avx10 => &[Avx512f],
+            Avx512vbmi | Avx512vbmi2 | Avx512bitalg | Avx512bf16 => &[Avx512bw],
+            Avx512fp16 => &[Avx512bw, Avx512dq, Avx512vl],
+            AmxBf16 | AmxInt8 => &[AmxTile],
+            Xsaveopt | Xsavec | Xsaves => &[Xsave],
+            Pmull => &[Aes],
+            Sha2 | Fp16 | Dotprod | I8mm | Bf16 | Rcpc => &[Neon],
+            Sha3 => &[Sha2],
+            Sve => &[Fp16],
+            Sve2 => &[Sve],
+            D => &[F],
+            V => &[D],
+            Zvbb => &[V],
+            Vsx => &[Altivec],
+            Power8Vector => &[Vsx],
+            Power9Vector => &[Power8Vector],
+            Power10Vector => &[Power9Vector],
+            Mma => &[Power10Vector],
+            VectorEnhancements1 => &[Vector],
+            VectorEnhancements2 => &[VectorEnhancements1],
+            _ => &[],
+        }
+    }
+
+    /// Индекс бита в `FeatureSet`
+    fn bit(self) -> usize {
+        self as usize
+    }
+}
+
+/// Приводит имя набора инструкций к виду для сравнения
+fn normalize_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .filter(|c| !matches!(c, '.' | '_' | '-'))
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+impl fmt::Display for CpuFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for CpuFeature {
+    type Err = CpuDetectionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CpuFeature::from_name(s).ok_or_else(|| {
+            CpuDetectionError(format!("Неизвестный набор инструкций: {:?}", s.trim()))
+        })
+    }
+}
+
+impl Serialize for CpuFeature {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for CpuFeature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Количество 64-битных слов в `FeatureSet`
+const FEATURE_WORDS: usize = 2;
+
+/// Множество наборов инструкций в виде битовой маски
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FeatureSet {
+    words: [u64; FEATURE_WORDS],
+}
+
+impl FeatureSet {
+    /// Создает пустое множество
+    pub const fn new() -> Self {
+        FeatureSet {
+            words: [0; FEATURE_WORDS],
+        }
+    }
+
+    /// Добавляет набор инструкций, возвращает `true`, если его не было
+    pub fn insert(&mut self, feature: CpuFeature) -> bool {
+        let (word, mask) = Self::position(feature);
+        let added = self.words[word] & mask == 0;
+        self.words[word] |= mask;
+        added
+    }
+
+    /// Удаляет набор инструкций, возвращает `true`, если он был
+    pub fn remove(&mut self, feature: CpuFeature) -> bool {
+        let (word, mask) = Self::position(feature);
+        let removed = self.words[word] & mask != 0;
+        self.words[word] &= !mask;
+        removed
+    }
+
+    /// Проверяет наличие набора инструкций
+    pub fn contains(&self, feature: CpuFeature) -> bool {
+        let (word, mask) = Self::position(feature);
+        self.words[word] & mask != 0
+    }
+
+    /// Количество наборов инструкций в множестве
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Проверяет, пусто ли множество
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    /// Проверяет, что все элементы множества содержатся в `other`
+    pub fn is_subset(&self, other: &FeatureSet) -> bool {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .all(|(a, b)| a & !b == 0)
+    }
+
+    /// Проверяет, что множество содержит все элементы `other`
+    pub fn is_superset(&self, other: &FeatureSet) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Объединение множеств
+    pub fn union(&self, other: &FeatureSet) -> FeatureSet {
+        self.combine(other, |a, b| a | b)
+    }
+
+    /// Пересечение множеств
+    pub fn intersection(&self, other: &FeatureSet) -> FeatureSet {
+        self.combine(other, |a, b| a & b)
+    }
+
+    /// Разность множеств: элементы `self`, отсутствующие в `other`
+    pub fn difference(&self, other: &FeatureSet) -> FeatureSet {
+        self.combine(other, |a, b| a & !b)
+    }
+
+    /// Множество вместе со всеми наборами инструкций, которые прямо или
+    /// транзитивно зависят от его элементов на архитектуре `arch` (см.
+    /// `CpuFeature::requires`)
+    pub fn with_dependents(&self, arch: &str) -> FeatureSet {
+        let mut closure = *self;
+        loop {
+            let mut changed = false;
+            for &feature in CpuFeature::ALL {
+                if !closure.contains(feature)
+                    && feature
+                        .requires(arch)
+                        .iter()
+                        .any(|&req| closure.contains(req))
+                {
+                    changed |= closure.insert(feature);
+                }
+            }
+            if !changed {
+                return closure;
+            }
+        }
+    }
+
+    /// Итератор по элементам в порядке объявления `CpuFeature`
+    pub fn iter(&self) -> impl Iterator<Item = CpuFeature> + '_ {
+        CpuFeature::ALL
+            .iter()
+            .copied()
+            .filter(move |&feature| self.contains(feature))
+    }
+
+    fn position(feature: CpuFeature) -> (usize, u64) {
+        let bit = feature.bit();
+        (bit / 64, 1 << (bit % 64))
+    }
+
+    fn combine(&self, other: &FeatureSet, op: impl Fn(u64, u64) -> u64) -> FeatureSet {
+        let mut result = FeatureSet::new();
+        for (i, word) in result.words.iter_mut().enumerate() {
+            *word = op(self.words[i], other.words[i]);
+        }
+        result
+    }
+}
+
+impl FromIterator<CpuFeature> for FeatureSet {
+    fn from_iter<I: IntoIterator<Item = CpuFeature>>(iter: I) -> Self {
+        let mut set = FeatureSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<CpuFeature> for FeatureSet {
+    fn extend<I: IntoIterator<Item = CpuFeature>>(&mut self, iter: I) {
+        for feature in iter {
+            self.insert(feature);
+        }
+    }
+}
+
+impl fmt::Debug for FeatureSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries(self.iter().map(CpuFeature::name))
+            .finish()
+    }
+}
+
+impl fmt::Display for FeatureSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, feature) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            f.write_str(feature.name())?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for FeatureSet {
+    type Err = CpuDetectionError;
+
+    /// Разбирает список наборов инструкций, разделенных запятыми
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .filter(|name| !name.trim().is_empty())
+            .map(str::parse)
+            .collect()
+    }
+}
+
+impl Serialize for FeatureSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for feature in self.iter() {
+            seq.serialize_element(&feature)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for FeatureSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let features = Vec::<CpuFeature>::deserialize(deserializer)?;
+        Ok(features.into_iter().collect())
+    }
+}
+
+/// Уровни микроархитектуры x86-64 согласно psABI
+///
+/// Соответствуют значениям `-C target-cpu` в rustc: `x86-64`, `x86-64-v2`,
+/// `x86-64-v3` и `x86-64-v4`. Каждый следующий уровень включает все
+/// инструкции предыдущего.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum X86_64Level {
+    /// Базовый уровень x86-64 (CMOV, CX8, FXSR, MMX, SSE, SSE2)
+    V1,
+    /// Уровень x86-64-v2 (CX16, LAHF/SAHF, POPCNT, SSE3, SSE4.1, SSE4.2, SSSE3)
+    V2,
+    /// Уровень x86-64-v3 (AVX, AVX2, BMI1, BMI2, F16C, FMA, LZCNT, MOVBE, XSAVE)
+    V3,
+    /// Уровень x86-64-v4 (AVX512F, AVX512BW, AVX512CD, AVX512DQ, AVX512VL)
+    V4,
+}
+
+impl X86_64Level {
+    /// Все уровни в порядке возрастания
+    pub const ALL: [X86_64Level; 4] = [
+        X86_64Level::V1,
+        X86_64Level::V2,
+        X86_64Level::V3,
+        X86_64Level::V4,
+    ];
+
+    /// Инструкции, которые добавляет данный уровень к предыдущему
+    pub fn own_features(self) -> &'static [CpuFeature] {
+        match self {
+            X86_64Level::V1 => &[
+                CpuFeature::Cmov,
+                CpuFeature::Cmpxchg8b,
+                CpuFeature::Fxsr,
+                CpuFeature::Mmx,
+                CpuFeature::Sse,
+                CpuFeature::Sse2,
+            ],
+            X86_64Level::V2 => &[
+                CpuFeature::Cmpxchg16b,
+                CpuFeature::Lahfsahf,
+                CpuFeature::Popcnt,
+                CpuFeature::Sse3,
+                CpuFeature::Sse41,
+                CpuFeature::Sse42,
+                CpuFeature::Ssse3,
+            ],
+            X86_64Level::V3 => &[
+                CpuFeature::Avx,
+                CpuFeature::Avx2,
+                CpuFeature::Bmi1,
+                CpuFeature::Bmi2,
+                CpuFeature::F16c,
+                CpuFeature::Fma,
+                CpuFeature::Lzcnt,
+                CpuFeature::Movbe,
+                CpuFeature::Xsave,
+            ],
+            X86_64Level::V4 => &[
+                CpuFeature::Avx512f,
+                CpuFeature::Avx512bw,
+                CpuFeature::Avx512cd,
+                CpuFeature::Avx512dq,
+                CpuFeature::Avx512vl,
+            ],
+        }
+    }
+
+    /// Полный набор инструкций, необходимых для данного уровня
+    pub fn required_features(self) -> FeatureSet {
+        X86_64Level::ALL
+            .into_iter()
+            .filter(|&level| level <= self)
+            .flat_map(|level| level.own_features().iter().copied())
+            .collect()
+    }
+
+    /// Имя уровня в формате `-C target-cpu` rustc
+    pub fn target_cpu(self) -> &'static str {
+        match self {
+            X86_64Level::V1 => "x86-64",
+            X86_64Level::V2 => "x86-64-v2",
+            X86_64Level::V3 => "x86-64-v3",
+            X86_64Level::V4 => "x86-64-v4",
+        }
+    }
+
+    /// Определяет уровень по имени в формате `-C target-cpu`
+    pub fn from_target_cpu(name: &str) -> Option<Self> {
+        X86_64Level::ALL
+            .into_iter()
+            .find(|level| level.target_cpu() == name)
+    }
+
+    /// Вычисляет наивысший уровень, все инструкции которого есть в списке
+    ///
+    /// Возвращает `None`, если не выполнены даже требования базового уровня.
+    pub fn classify(features: &FeatureSet) -> Option<Self> {
+        X86_64Level::ALL
+            .into_iter()
+            .rev()
+            .find(|level| features.is_superset(&level.required_features()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_names_are_unique() {
+        // Битовая маска должна вмещать все наборы инструкций
+        assert!(CpuFeature::ALL.len() <= FEATURE_WORDS * 64);
+
+        // Имена и синонимы не должны пересекаться после нормализации
+        let mut names: Vec<String> = CpuFeature::ALL
+            .iter()
+            .flat_map(|f| core::iter::once(f.name()).chain(f.aliases().iter().copied()))
+            .map(normalize_name)
+            .collect();
+        let total = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), total);
+
+        for &feature in CpuFeature::ALL {
+            assert_eq!(CpuFeature::from_name(feature.name()), Some(feature));
+        }
+    }
+
+    #[test]
+    fn test_feature_aliases() {
+        for name in ["sse4.2", "sse4_2", "SSE42", " Sse4-2 "] {
+            assert_eq!(name.parse::<CpuFeature>().unwrap(), CpuFeature::Sse42);
+        }
+        assert_eq!("aesni".parse::<CpuFeature>().unwrap(), CpuFeature::Aes);
+        assert_eq!("asimd".parse::<CpuFeature>().unwrap(), CpuFeature::Neon);
+
+        let err = "avx3".parse::<CpuFeature>().unwrap_err();
+        assert!(matches!(err, CpuDetectionError(_)));
+        assert!(err.to_string().contains("avx3"));
+    }
+
+    #[test]
+    fn test_feature_set_operations() {
+        let set: FeatureSet = "avx2,avx,sse4.2".parse().unwrap();
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(CpuFeature::Avx2));
+        assert!(!set.contains(CpuFeature::Avx512f));
+        assert_eq!(set.to_string(), "sse4.2,avx,avx2");
+
+        let subset: FeatureSet = [CpuFeature::Avx, CpuFeature::Sse42].into_iter().collect();
+        assert!(subset.is_subset(&set));
+        assert!(set.is_superset(&subset));
+        assert!(!set.is_subset(&subset));
+
+        assert_eq!(
+            set.difference(&subset).iter().collect::<Vec<_>>(),
+            [CpuFeature::Avx2]
+        );
+        assert_eq!(set.intersection(&subset), subset);
+        assert_eq!(subset.union(&set), set);
+
+        let mut set = set;
+        assert!(set.remove(CpuFeature::Avx2));
+        assert!(!set.remove(CpuFeature::Avx2));
+        assert_eq!(set, subset);
+
+        assert!("avx2,unknown".parse::<FeatureSet>().is_err());
+        assert!("".parse::<FeatureSet>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_feature_set_serde() {
+        let set: FeatureSet = "avx2,sse4_2,neon".parse().unwrap();
+        let json = serde_json::to_string(&set).unwrap();
+        assert_eq!(json, r#"["sse4.2","avx2","neon"]"#);
+
+        let parsed: FeatureSet = serde_json::from_str(r#"["SSE42","avx2","asimd"]"#).unwrap();
+        assert_eq!(parsed, set);
+
+        assert!(serde_json::from_str::<FeatureSet>(r#"["avx9"]"#).is_err());
+    }
+
+    #[test]
+    fn test_with_dependents() {
+        // Отключение AVX отключает AVX2, FMA и все, что от них зависит
+        let closure = FeatureSet::from_iter([CpuFeature::Avx]).with_dependents("x86_64");
+        for feature in [
+            CpuFeature::Avx,
+            CpuFeature::Avx2,
+            CpuFeature::Fma,
+            CpuFeature::Avx512f,
+            CpuFeature::Avx512vbmi,
+            CpuFeature::Avxvnni,
+            CpuFeature::Vaes,
+        ] {
+            assert!(closure.contains(feature), "{}", feature);
+        }
+        assert!(!closure.contains(CpuFeature::Sse42));
+        assert!(!closure.contains(CpuFeature::Bmi2));
+
+        let closure = FeatureSet::from_iter([CpuFeature::Neon]).with_dependents("aarch64");
+        assert!(closure.contains(CpuFeature::Sve2));
+        assert!(closure.contains(CpuFeature::Pmull));
+        assert!(closure.contains(CpuFeature::Aes));
+        assert!(!closure.contains(CpuFeature::Crc));
+
+        // AES зависит только от набора своей архитектуры
+        let neon = FeatureSet::from_iter([CpuFeature::Neon]);
+        assert!(!neon.with_dependents("x86_64").contains(CpuFeature::Aes));
+        let sse2 = FeatureSet::from_iter([CpuFeature::Sse2]);
+        assert!(sse2.with_dependents("x86_64").contains(CpuFeature::Aes));
+        assert!(!sse2.with_dependents("aarch64").contains(CpuFeature::Aes));
+
+        // Зависимости не должны быть циклическими
+        for arch in ["x86_64", "aarch64", ""] {
+            for &feature in CpuFeature::ALL {
+                for &required in feature.requires(arch) {
+                    let closure = FeatureSet::from_iter([feature]).with_dependents(arch);
+                    assert!(!closure.contains(required), "{} <-> {}", feature, required);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_x86_64_level_classify() {
+        // Для каждого уровня полный набор его инструкций дает ровно этот уровень
+        for level in X86_64Level::ALL {
+            assert_eq!(
+                X86_64Level::classify(&level.required_features()),
+                Some(level)
+            );
+        }
+
+        // Отсутствие одной инструкции понижает уровень
+        let mut features = X86_64Level::V4.required_features();
+        features.remove(CpuFeature::Lzcnt);
+        assert_eq!(X86_64Level::classify(&features), Some(X86_64Level::V2));
+
+        // Инструкции более высокого уровня не учитываются без предыдущих
+        let features: FeatureSet = X86_64Level::V4.own_features().iter().copied().collect();
+        assert_eq!(X86_64Level::classify(&features), None);
+    }
+
+    #[test]
+    fn test_x86_64_level_target_cpu() {
+        for level in X86_64Level::ALL {
+            assert_eq!(
+                X86_64Level::from_target_cpu(level.target_cpu()),
+                Some(level)
+            );
+        }
+        assert_eq!(X86_64Level::from_target_cpu("avx2"), None);
+    }
+}
@@ -0,0 +1,46 @@
+//! Определение архитектуры процессора и поддерживаемых наборов инструкций.
+//!
+//! Библиотека используется основным приложением для выбора варианта
+//! `core_lib` и самой `core_lib` для разбора переданного описания
+//! процессора. Предоставляет:
+//!
+//! - `CpuFeature` и `FeatureSet` - наборы инструкций и их множества;
+//! - `X86_64Level`, `RiscvProfile`, `PowerLevel`, `S390xLevel` - уровни
+//!   микроархитектуры, соответствующие `-C target-cpu` в rustc;
+//! - `CpuInfo` и `detect_cpu` - полное описание процессора: кэши,
+//!   топология, доступный процессу ресурс, виртуализация, частоты и
+//!   подсистема памяти (`MemoryInfo`);
+//! - `load_profile`/`parse_profile`/`export_profile` - профиль процессора
+//!   в формате JSON;
+//! - `CoreLibDescriptor` и `ABI_VERSION` - раскладка описания сборки
+//!   `core_lib` и версия интерфейса между приложением и библиотекой.
+//!
+//! Без функции `std` (включена по умолчанию) библиотека собирается в
+//! режиме `no_std` с `alloc` и содержит только наборы инструкций,
+//! уровни x86-64, описание сборки и тип ошибки: определение процессора
+//! требует доступа к файловой системе и переменным окружения.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod descriptor;
+mod error;
+mod features;
+
+#[cfg(feature = "std")]
+mod cpu_info;
+
+pub use descriptor::{CoreLibDescriptor, ABI_VERSION, DESCRIPTOR_MAGIC, DESCRIPTOR_VERSION};
+pub use error::CpuDetectionError;
+pub use features::{CpuFeature, FeatureSet, X86_64Level};
+
+#[cfg(feature = "std")]
+pub use cpu_info::{
+    cpu_info_from_cpuid_dump, decode_aarch64_hwcaps, decode_powerpc64_hwcaps, decode_riscv_hwcap,
+    decode_riscv_hwprobe, decode_s390x_hwcap, detect_cpu, disable_features, export_profile,
+    load_profile, parse_profile, CacheInfo, CacheType, ContainerRuntime, CoreType, CpuFrequency,
+    CpuIdDump, CpuIdLeaf, CpuInfo, CpuSignature, CpuTopology, HugePagePool, Hwcaps, Hypervisor,
+    MemoryInfo, Microarchitecture, NumaNode, Parallelism, PowerLevel, RiscvProfile, S390xLevel,
+    SuppressedFeature, ThpDefrag, ThpMode, TransparentHugePages, Virtualization,
+};
@@ -135,7 +135,7 @@ fn find_libraries(lib_dir: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Er
         let entry = entry?;
         let path = entry.path();
 
-        if path.is_file() && path.extension().map_or(false, |ext| ext == lib_ext) {
+        if path.is_file() && path.extension().is_some_and(|ext| ext == lib_ext) {
             libraries.push(path);
         }
     }
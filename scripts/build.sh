@@ -22,9 +22,11 @@ print_help() {
     echo "  --all                   Собрать все варианты библиотек"
     echo "  --release               Собрать в режиме release (по умолчанию)"
     echo "  --debug                 Собрать в режиме debug"
-    echo "  --arch=ARCH             Архитектура (x86_64, aarch64)"
+    echo "  --arch=ARCH             Архитектура (x86_64, aarch64, riscv64, powerpc64, s390x)"
     echo "  --os=OS                 Операционная система (linux, windows, macos)"
-    echo "  --features=FEATURES     Набор инструкций (avx2, avx, sse4_2, neon, base)"
+    echo "  --features=FEATURES     Набор инструкций (x86-64-v4, x86-64-v3, x86-64-v2, x86-64,"
+    echo "                          avx2, avx, sse4_2, neon, rva23, rvv, rva22, pwr10, pwr9,"
+    echo "                          pwr8, z15, z14, z13, base)"
     echo "  --allocator=ALLOCATOR   Аллокатор памяти (system, jemalloc, mimalloc)"
     echo "  --clean                 Очистить директорию сборки перед компиляцией"
     echo "  --verbose               Подробный вывод"
@@ -153,6 +155,27 @@ build_library() {
                     ;;
             esac
             ;;
+        "riscv64")
+            case $os in
+                "linux")
+                    target="riscv64gc-unknown-linux-gnu"
+                    ;;
+            esac
+            ;;
+        "powerpc64")
+            case $os in
+                "linux")
+                    target="powerpc64le-unknown-linux-gnu"
+                    ;;
+            esac
+            ;;
+        "s390x")
+            case $os in
+                "linux")
+                    target="s390x-unknown-linux-gnu"
+                    ;;
+            esac
+            ;;
     esac
     
     if [ -n "$target" ]; then
@@ -176,7 +199,12 @@ build_library() {
     esac
     
     # Добавление набора инструкций
+    local rustflags=""
     case $features in
+        "x86-64"|"x86-64-v2"|"x86-64-v3"|"x86-64-v4"|"pwr8"|"pwr9"|"pwr10"|"z13"|"z14"|"z15")
+            feature_args="$feature_args $features"
+            rustflags="-C target-cpu=$features"
+            ;;
         "avx2")
             feature_args="$feature_args avx2"
             ;;
@@ -189,6 +217,12 @@ build_library() {
         "neon")
             feature_args="$feature_args neon"
             ;;
+        "rva23"|"rvv"|"rva22")
+            # Профили RISC-V не являются значениями target-cpu: расширения
+            # сверх RV64GC включаются по отдельности
+            feature_args="$feature_args $features"
+            rustflags="-C target-feature=$(riscv_target_features "$features")"
+            ;;
     esac
     
     if [ -n "$feature_args" ]; then
@@ -200,21 +234,106 @@ build_library() {
     fi
     
     # Запуск сборки
-    eval cargo $cargo_args
+    if [ -n "$rustflags" ]; then
+        RUSTFLAGS="$rustflags" eval cargo $cargo_args
+    else
+        eval cargo $cargo_args
+    fi
     
     # Копирование библиотеки в директорию lib
-    copy_library "$arch" "$features" "$allocator" "$build_type" "$target"
+    copy_library "$arch" "$os" "$features" "$allocator" "$build_type" "$target"
     
     log "info" "Библиотека успешно собрана."
 }
 
+# Функция для вывода расширений RISC-V варианта в формате -C target-feature
+riscv_target_features() {
+    local features=$1
+
+    local rva22="+zba,+zbb,+zbs,+zicbom,+zicboz"
+
+    case $features in
+        "rva23") echo "$rva22,+v,+zvbb" ;;
+        "rvv") echo "+v" ;;
+        "rva22") echo "$rva22" ;;
+    esac
+}
+
+# Функция для вывода наборов инструкций варианта в формате JSON
+manifest_features() {
+    local features=$1
+
+    local v1='"cmov", "cmpxchg8b", "fxsr", "mmx", "sse", "sse2"'
+    local v2="$v1"', "cmpxchg16b", "lahfsahf", "popcnt", "sse3", "sse4.1", "sse4.2", "ssse3"'
+    local v3="$v2"', "avx", "avx2", "bmi1", "bmi2", "f16c", "fma", "lzcnt", "movbe", "xsave"'
+    local v4="$v3"', "avx512f", "avx512bw", "avx512cd", "avx512dq", "avx512vl"'
+    local rva22='"m", "a", "f", "d", "c", "zba", "zbb", "zbs", "zicbom", "zicboz"'
+    local pwr8='"altivec", "vsx", "power8-vector"'
+    local z13='"vector"'
+
+    case $features in
+        "x86-64") echo "[$v1]" ;;
+        "x86-64-v2") echo "[$v2]" ;;
+        "x86-64-v3") echo "[$v3]" ;;
+        "x86-64-v4") echo "[$v4]" ;;
+        "avx2") echo '["avx2"]' ;;
+        "avx") echo '["avx"]' ;;
+        "sse4_2") echo '["sse4.2"]' ;;
+        "neon") echo '["neon"]' ;;
+        "rva23") echo "[$rva22"', "v", "zvbb"]' ;;
+        "rvv") echo '["v"]' ;;
+        "rva22") echo "[$rva22]" ;;
+        "pwr10") echo "[$pwr8"', "power9-vector", "power10-vector"]' ;;
+        "pwr9") echo "[$pwr8"', "power9-vector"]' ;;
+        "pwr8") echo "[$pwr8]" ;;
+        "z15") echo "[$z13"', "vector-enhancements-1", "vector-enhancements-2"]' ;;
+        "z14") echo "[$z13"', "vector-enhancements-1"]' ;;
+        "z13") echo "[$z13]" ;;
+        *) echo '[]' ;;
+    esac
+}
+
+# Функция для создания манифеста библиотеки
+#
+# Манифест описывает платформу, наборы инструкций, аллокатор и версию
+# ABI библиотеки; приложение выбирает библиотеку по манифестам.
+write_manifest() {
+    local lib_name=$1
+    local arch=$2
+    local os=$3
+    local features=$4
+    local allocator=$5
+    local manifest=$6
+
+    local version=$(grep -m1 '^version' core_lib/Cargo.toml | cut -d '"' -f 2)
+    # Версия ABI определена в cpu_detection вместе с описанием сборки
+    local abi_version=$(grep -m1 '^pub const ABI_VERSION' cpu_detection/src/descriptor.rs \
+        | sed 's/.*= *\([0-9]*\);.*/\1/')
+
+    cat > "$manifest" <<EOF
+{
+  "library": "$lib_name",
+  "variant": "$features",
+  "arch": "$arch",
+  "os": "$os",
+  "features": $(manifest_features "$features"),
+  "allocator": "$allocator",
+  "abi_version": $abi_version,
+  "version": "$version"
+}
+EOF
+
+    log "info" "Манифест записан в $manifest"
+}
+
 # Функция для копирования библиотеки в директорию lib
 copy_library() {
     local arch=$1
-    local features=$2
-    local allocator=$3
-    local build_type=$4
-    local target=$5
+    local os=$2
+    local features=$3
+    local allocator=$4
+    local build_type=$5
+    local target=$6
     
     log "info" "Копирование библиотеки в директорию lib..."
     
@@ -263,6 +382,9 @@ copy_library() {
     cp "$src_lib" "$dst_lib"
     
     log "info" "Библиотека скопирована в $dst_lib"
+    
+    write_manifest "$lib_name" "$arch" "$os" "$features" "$allocator" \
+        "lib/${lib_prefix}${arch}_${features}_${allocator}.json"
 }
 
 # Функция для сборки всех вариантов библиотек
@@ -277,7 +399,7 @@ build_all() {
     # Операционные системы
     local oses=("linux" "windows" "macos")
     # Наборы инструкций
-    local features_list=("avx2" "avx" "sse4_2" "base")
+    local features_list=("x86-64-v4" "x86-64-v3" "x86-64-v2" "x86-64" "avx2" "avx" "sse4_2" "base")
     # Аллокаторы
     local allocators=("system" "jemalloc" "mimalloc")
     
@@ -306,6 +428,21 @@ build_all() {
             archs+=("aarch64")
             features_list=("neon" "base")
             ;;
+        "riscv64")
+            current_arch="riscv64"
+            archs=("riscv64")
+            features_list=("rva23" "rvv" "rva22" "base")
+            ;;
+        "ppc64le")
+            current_arch="powerpc64"
+            archs=("powerpc64")
+            features_list=("pwr10" "pwr9" "pwr8" "base")
+            ;;
+        "s390x")
+            current_arch="s390x"
+            archs=("s390x")
+            features_list=("z15" "z14" "z13" "base")
+            ;;
     esac
     
     # Сборка только для текущей ОС
@@ -0,0 +1,200 @@
+//! Предпочтения аллокатора при выборе библиотеки.
+//!
+//! Библиотека собирается с одним из аллокаторов (`system`, `jemalloc`,
+//! `mimalloc`), и нужной сборки может не оказаться. Поэтому аллокатор
+//! задается списком в порядке предпочтения (`jemalloc,mimalloc,system`),
+//! а политика выбора определяет, что важнее, если лучший уровень
+//! варианта собран только с менее предпочтительным аллокатором.
+
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::AppConfig;
+use crate::error::AppError;
+
+/// Переменная окружения со списком аллокаторов
+pub const ALLOCATOR_ENV: &str = "ALLOCATOR";
+
+/// Переменная окружения с политикой выбора
+pub const SELECTION_POLICY_ENV: &str = "CPU_OPT_SELECTION_POLICY";
+
+/// Аллокаторы, с которыми собирается `core_lib`
+pub const ALLOCATORS: [&str; 3] = ["system", "jemalloc", "mimalloc"];
+
+/// Системный аллокатор; доступен всегда и замыкает список предпочтений
+pub const SYSTEM_ALLOCATOR: &str = "system";
+
+/// Что важнее при выборе библиотеки: уровень варианта или аллокатор
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SelectionPolicy {
+    /// Лучший уровень варианта с любым аллокатором из списка; аллокатор
+    /// выбирает только между библиотеками одного уровня
+    #[default]
+    TierFirst,
+    /// Первый аллокатор из списка, для которого есть совместимая
+    /// библиотека; уровень выбирается среди его библиотек
+    AllocatorFirst,
+}
+
+impl SelectionPolicy {
+    /// Имя политики (`tier-first`, `allocator-first`)
+    pub fn name(self) -> &'static str {
+        match self {
+            SelectionPolicy::TierFirst => "tier-first",
+            SelectionPolicy::AllocatorFirst => "allocator-first",
+        }
+    }
+}
+
+impl fmt::Display for SelectionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for SelectionPolicy {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "tier-first" => Ok(SelectionPolicy::TierFirst),
+            "allocator-first" => Ok(SelectionPolicy::AllocatorFirst),
+            _ => Err(AppError::ArgumentError(format!(
+                "Неизвестная политика выбора библиотеки: {} (допустимы tier-first, allocator-first)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Аллокаторы в порядке предпочтения и политика выбора
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllocatorPreference {
+    /// Аллокаторы от наиболее к наименее предпочтительному
+    allocators: Vec<String>,
+    /// Политика выбора
+    pub policy: SelectionPolicy,
+}
+
+impl AllocatorPreference {
+    /// Создает предпочтения из списка аллокаторов
+    ///
+    /// Имена проверяются, повторы удаляются; `system` добавляется в конец
+    /// списка, если его там нет.
+    pub fn new<S: AsRef<str>>(
+        allocators: impl IntoIterator<Item = S>,
+        policy: SelectionPolicy,
+    ) -> Result<Self, AppError> {
+        let mut list: Vec<String> = Vec::new();
+        for allocator in allocators {
+            let allocator = allocator.as_ref().trim().to_ascii_lowercase();
+            if allocator.is_empty() {
+                continue;
+            }
+            if !ALLOCATORS.contains(&allocator.as_str()) {
+                return Err(AppError::ArgumentError(format!(
+                    "Неизвестный аллокатор: {} (допустимы {})",
+                    allocator,
+                    ALLOCATORS.join(", ")
+                )));
+            }
+            if !list.contains(&allocator) {
+                list.push(allocator);
+            }
+        }
+        if !list.iter().any(|allocator| allocator == SYSTEM_ALLOCATOR) {
+            list.push(SYSTEM_ALLOCATOR.to_string());
+        }
+
+        Ok(Self {
+            allocators: list,
+            policy,
+        })
+    }
+
+    /// Предпочтения из `ALLOCATOR` (список через запятую) и
+    /// `CPU_OPT_SELECTION_POLICY`, а без них - из файла конфигурации
+    pub fn from_env(config: Option<&AppConfig>) -> Result<Self, AppError> {
+        let policy = match std::env::var(SELECTION_POLICY_ENV) {
+            Ok(policy) => policy.parse()?,
+            Err(_) => config
+                .and_then(|config| config.selection_policy)
+                .unwrap_or_default(),
+        };
+
+        match std::env::var(ALLOCATOR_ENV) {
+            Ok(allocators) => Self::new(allocators.split(','), policy),
+            Err(_) => Self::new(config.map_or(&[][..], |config| &config.allocators), policy),
+        }
+    }
+
+    /// Аллокаторы в порядке предпочтения
+    pub fn allocators(&self) -> &[String] {
+        &self.allocators
+    }
+
+    /// Наиболее предпочтительный аллокатор
+    pub fn preferred(&self) -> &str {
+        &self.allocators[0]
+    }
+
+    /// Позиция аллокатора в списке предпочтений
+    pub fn rank(&self, allocator: &str) -> Option<usize> {
+        self.allocators
+            .iter()
+            .position(|preferred| preferred == allocator)
+    }
+}
+
+impl fmt::Display for AllocatorPreference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (политика {})",
+            self.allocators.join(","),
+            self.policy
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocator_preference() {
+        let preference = AllocatorPreference::new(
+            ["jemalloc", " MiMalloc", "", "jemalloc"],
+            SelectionPolicy::TierFirst,
+        )
+        .unwrap();
+        assert_eq!(preference.allocators(), ["jemalloc", "mimalloc", "system"]);
+        assert_eq!(preference.preferred(), "jemalloc");
+        assert_eq!(preference.rank("system"), Some(2));
+        assert_eq!(preference.rank("tcmalloc"), None);
+        assert_eq!(
+            preference.to_string(),
+            "jemalloc,mimalloc,system (политика tier-first)"
+        );
+
+        // system остается на указанном месте
+        let preference =
+            AllocatorPreference::new(["system", "jemalloc"], SelectionPolicy::AllocatorFirst)
+                .unwrap();
+        assert_eq!(preference.allocators(), ["system", "jemalloc"]);
+
+        let empty: [&str; 0] = [];
+        let preference = AllocatorPreference::new(empty, SelectionPolicy::TierFirst).unwrap();
+        assert_eq!(preference.allocators(), ["system"]);
+
+        assert!(AllocatorPreference::new(["tcmalloc"], SelectionPolicy::TierFirst).is_err());
+
+        assert_eq!(
+            "Allocator-First".parse::<SelectionPolicy>().unwrap(),
+            SelectionPolicy::AllocatorFirst
+        );
+        assert!("fastest".parse::<SelectionPolicy>().is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! Файл конфигурации приложения.
+//!
+//! Конфигурация хранится в JSON:
+//!
+//! ```json
+//! {
+//!   "lib_path": ["/opt/ourapp/lib"],
+//!   "allocators": ["jemalloc", "mimalloc", "system"],
+//!   "selection_policy": "tier-first"
+//! }
+//! ```
+//!
+//! Файл задается переменной окружения `CPU_OPT_CONFIG`; без нее
+//! используется первый существующий из
+//! `$XDG_CONFIG_HOME/cpu_optimized_app/config.json` (по умолчанию
+//! `~/.config/cpu_optimized_app/config.json`) и
+//! `/etc/cpu_optimized_app/config.json`.
+
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::allocator::SelectionPolicy;
+use crate::error::AppError;
+
+/// Переменная окружения с путем к файлу конфигурации
+pub const CONFIG_ENV: &str = "CPU_OPT_CONFIG";
+
+/// Имя директории приложения в директориях XDG и системных директориях
+pub const APP_DIR_NAME: &str = "cpu_optimized_app";
+
+/// Имя файла конфигурации
+const CONFIG_FILE_NAME: &str = "config.json";
+
+/// Конфигурация приложения
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    /// Директории поиска библиотек; относительные пути отсчитываются от
+    /// директории файла конфигурации
+    pub lib_path: Vec<PathBuf>,
+    /// Аллокаторы в порядке предпочтения (переопределяется `ALLOCATOR`)
+    pub allocators: Vec<String>,
+    /// Политика выбора библиотеки (переопределяется
+    /// `CPU_OPT_SELECTION_POLICY`)
+    pub selection_policy: Option<SelectionPolicy>,
+}
+
+impl AppConfig {
+    /// Разбирает конфигурацию из JSON
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Загружает конфигурацию из файла
+    ///
+    /// Относительные директории `lib_path` заменяются путями от
+    /// директории файла.
+    pub fn load_file(path: &Path) -> Result<Self, AppError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            AppError::PathError(format!(
+                "Не удалось прочитать файл конфигурации {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        let mut config = Self::from_json(&content).map_err(|e| {
+            AppError::ConfigError(format!(
+                "Некорректный файл конфигурации {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        for dir in &mut config.lib_path {
+            *dir = base.join(&*dir);
+        }
+        Ok(config)
+    }
+
+    /// Загружает конфигурацию из `CPU_OPT_CONFIG` или первого
+    /// существующего файла по умолчанию
+    ///
+    /// Возвращает путь к файлу и конфигурацию; `None`, если файла нет.
+    /// Отсутствие файла, заданного `CPU_OPT_CONFIG`, - ошибка.
+    pub fn load() -> Result<Option<(PathBuf, Self)>, AppError> {
+        let path = match std::env::var_os(CONFIG_ENV) {
+            Some(path) => PathBuf::from(path),
+            None => match default_config_paths()
+                .into_iter()
+                .find(|path| path.is_file())
+            {
+                Some(path) => path,
+                None => return Ok(None),
+            },
+        };
+
+        let config = Self::load_file(&path)?;
+        Ok(Some((path, config)))
+    }
+}
+
+/// Файлы конфигурации по умолчанию в порядке приоритета
+fn default_config_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(config_home) = xdg_dir("XDG_CONFIG_HOME", ".config") {
+        paths.push(config_home.join(APP_DIR_NAME).join(CONFIG_FILE_NAME));
+    }
+    if cfg!(unix) {
+        paths.push(Path::new("/etc").join(APP_DIR_NAME).join(CONFIG_FILE_NAME));
+    }
+    paths
+}
+
+/// Базовая директория XDG: значение переменной `var` или `fallback`
+/// в домашней директории
+///
+/// Пустые и относительные значения игнорируются, как требует
+/// спецификация XDG Base Directory.
+pub fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .map(PathBuf::from)
+                .filter(|home| home.is_absolute())
+                .map(|home| home.join(fallback))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_load_config() {
+        assert_eq!(AppConfig::from_json("{}").unwrap(), AppConfig::default());
+        assert!(AppConfig::from_json(r#"{"lib_dir": "/opt"}"#).is_err());
+        assert!(AppConfig::from_json(r#"{"selection_policy": "fastest"}"#).is_err());
+
+        let config = AppConfig::from_json(
+            r#"{"allocators": ["jemalloc", "system"], "selection_policy": "allocator-first"}"#,
+        )
+        .unwrap();
+        assert_eq!(config.allocators, ["jemalloc", "system"]);
+        assert_eq!(
+            config.selection_policy,
+            Some(SelectionPolicy::AllocatorFirst)
+        );
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        let absolute = dir.path().join("opt").join("lib");
+        std::fs::write(
+            &path,
+            serde_json::json!({ "lib_path": [absolute, "lib"] }).to_string(),
+        )
+        .unwrap();
+
+        // Относительные пути отсчитываются от директории файла
+        let config = AppConfig::load_file(&path).unwrap();
+        assert_eq!(config.lib_path, [absolute, dir.path().join("lib")]);
+
+        assert!(AppConfig::load_file(&dir.path().join("missing.json")).is_err());
+
+        std::fs::write(&path, r#"{"allocators": "system"}"#).unwrap();
+        match AppConfig::load_file(&path) {
+            Err(AppError::ConfigError(message)) => {
+                assert!(message.contains(&path.display().to_string()))
+            },
+            other => panic!("ожидается ошибка конфигурации: {:?}", other.err()),
+        }
+    }
+}
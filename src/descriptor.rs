@@ -0,0 +1,214 @@
+//! Встроенное описание сборки библиотеки (`core_lib_describe`).
+//!
+//! Манифест может потеряться или не соответствовать файлу библиотеки,
+//! поэтому после загрузки библиотеки и до вызова ее функций приложение
+//! читает описание, встроенное в саму библиотеку. Символ
+//! `core_lib_describe` - статические данные, а не функция, поэтому чтение
+//! не выполняет код, собранный с недоступными процессору инструкциями.
+
+use libloading::Library;
+use log::{debug, info, warn};
+use serde::Deserialize;
+
+use crate::error::AppError;
+use cpu_detection::{
+    CoreLibDescriptor, CpuFeature, CpuInfo, FeatureSet, PowerLevel, S390xLevel, X86_64Level,
+    ABI_VERSION,
+};
+
+/// Описание сборки библиотеки
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct LibraryDescriptor {
+    /// Версия интерфейса с приложением
+    #[serde(skip)]
+    pub abi_version: u32,
+    /// Вариант сборки (`x86-64-v3`, `avx2`, `base`, ...)
+    pub variant: String,
+    /// Целевая архитектура
+    pub arch: String,
+    /// Целевая ОС
+    pub os: String,
+    /// Наборы инструкций, с которыми скомпилирован код (имена rustc)
+    pub target_features: Vec<String>,
+    /// Аллокатор памяти
+    pub allocator: String,
+    /// Версия библиотеки
+    pub version: String,
+    /// Версия компилятора
+    pub rustc: String,
+    /// Хеш коммита git
+    pub git_hash: String,
+}
+
+impl LibraryDescriptor {
+    /// Разбирает описание сборки из JSON
+    pub fn from_json(json: &str, abi_version: u32) -> Result<Self, serde_json::Error> {
+        let mut descriptor: LibraryDescriptor = serde_json::from_str(json)?;
+        descriptor.abi_version = abi_version;
+        Ok(descriptor)
+    }
+
+    /// Наборы инструкций, которые библиотека требует от процессора
+    ///
+    /// Наборы, гарантированные самой архитектурой (SSE2 на x86_64, NEON на
+    /// aarch64, RV64GC на riscv64, POWER8 на powerpc64, z13 на s390x), и
+    /// неизвестные приложению имена не учитываются.
+    pub fn required_features(&self) -> FeatureSet {
+        let features: FeatureSet = self
+            .target_features
+            .iter()
+            .filter_map(|name| CpuFeature::from_name(name))
+            .collect();
+        features.difference(&target_baseline(&self.arch))
+    }
+
+    /// Проверяет, можно ли вызывать функции библиотеки на процессоре
+    pub fn check(&self, cpu_info: &CpuInfo) -> Result<(), AppError> {
+        if self.abi_version != ABI_VERSION {
+            return Err(AppError::LibraryError(format!(
+                "Версия ABI библиотеки {} не поддерживается (требуется {})",
+                self.abi_version, ABI_VERSION
+            )));
+        }
+
+        let missing = self.required_features().difference(&cpu_info.features);
+        if !missing.is_empty() {
+            return Err(AppError::LibraryError(format!(
+                "Библиотека (вариант {}) собрана с наборами инструкций, \
+                 которые не поддерживает процессор: {}",
+                self.variant, missing
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Наборы инструкций, которые поддерживает любой процессор архитектуры
+fn target_baseline(arch: &str) -> FeatureSet {
+    match arch {
+        "x86_64" => X86_64Level::V1.required_features(),
+        "aarch64" => [CpuFeature::Neon].into_iter().collect(),
+        "riscv64" => [
+            CpuFeature::M,
+            CpuFeature::A,
+            CpuFeature::F,
+            CpuFeature::D,
+            CpuFeature::C,
+        ]
+        .into_iter()
+        .collect(),
+        "powerpc64" => PowerLevel::Power8.required_features(),
+        "s390x" => S390xLevel::Z13.required_features(),
+        _ => FeatureSet::new(),
+    }
+}
+
+/// Читает описание сборки из загруженной библиотеки
+///
+/// Возвращает `None`, если библиотека не экспортирует `core_lib_describe`
+/// (сборки до появления описания).
+pub fn read_descriptor(lib: &Library) -> Result<Option<LibraryDescriptor>, AppError> {
+    let symbol = match unsafe { lib.get::<*const CoreLibDescriptor>(b"core_lib_describe") } {
+        Ok(symbol) => symbol,
+        Err(e) => {
+            debug!("Символ 'core_lib_describe' не найден в библиотеке: {}", e);
+            return Ok(None);
+        },
+    };
+
+    // SAFETY: символ указывает на статические данные библиотеки, которые
+    // остаются доступны, пока загружена библиотека
+    let raw = unsafe { &**symbol };
+    if !raw.is_supported() {
+        return Err(AppError::LibraryError(format!(
+            "Неподдерживаемый формат описания библиотеки (версия {})",
+            raw.descriptor_version
+        )));
+    }
+
+    // SAFETY: json указывает на json_len байт статической строки
+    let json = unsafe { raw.json_bytes() };
+    let json = std::str::from_utf8(json)
+        .map_err(|e| AppError::LibraryError(format!("Некорректное описание библиотеки: {}", e)))?;
+
+    LibraryDescriptor::from_json(json, raw.abi_version)
+        .map(Some)
+        .map_err(|e| AppError::LibraryError(format!("Некорректное описание библиотеки: {}", e)))
+}
+
+/// Проверяет описание загруженной библиотеки перед вызовом ее функций
+///
+/// Библиотека без описания допускается с предупреждением.
+pub fn verify_library(lib: &Library, cpu_info: &CpuInfo) -> Result<(), AppError> {
+    match read_descriptor(lib)? {
+        Some(descriptor) => {
+            info!(
+                "Библиотека: вариант {}, аллокатор {}, версия {}, {}, git {}",
+                descriptor.variant,
+                descriptor.allocator,
+                descriptor.version,
+                descriptor.rustc,
+                descriptor.git_hash
+            );
+            descriptor.check(cpu_info)
+        },
+        None => {
+            warn!("Библиотека не содержит описания сборки, наборы инструкций не проверены");
+            Ok(())
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_descriptor_check() {
+        let json = r#"{
+            "variant": "x86-64-v3", "arch": "x86_64", "os": "linux",
+            "target_features": ["fxsr", "sse", "sse2", "avx", "avx2", "bmi1", "bmi2",
+                                "fma", "x87", "crt-static"],
+            "allocator": "system", "version": "0.1.0",
+            "rustc": "rustc 1.87.0", "git_hash": "0123456789ab"
+        }"#;
+        let descriptor = LibraryDescriptor::from_json(json, ABI_VERSION).unwrap();
+
+        // Базовые наборы x86_64 и неизвестные имена не требуются
+        assert_eq!(
+            descriptor.required_features(),
+            "avx,avx2,bmi1,bmi2,fma".parse().unwrap()
+        );
+
+        let cpu = |features: &str| CpuInfo {
+            features: features.parse().unwrap(),
+            ..CpuInfo::default()
+        };
+        assert!(descriptor.check(&cpu("avx,avx2,bmi1,bmi2,fma")).is_ok());
+
+        let err = descriptor.check(&cpu("avx,avx2")).unwrap_err();
+        assert!(err.to_string().ends_with(": fma,bmi1,bmi2"));
+
+        let old_abi = LibraryDescriptor::from_json(json, ABI_VERSION + 1).unwrap();
+        assert!(old_abi.check(&cpu("avx,avx2,bmi1,bmi2,fma")).is_err());
+
+        // Инструкции POWER8 и z13 гарантированы архитектурой
+        let power9 = LibraryDescriptor {
+            arch: "powerpc64".to_string(),
+            target_features: ["altivec", "vsx", "power8-vector", "power9-vector"]
+                .map(String::from)
+                .to_vec(),
+            ..descriptor.clone()
+        };
+        assert_eq!(power9.required_features().to_string(), "power9-vector");
+        let z14 = LibraryDescriptor {
+            arch: "s390x".to_string(),
+            target_features: ["vector", "vector-enhancements-1"]
+                .map(String::from)
+                .to_vec(),
+            ..descriptor
+        };
+        assert_eq!(z14.required_features().to_string(), "vector-enhancements-1");
+    }
+}
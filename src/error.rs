@@ -29,6 +29,14 @@ pub enum AppError {
     #[error("Ошибка определения процессора: {0}")]
     CpuDetectionError(String),
 
+    /// Ошибки в файлах конфигурации и таблицах особенностей
+    #[error("Ошибка конфигурации: {0}")]
+    ConfigError(String),
+
+    /// Ошибки в аргументах командной строки
+    #[error("Ошибка в аргументах командной строки: {0}")]
+    ArgumentError(String),
+
     /// Ошибки из библиотеки core_lib
     #[error("Ошибка в core_lib: {0}")]
     CoreLibError(String),
@@ -38,6 +46,12 @@ pub enum AppError {
     Unknown(String),
 }
 
+impl From<cpu_detection::CpuDetectionError> for AppError {
+    fn from(err: cpu_detection::CpuDetectionError) -> Self {
+        AppError::CpuDetectionError(err.0)
+    }
+}
+
 impl From<libloading::Error> for AppError {
     fn from(err: libloading::Error) -> Self {
         AppError::LibraryError(err.to_string())
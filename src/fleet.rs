@@ -0,0 +1,420 @@
+//! Общий набор инструкций для парка машин.
+//!
+//! По каталогу профилей процессоров (`--export-cpu-profile`) вычисляет
+//! пересечение наборов инструкций, наивысший общий уровень x86-64 и
+//! вариант библиотеки, который выберет каждая машина, а также машины,
+//! которые не позволяют поднять общий вариант. Особенности процессоров
+//! (`quirks`) учитываются так же, как при выборе библиотеки на машине.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::error::AppError;
+use crate::lib_loader;
+use crate::quirks::QuirkTable;
+use cpu_detection::{CpuInfo, FeatureSet, X86_64Level};
+
+/// Машина парка и ее профиль процессора
+#[derive(Debug)]
+pub struct FleetMachine {
+    /// Имя машины (имя файла профиля без расширения)
+    pub name: String,
+    /// Профиль процессора
+    pub cpu_info: CpuInfo,
+}
+
+/// Сводка по одной машине
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineSummary {
+    /// Имя машины
+    pub name: String,
+    /// Наивысший уровень x86-64 машины
+    pub x86_64_level: Option<X86_64Level>,
+    /// Вариант библиотеки, который выберет машина
+    pub variant: &'static str,
+}
+
+/// Вариант библиотеки выше общего и машины, которые его не поддерживают
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockedVariant {
+    /// Вариант библиотеки
+    pub variant: &'static str,
+    /// Машины и причины, по которым они не выберут вариант
+    pub machines: Vec<(String, Blocker)>,
+}
+
+/// Причина, по которой машина блокирует вариант библиотеки
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Blocker {
+    /// На машине отсутствуют наборы инструкций
+    Missing(FeatureSet),
+    /// Вариант запрещен или понижен особенностью процессора
+    Quirk(String),
+}
+
+impl fmt::Display for Blocker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Blocker::Missing(features) => write!(f, "нет {}", features),
+            Blocker::Quirk(id) => write!(f, "особенность {}", id),
+        }
+    }
+}
+
+/// Общий набор инструкций парка машин
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FleetReport {
+    /// Архитектура всех машин парка
+    pub arch: String,
+    /// Наборы инструкций, поддерживаемые всеми машинами
+    pub common_features: FeatureSet,
+    /// Наивысший уровень x86-64, поддерживаемый всеми машинами
+    pub common_level: Option<X86_64Level>,
+    /// Наивысший вариант библиотеки, подходящий всем машинам
+    pub common_variant: &'static str,
+    /// Сводка по каждой машине
+    pub machines: Vec<MachineSummary>,
+    /// Варианты выше общего с указанием машин, которые их блокируют
+    pub blocked: Vec<BlockedVariant>,
+}
+
+/// Загружает профили процессоров (`*.json`) из каталога в порядке имен
+pub fn load_fleet(dir: &Path) -> Result<Vec<FleetMachine>, AppError> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(|e| {
+        AppError::PathError(format!(
+            "Не удалось прочитать каталог профилей {}: {}",
+            dir.display(),
+            e
+        ))
+    })? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            Ok(FleetMachine {
+                name: path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                cpu_info: cpu_detection::load_profile(path)?,
+            })
+        })
+        .collect()
+}
+
+impl FleetReport {
+    /// Строит отчет по машинам парка
+    ///
+    /// Варианты библиотек выбираются так же, как при запуске на каждой
+    /// машине, с учетом консервативного выбора под гипервизорами из
+    /// списка `conservative_hypervisors` и таблицы особенностей. Общим
+    /// становится наивысший вариант, доступный всем машинам, причем
+    /// вариант, пониженный особенностью хотя бы на одной машине,
+    /// выбирается, только если остальные общие варианты - базовые.
+    pub fn new(
+        machines: &[FleetMachine],
+        quirks: &QuirkTable,
+        conservative_hypervisors: &str,
+    ) -> Result<Self, AppError> {
+        let Some(first) = machines.first() else {
+            return Err(AppError::ArgumentError(
+                "Не найдено ни одного профиля процессора".to_string(),
+            ));
+        };
+
+        let arch = first.cpu_info.effective_arch();
+        if let Some(other) = machines
+            .iter()
+            .find(|machine| machine.cpu_info.effective_arch() != arch)
+        {
+            return Err(AppError::ArgumentError(format!(
+                "Профили относятся к разным архитектурам: {} ({}) и {} ({})",
+                first.name,
+                arch,
+                other.name,
+                other.cpu_info.effective_arch()
+            )));
+        }
+
+        let candidates: Vec<Vec<&'static str>> = machines
+            .iter()
+            .map(|machine| {
+                let (features, _) =
+                    lib_loader::selection_features(&machine.cpu_info, conservative_hypervisors);
+                lib_loader::candidate_variants(&machine.cpu_info, &features, quirks)
+            })
+            .collect();
+
+        let common_features = machines
+            .iter()
+            .map(|machine| machine.cpu_info.features)
+            .reduce(|common, features| common.intersection(&features))
+            .unwrap_or_default();
+
+        let demoted = |variant: &str| {
+            machines
+                .iter()
+                .any(|machine| quirks.demoting(&machine.cpu_info, variant).is_some())
+        };
+        let common: Vec<&'static str> = lib_loader::library_variants(arch)
+            .filter(|variant| candidates.iter().all(|list| list.contains(variant)))
+            .collect();
+        let common_variant = common
+            .iter()
+            .copied()
+            .find(|variant| !demoted(variant) && !lib_loader::is_baseline_variant(variant, arch))
+            .or_else(|| common.first().copied())
+            .unwrap_or("base");
+
+        let summaries = machines
+            .iter()
+            .zip(&candidates)
+            .map(|(machine, list)| MachineSummary {
+                name: machine.name.clone(),
+                x86_64_level: machine.cpu_info.x86_64_level(),
+                variant: list.first().copied().unwrap_or("base"),
+            })
+            .collect();
+
+        let blocked = lib_loader::library_variants(arch)
+            .take_while(|&variant| variant != common_variant)
+            .map(|variant| BlockedVariant {
+                variant,
+                machines: machines
+                    .iter()
+                    .filter_map(|machine| {
+                        blocker(&machine.cpu_info, quirks, conservative_hypervisors, variant)
+                            .map(|blocker| (machine.name.clone(), blocker))
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Ok(FleetReport {
+            arch: arch.to_string(),
+            common_features,
+            common_level: X86_64Level::classify(&common_features),
+            common_variant,
+            machines: summaries,
+            blocked,
+        })
+    }
+}
+
+/// Причина, по которой машина не выберет вариант библиотеки
+fn blocker(
+    cpu_info: &CpuInfo,
+    quirks: &QuirkTable,
+    conservative_hypervisors: &str,
+    variant: &str,
+) -> Option<Blocker> {
+    let required = lib_loader::variant_requirements(variant);
+    let (features, _) = lib_loader::selection_features(cpu_info, conservative_hypervisors);
+    if !features.is_superset(&required) {
+        return Some(Blocker::Missing(required.difference(&features)));
+    }
+
+    quirks
+        .forbidding(cpu_info, variant)
+        .or_else(|| quirks.demoting(cpu_info, variant))
+        .map(|quirk| Blocker::Quirk(quirk.id.clone()))
+}
+
+impl fmt::Display for FleetReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name_width = self
+            .machines
+            .iter()
+            .map(|machine| machine.name.chars().count())
+            .chain([6])
+            .max()
+            .unwrap_or(0);
+
+        writeln!(
+            f,
+            "{:<name_width$}  {:<10}  Вариант библиотеки",
+            "Машина", "Уровень"
+        )?;
+        for machine in &self.machines {
+            let level = machine.x86_64_level.map_or("-", |level| level.target_cpu());
+            writeln!(
+                f,
+                "{:<name_width$}  {:<10}  {}",
+                machine.name, level, machine.variant
+            )?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Архитектура: {}", self.arch)?;
+        writeln!(f, "Общие наборы инструкций: {}", self.common_features)?;
+        if let Some(level) = self.common_level {
+            writeln!(f, "Общий уровень x86-64: {}", level.target_cpu())?;
+        }
+        writeln!(f, "Общий вариант библиотеки: {}", self.common_variant)?;
+
+        if self.blocked.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(f)?;
+        writeln!(f, "{:<10}  Блокирующие машины", "Вариант")?;
+        for blocked in &self.blocked {
+            let machines: Vec<String> = blocked
+                .machines
+                .iter()
+                .map(|(name, blocker)| format!("{} ({})", name, blocker))
+                .collect();
+            writeln!(f, "{:<10}  {}", blocked.variant, machines.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib_loader::DEFAULT_CONSERVATIVE_HYPERVISORS;
+    use cpu_detection::{CpuFeature, CpuSignature};
+    use tempfile::tempdir;
+
+    fn machine(name: &str, features: FeatureSet) -> FleetMachine {
+        FleetMachine {
+            name: name.to_string(),
+            cpu_info: CpuInfo {
+                arch: "x86_64".to_string(),
+                features,
+                ..CpuInfo::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_fleet_report() {
+        let mut skylake = X86_64Level::V3.required_features();
+        skylake.insert(CpuFeature::Aes);
+        let mut westmere = X86_64Level::V2.required_features();
+        westmere.insert(CpuFeature::Aes);
+
+        let machines = [
+            machine("icelake", X86_64Level::V4.required_features()),
+            machine("skylake", skylake),
+            machine("westmere", westmere),
+        ];
+        let report = FleetReport::new(
+            &machines,
+            &QuirkTable::builtin(),
+            DEFAULT_CONSERVATIVE_HYPERVISORS,
+        )
+        .unwrap();
+
+        assert_eq!(report.common_features, X86_64Level::V2.required_features());
+        assert_eq!(report.common_level, Some(X86_64Level::V2));
+        assert_eq!(report.common_variant, "x86-64-v2");
+
+        let variants: Vec<_> = report.machines.iter().map(|m| m.variant).collect();
+        assert_eq!(variants, ["x86-64-v4", "x86-64-v3", "x86-64-v2"]);
+
+        // x86-64-v4 блокируют две машины, остальные варианты - одна
+        let blocked: Vec<_> = report
+            .blocked
+            .iter()
+            .map(|blocked| (blocked.variant, blocked.machines.len()))
+            .collect();
+        assert_eq!(
+            blocked,
+            [("x86-64-v4", 2), ("x86-64-v3", 1), ("avx2", 1), ("avx", 1)]
+        );
+        let (name, blocker) = &report.blocked[3].machines[0];
+        assert_eq!(name, "westmere");
+        assert_eq!(blocker.to_string(), "нет avx");
+
+        let table = report.to_string();
+        assert!(table.contains("Общий уровень x86-64: x86-64-v2"));
+        assert!(table.contains("x86-64-v3   westmere (нет "));
+    }
+
+    #[test]
+    fn test_fleet_report_quirks() {
+        let mut skylake_sp = machine("skylake-sp", X86_64Level::V4.required_features());
+        skylake_sp.cpu_info.vendor = "GenuineIntel".to_string();
+        skylake_sp.cpu_info.signature = Some(CpuSignature {
+            family: 6,
+            model: 0x55,
+            stepping: 4,
+        });
+        let machines = [
+            machine("icelake", X86_64Level::V4.required_features()),
+            skylake_sp,
+        ];
+        let report = FleetReport::new(
+            &machines,
+            &QuirkTable::builtin(),
+            DEFAULT_CONSERVATIVE_HYPERVISORS,
+        )
+        .unwrap();
+
+        // x86-64-v4 поддерживают обе машины, но на Skylake-SP он понижен
+        let variants: Vec<_> = report.machines.iter().map(|m| m.variant).collect();
+        assert_eq!(variants, ["x86-64-v4", "x86-64-v3"]);
+        assert_eq!(report.common_variant, "x86-64-v3");
+        assert_eq!(
+            report.blocked,
+            [BlockedVariant {
+                variant: "x86-64-v4",
+                machines: vec![(
+                    "skylake-sp".to_string(),
+                    Blocker::Quirk("skylake-sp-avx512-frequency".to_string())
+                )],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_load_fleet() {
+        let dir = tempdir().unwrap();
+        for (name, level) in [("b", X86_64Level::V3), ("a", X86_64Level::V4)] {
+            let machine = machine(name, level.required_features());
+            let file = std::fs::File::create(dir.path().join(format!("{}.json", name))).unwrap();
+            cpu_detection::export_profile(&machine.cpu_info, file).unwrap();
+        }
+        std::fs::write(dir.path().join("README.txt"), "не профиль").unwrap();
+
+        let machines = load_fleet(dir.path()).unwrap();
+        let names: Vec<_> = machines.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+
+        let report = FleetReport::new(
+            &machines,
+            &QuirkTable::builtin(),
+            DEFAULT_CONSERVATIVE_HYPERVISORS,
+        )
+        .unwrap();
+        assert_eq!(report.common_variant, "x86-64-v3");
+
+        // Разные архитектуры и пустой каталог отклоняются
+        let mut mixed = machines;
+        mixed[1].cpu_info.arch = "aarch64".to_string();
+        assert!(matches!(
+            FleetReport::new(
+                &mixed,
+                &QuirkTable::builtin(),
+                DEFAULT_CONSERVATIVE_HYPERVISORS
+            ),
+            Err(AppError::ArgumentError(_))
+        ));
+        assert!(FleetReport::new(
+            &[],
+            &QuirkTable::builtin(),
+            DEFAULT_CONSERVATIVE_HYPERVISORS
+        )
+        .is_err());
+    }
+}
@@ -3,16 +3,33 @@
 //! Использует библиотеку libloading для работы с динамическими библиотеками.
 
 use libloading::{Library, Symbol};
-use log::{debug, info, warn};
-use std::path::{Path, PathBuf};
+use log::{debug, info};
+use std::path::Path;
 
+use crate::descriptor;
 use crate::error::AppError;
+use crate::manifest::ManifestEntry;
+use crate::quirks::QuirkTable;
+use crate::resolver::LibraryResolver;
+use cpu_detection::{
+    CpuFeature, CpuInfo, FeatureSet, Hypervisor, PowerLevel, RiscvProfile, S390xLevel, X86_64Level,
+};
 
 /// Тип функции run в динамической библиотеке
 type RunFunction = unsafe fn(Vec<String>) -> Result<i32, Box<dyn std::error::Error>>;
 
-/// Загружает библиотеку и вызывает функцию run
-pub fn load_and_run(lib_path: &Path, args: Vec<String>) -> Result<i32, AppError> {
+/// Тип функции set_host_info в динамической библиотеке
+///
+/// Принимает описание процессора (`CpuInfo`) в формате JSON.
+type SetHostInfoFunction = unsafe fn(&str);
+
+/// Загружает библиотеку, передает ей информацию о процессоре и вызывает
+/// функцию run
+pub fn load_and_run(
+    lib_path: &Path,
+    cpu_info: &CpuInfo,
+    args: Vec<String>,
+) -> Result<i32, AppError> {
     info!("Загрузка библиотеки: {}", lib_path.display());
 
     // Загрузка библиотеки
@@ -26,6 +43,25 @@ pub fn load_and_run(lib_path: &Path, args: Vec<String>) -> Result<i32, AppError>
 
     debug!("Библиотека успешно загружена");
 
+    // Проверка встроенного описания до вызова кода библиотеки
+    descriptor::verify_library(&lib, cpu_info)?;
+
+    // Передача информации о процессоре (кэши и т.д.); функция необязательна,
+    // старые версии библиотеки ее не экспортируют
+    match unsafe { lib.get::<SetHostInfoFunction>(b"set_host_info") } {
+        Ok(set_host_info) => {
+            let host_info = serde_json::to_string(cpu_info).map_err(|e| {
+                AppError::FunctionCallError(format!(
+                    "Не удалось сериализовать информацию о процессоре: {}",
+                    e
+                ))
+            })?;
+            debug!("Передача информации о процессоре в библиотеку");
+            unsafe { set_host_info(&host_info) };
+        },
+        Err(e) => debug!("Функция 'set_host_info' не найдена в библиотеке: {}", e),
+    }
+
     // Получение функции run из библиотеки
     let run: Symbol<RunFunction> = unsafe { lib.get(b"run") }.map_err(|e| {
         AppError::FunctionCallError(format!(
@@ -44,128 +80,229 @@ pub fn load_and_run(lib_path: &Path, args: Vec<String>) -> Result<i32, AppError>
     Ok(result)
 }
 
-/// Ищет подходящую библиотеку в директории lib
-pub fn find_library(cpu_features: &[String], allocator: &str) -> Result<PathBuf, AppError> {
-    let arch = std::env::consts::ARCH;
+/// Переменная окружения со списком гипервизоров для консервативного
+/// выбора библиотеки
+pub const CONSERVATIVE_HYPERVISORS_ENV: &str = "CPU_CONSERVATIVE_HYPERVISORS";
 
-    // Определение расширения библиотеки в зависимости от ОС
-    let lib_ext = match std::env::consts::OS {
-        "windows" => "dll",
-        "macos" => "dylib",
-        _ => "so",
-    };
+/// Гипервизоры, под которыми по умолчанию выбирается консервативный
+/// вариант библиотеки: эмуляция TCG сообщает наборы инструкций, которые
+/// выполняются программно и не всегда корректно
+pub const DEFAULT_CONSERVATIVE_HYPERVISORS: &str = "qemu-tcg";
 
-    // Определение директории с библиотеками
-    let mut lib_dir = std::env::current_exe()?
-        .parent()
-        .ok_or_else(|| {
-            AppError::PathError("Не удалось определить директорию исполняемого файла".to_string())
-        })?
-        .to_path_buf();
-
-    lib_dir.push("lib");
-
-    if !lib_dir.exists() {
-        return Err(AppError::PathError(format!(
-            "Директория библиотек не найдена: {}",
-            lib_dir.display()
-        )));
-    }
-
-    // Приоритет наборов инструкций (от лучшего к худшему)
-    let priority_features = ["avx2", "avx", "sse4.2", "neon", "base"];
-
-    // Поиск наилучшего набора инструкций среди поддерживаемых
-    for &feature in priority_features.iter() {
-        // Проверяем, поддерживает ли процессор данный набор инструкций
-        // Для "base" всегда возвращаем true, так как это базовый набор
-        let is_supported =
-            feature == "base" || cpu_features.iter().any(|f| f.replace(".", "_") == feature);
+/// Гипервизоры для консервативного выбора из
+/// `CPU_CONSERVATIVE_HYPERVISORS` или список по умолчанию
+pub fn conservative_hypervisors_from_env() -> String {
+    std::env::var(CONSERVATIVE_HYPERVISORS_ENV)
+        .unwrap_or_else(|_| DEFAULT_CONSERVATIVE_HYPERVISORS.to_string())
+}
 
-        if !is_supported {
-            continue;
-        }
+/// Возвращает наборы инструкций, по которым выбирается библиотека, и
+/// признак консервативного выбора
+///
+/// Под гипервизорами из списка `conservative_hypervisors` (имена через
+/// запятую) используются только инструкции уровня x86-64-v2 на x86_64,
+/// NEON на aarch64, RV64GC на riscv64, POWER8 на powerpc64 и z13 на
+/// s390x: виртуальные машины могут сообщать наборы инструкций, которые
+/// на деле не поддерживаются. Сообщать о консервативном выборе должна
+/// вызывающая сторона.
+pub fn selection_features(
+    cpu_info: &CpuInfo,
+    conservative_hypervisors: &str,
+) -> (FeatureSet, bool) {
+    match &cpu_info.virtualization.hypervisor {
+        Some(hypervisor) if is_listed(hypervisor, conservative_hypervisors) => (
+            conservative_features(&cpu_info.features, cpu_info.effective_arch()),
+            true,
+        ),
+        _ => (cpu_info.features, false),
+    }
+}
 
-        // Формирование имени библиотеки
-        let lib_name = format!("{}_{}_{}", arch, feature, allocator);
+/// Проверяет, входит ли гипервизор в список имен через запятую
+fn is_listed(hypervisor: &Hypervisor, list: &str) -> bool {
+    list.split(',')
+        .map(str::trim)
+        .any(|name| name.eq_ignore_ascii_case(hypervisor.name()))
+}
 
-        let mut lib_path = lib_dir.clone();
+/// Ограничивает наборы инструкций базовыми для архитектуры
+fn conservative_features(features: &FeatureSet, arch: &str) -> FeatureSet {
+    let baseline = match arch {
+        "x86_64" => X86_64Level::V2.required_features(),
+        "aarch64" => [CpuFeature::Neon].into_iter().collect(),
+        "riscv64" => [
+            CpuFeature::M,
+            CpuFeature::A,
+            CpuFeature::F,
+            CpuFeature::D,
+            CpuFeature::C,
+        ]
+        .into_iter()
+        .collect(),
+        "powerpc64" => PowerLevel::Power8.required_features(),
+        "s390x" => S390xLevel::Z13.required_features(),
+        _ => FeatureSet::new(),
+    };
+    features.intersection(&baseline)
+}
 
-        #[cfg(target_os = "windows")]
-        lib_path.push(format!("{}.{}", lib_name, lib_ext));
+/// Варианты библиотек в порядке приоритета (от лучшего к худшему) и
+/// архитектуры, для которых они собираются: уровни микроархитектуры x86-64
+/// и профили RISC-V чередуются с отдельными наборами инструкций, затем
+/// идут уровни POWER и поколения IBM Z. Вариант `base` собирается для
+/// любой архитектуры.
+const LIBRARY_VARIANTS: [(&str, Option<&str>); 18] = [
+    ("x86-64-v4", Some("x86_64")),
+    ("x86-64-v3", Some("x86_64")),
+    ("avx2", Some("x86_64")),
+    ("avx", Some("x86_64")),
+    ("x86-64-v2", Some("x86_64")),
+    ("sse4_2", Some("x86_64")),
+    ("neon", Some("aarch64")),
+    ("rva23", Some("riscv64")),
+    ("rvv", Some("riscv64")),
+    ("rva22", Some("riscv64")),
+    ("pwr10", Some("powerpc64")),
+    ("pwr9", Some("powerpc64")),
+    ("pwr8", Some("powerpc64")),
+    ("z15", Some("s390x")),
+    ("z14", Some("s390x")),
+    ("z13", Some("s390x")),
+    ("x86-64", Some("x86_64")),
+    ("base", None),
+];
+
+/// Варианты библиотек для архитектуры `arch` в порядке приоритета
+pub fn library_variants(arch: &str) -> impl Iterator<Item = &'static str> + '_ {
+    LIBRARY_VARIANTS
+        .into_iter()
+        .filter(move |&(_, variant_arch)| {
+            variant_arch.is_none_or(|variant_arch| variant_arch == arch)
+        })
+        .map(|(variant, _)| variant)
+}
 
-        #[cfg(not(target_os = "windows"))]
-        lib_path.push(format!("lib{}.{}", lib_name, lib_ext));
+/// Проверяет, является ли вариант нижней границей выбора для архитектуры
+/// `arch`: базовым уровнем архитектуры (`x86-64`, `neon`, `rva22`,
+/// `pwr8`, `z13`) или вариантом `base`
+pub fn is_baseline_variant(variant: &str, arch: &str) -> bool {
+    variant == "base"
+        || library_variants(arch)
+            .filter(|&candidate| candidate != "base")
+            .last()
+            == Some(variant)
+}
 
-        // Проверка наличия библиотеки
-        if lib_path.exists() {
-            info!("Найдена оптимальная библиотека: {}", lib_path.display());
-            return Ok(lib_path);
+/// Наборы инструкций, необходимые для варианта библиотеки
+///
+/// Вариант может быть уровнем микроархитектуры (`x86-64-v3`, `pwr9`,
+/// `z15`), профилем RISC-V (`rva23`) или отдельным набором инструкций
+/// (`avx2`, `neon`). Базовому уровню x86-64 и варианту `base` не нужно
+/// ничего: они доступны всегда.
+pub fn variant_requirements(variant: &str) -> FeatureSet {
+    if let Some(level) = X86_64Level::from_target_cpu(variant) {
+        if level == X86_64Level::V1 {
+            FeatureSet::new()
         } else {
-            warn!(
-                "Библиотека {} не найдена, проверка следующего варианта",
-                lib_path.display()
-            );
+            level.required_features()
         }
+    } else if let Some(profile) = RiscvProfile::from_name(variant) {
+        profile.required_features()
+    } else if let Some(level) = PowerLevel::from_target_cpu(variant) {
+        level.required_features()
+    } else if let Some(level) = S390xLevel::from_target_cpu(variant) {
+        level.required_features()
+    } else {
+        CpuFeature::from_name(variant).into_iter().collect()
     }
+}
+
+/// Варианты библиотек, которые поддерживает процессор, в порядке
+/// приоритета
+pub fn supported_variants<'a>(
+    cpu_features: &'a FeatureSet,
+    arch: &'a str,
+) -> impl Iterator<Item = &'static str> + 'a {
+    library_variants(arch)
+        .filter(|variant| cpu_features.is_superset(&variant_requirements(variant)))
+}
 
-    // Если не найдена ни одна подходящая библиотека
-    Err(AppError::LibraryError(format!(
-        "Не удалось найти подходящую библиотеку в директории: {}",
-        lib_dir.display()
-    )))
+/// Варианты библиотек для процессора в порядке проверки
+///
+/// `features` - наборы инструкций для выбора ([`selection_features`]).
+/// Учитывает таблицу особенностей процессоров: варианты, которые
+/// процессор выполняет медленно, проверяются позже или не проверяются
+/// вовсе.
+pub fn candidate_variants(
+    cpu_info: &CpuInfo,
+    features: &FeatureSet,
+    quirks: &QuirkTable,
+) -> Vec<&'static str> {
+    let variants = supported_variants(features, cpu_info.effective_arch()).collect();
+    quirks.apply(cpu_info, variants)
+}
+
+/// Ищет наилучшую совместимую библиотеку по манифестам в директориях
+/// поиска и настройками из окружения процесса (см. [`LibraryResolver`])
+pub fn find_library(cpu_info: &CpuInfo, quirks: &QuirkTable) -> Result<ManifestEntry, AppError> {
+    LibraryResolver::from_env(quirks.clone())?.resolve(cpu_info)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
-    use std::io::Write;
-    use tempfile::tempdir;
 
     #[test]
-    fn test_find_library() {
-        // Создание временной директории для тестов
-        let temp_dir = tempdir().unwrap();
-        let lib_dir = temp_dir.path().join("lib");
-        fs::create_dir(&lib_dir).unwrap();
-
-        // Создание фиктивных библиотек
-        let lib_ext = if cfg!(target_os = "windows") {
-            "dll"
-        } else if cfg!(target_os = "macos") {
-            "dylib"
-        } else {
-            "so"
+    fn test_conservative_features() {
+        let features: FeatureSet = X86_64Level::V4.required_features();
+        let conservative = conservative_features(&features, "x86_64");
+        assert_eq!(X86_64Level::classify(&conservative), Some(X86_64Level::V2));
+
+        let features: FeatureSet = "neon,sve,dotprod".parse().unwrap();
+        assert_eq!(
+            conservative_features(&features, "aarch64").to_string(),
+            "neon"
+        );
+
+        let features = PowerLevel::Power10.required_features();
+        let conservative = conservative_features(&features, "powerpc64");
+        assert_eq!(
+            PowerLevel::classify(&conservative),
+            Some(PowerLevel::Power8)
+        );
+
+        let features = S390xLevel::Z15.required_features();
+        assert_eq!(
+            conservative_features(&features, "s390x").to_string(),
+            "vector"
+        );
+
+        assert!(is_listed(&Hypervisor::QemuTcg, "qemu-tcg"));
+        assert!(is_listed(&Hypervisor::Vmware, "kvm, VMware"));
+        assert!(!is_listed(&Hypervisor::Kvm, "qemu-tcg,vmware"));
+        assert!(!is_listed(&Hypervisor::Kvm, ""));
+
+        assert!(is_baseline_variant("x86-64", "x86_64"));
+        assert!(is_baseline_variant("rva22", "riscv64"));
+        assert!(is_baseline_variant("base", "aarch64"));
+        assert!(!is_baseline_variant("x86-64-v2", "x86_64"));
+        assert!(!is_baseline_variant("neon", "x86_64"));
+
+        // Признак консервативного выбора выставляется только для
+        // гипервизоров из списка
+        let mut cpu_info = CpuInfo {
+            arch: "x86_64".to_string(),
+            features: X86_64Level::V4.required_features(),
+            ..CpuInfo::default()
         };
-        let arch = std::env::consts::ARCH;
-
-        let lib_prefix = if cfg!(target_os = "windows") {
-            ""
-        } else {
-            "lib"
-        };
-
-        // Создание библиотек с разными наборами инструкций
-        let libs = [
-            format!("{}{}_{}_system.{}", lib_prefix, arch, "avx2", lib_ext),
-            format!("{}{}_{}_system.{}", lib_prefix, arch, "avx", lib_ext),
-            format!("{}{}_{}_system.{}", lib_prefix, arch, "sse4_2", lib_ext),
-            format!("{}{}_{}_system.{}", lib_prefix, arch, "base", lib_ext),
-        ];
-
-        for lib in &libs {
-            let lib_path = lib_dir.join(lib);
-            let mut file = fs::File::create(lib_path).unwrap();
-            file.write_all(b"dummy library").unwrap();
-        }
-
-        // Тест с поддержкой AVX2
-        let cpu_features = vec!["avx2".to_string(), "avx".to_string(), "sse4.2".to_string()];
-        #[allow(unused_variables)]
-        let result = find_library(&cpu_features, "system");
-
-        // Тест не будет работать, так как find_library использует current_exe()
-        // Это просто пример структуры теста
+        assert_eq!(
+            selection_features(&cpu_info, "qemu-tcg"),
+            (X86_64Level::V4.required_features(), false)
+        );
+        cpu_info.virtualization.hypervisor = Some(Hypervisor::QemuTcg);
+        let (features, conservative) = selection_features(&cpu_info, "qemu-tcg");
+        assert!(conservative);
+        assert_eq!(X86_64Level::classify(&features), Some(X86_64Level::V2));
+        assert!(!selection_features(&cpu_info, "kvm").1);
     }
 }
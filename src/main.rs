@@ -3,17 +3,36 @@
 //! Определяет архитектуру процессора и загружает соответствующую
 //! динамическую библиотеку, оптимизированную для этой архитектуры.
 
-mod cpu_detection;
+mod allocator;
+mod config;
+mod descriptor;
 mod error;
+mod fleet;
 mod lib_loader;
+mod manifest;
+mod quirks;
+mod resolver;
+mod search_path;
 
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process;
 
 use error::AppError;
 use log::{debug, error, info};
 
+/// Аргумент командной строки, включающий экспорт профиля процессора
+const EXPORT_CPU_PROFILE_ARG: &str = "--export-cpu-profile";
+
+/// Аргумент командной строки, включающий запись дампа CPUID
+const DUMP_CPUID_ARG: &str = "--dump-cpuid";
+
+/// Аргумент командной строки со списком отключаемых наборов инструкций
+const DISABLE_CPU_FEATURES_ARG: &str = "--disable-cpu-features";
+
+/// Аргумент командной строки с каталогом профилей процессоров парка машин
+const FLEET_REPORT_ARG: &str = "--fleet-report";
+
 fn main() {
     // Инициализация логгера
     env_logger::init();
@@ -36,15 +55,89 @@ fn main() {
 }
 
 /// Основная логика программы
-fn run(args: Vec<String>) -> Result<i32, AppError> {
+fn run(mut args: Vec<String>) -> Result<i32, AppError> {
+    // Аргументы приложения, которые не передаются библиотеке
+    let disabled_features = take_option(&mut args, DISABLE_CPU_FEATURES_ARG)?;
+    let fleet_dir = take_option(&mut args, FLEET_REPORT_ARG)?;
+
+    // Режим отчета по парку машин: общий вариант библиотеки вычисляется
+    // по профилям, текущий процессор не определяется
+    if let Some(fleet_dir) = fleet_dir {
+        let machines = fleet::load_fleet(Path::new(&fleet_dir))?;
+        let quirks = quirks::QuirkTable::load()?;
+        print!(
+            "{}",
+            fleet::FleetReport::new(
+                &machines,
+                &quirks,
+                &lib_loader::conservative_hypervisors_from_env()
+            )?
+        );
+        return Ok(0);
+    }
+
+    // Режим записи дампа CPUID: дамп выводится в stdout
+    if args.iter().skip(1).any(|arg| arg == DUMP_CPUID_ARG) {
+        dump_cpuid()?;
+        return Ok(0);
+    }
+
     // Вывод информации о системе
     print_system_info()?;
 
     // Определение архитектуры процессора
-    let cpu_info = cpu_detection::detect_cpu()?;
+    let mut cpu_info = cpu_detection::detect_cpu()?;
+    if let Some(disabled_features) = disabled_features {
+        cpu_detection::disable_features(
+            &mut cpu_info,
+            &disabled_features.parse()?,
+            DISABLE_CPU_FEATURES_ARG,
+        );
+    }
+
+    // Режим экспорта: профиль процессора выводится в stdout, библиотека
+    // не загружается
+    if args.iter().skip(1).any(|arg| arg == EXPORT_CPU_PROFILE_ARG) {
+        cpu_detection::export_profile(&cpu_info, std::io::stdout().lock())?;
+        println!();
+        return Ok(0);
+    }
+
     eprintln!("Тип процессора: {}", cpu_info.vendor);
     eprintln!("Модель процессора: {}", cpu_info.model);
-    eprintln!("Поддерживаемые наборы инструкций: {:?}", cpu_info.features);
+    if let Some(signature) = &cpu_info.signature {
+        eprintln!("Сигнатура процессора: {}", signature);
+    }
+    if let Some(microarchitecture) = &cpu_info.microarchitecture {
+        eprintln!("Микроархитектура: {}", microarchitecture);
+    }
+    eprintln!("Частота процессора: {}", cpu_info.frequency);
+    eprintln!("Поддерживаемые наборы инструкций: {}", cpu_info.features);
+    for suppressed in &cpu_info.suppressed_features {
+        eprintln!(
+            "Отключенный набор инструкций: {} ({})",
+            suppressed.feature, suppressed.reason
+        );
+    }
+    if let Some(level) = cpu_info.x86_64_level() {
+        eprintln!("Уровень микроархитектуры: {}", level.target_cpu());
+    }
+    if let Some(profile) = cpu_info.riscv_profile() {
+        eprintln!("Профиль RISC-V: {}", profile.name());
+    }
+    if let Some(level) = cpu_info.power_level() {
+        eprintln!("Уровень POWER: {}", level.target_cpu());
+    }
+    if let Some(level) = cpu_info.s390x_level() {
+        eprintln!("Поколение IBM Z: {}", level.target_cpu());
+    }
+    for cache in &cpu_info.caches {
+        eprintln!("Кэш {}", cache);
+    }
+    eprintln!("Топология: {}", cpu_info.topology);
+    eprintln!("Доступно процессоров: {}", cpu_info.parallelism);
+    eprintln!("Виртуализация: {}", cpu_info.virtualization);
+    eprintln!("Память: {}", cpu_info.memory);
 
     // Проверка принудительного выбора библиотеки через переменные окружения
     let lib_path = if let Ok(forced_lib) = env::var("FORCE_LIB_PATH") {
@@ -61,22 +154,68 @@ fn run(args: Vec<String>) -> Result<i32, AppError> {
         );
         path
     } else {
-        // Определение аллокатора (по умолчанию system)
-        let allocator = env::var("ALLOCATOR").unwrap_or_else(|_| "system".to_string());
-        eprintln!("Используемый аллокатор: {}", allocator);
-
-        // Поиск подходящей библиотеки
-        lib_loader::find_library(&cpu_info.features, &allocator)?
+        // Поиск подходящей библиотеки; аллокаторы перебираются в порядке
+        // предпочтения из ALLOCATOR (по умолчанию system)
+        let quirks = quirks::QuirkTable::load()?;
+        let library = lib_loader::find_library(&cpu_info, &quirks)?;
+        eprintln!("Используемый аллокатор: {}", library.manifest.allocator);
+        library.library_path
     };
 
     eprintln!("Выбранная динамическая библиотека: {}", lib_path.display());
 
     // Загрузка библиотеки и вызов функции run
-    let result = lib_loader::load_and_run(&lib_path, args)?;
+    let result = lib_loader::load_and_run(&lib_path, &cpu_info, args)?;
 
     Ok(result)
 }
 
+/// Записывает дамп CPUID текущего процессора в stdout
+fn dump_cpuid() -> Result<(), AppError> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        cpu_detection::CpuIdDump::record_native().export(std::io::stdout().lock())?;
+        println!();
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        Err(AppError::CpuDetectionError(format!(
+            "Инструкция CPUID недоступна на архитектуре {}",
+            env::consts::ARCH
+        )))
+    }
+}
+
+/// Извлекает из аргументов опцию `name` в виде `name value` или
+/// `name=value` и возвращает ее значение
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, AppError> {
+    let prefix = format!("{}=", name);
+
+    for i in 1..args.len() {
+        if let Some(value) = args[i].strip_prefix(&prefix) {
+            let value = value.to_string();
+            args.remove(i);
+            return Ok(Some(value));
+        }
+
+        if args[i] == name {
+            if i + 1 >= args.len() {
+                return Err(AppError::ArgumentError(format!(
+                    "Не указано значение аргумента {}",
+                    name
+                )));
+            }
+            let value = args.remove(i + 1);
+            args.remove(i);
+            return Ok(Some(value));
+        }
+    }
+
+    Ok(None)
+}
+
 /// Выводит информацию о системе
 fn print_system_info() -> Result<(), AppError> {
     // Вывод информации об ОС
@@ -126,4 +265,24 @@ mod tests {
         // Проверка, что функция не вызывает панику
         print_system_info().unwrap();
     }
+
+    #[test]
+    fn test_take_option() {
+        let mut args: Vec<String> = ["app", "--disable-cpu-features", "avx2", "benchmark"]
+            .map(String::from)
+            .to_vec();
+        let value = take_option(&mut args, DISABLE_CPU_FEATURES_ARG).unwrap();
+        assert_eq!(value.as_deref(), Some("avx2"));
+        assert_eq!(args, ["app", "benchmark"]);
+
+        let mut args: Vec<String> = ["app", "--disable-cpu-features=avx,sve"]
+            .map(String::from)
+            .to_vec();
+        let value = take_option(&mut args, DISABLE_CPU_FEATURES_ARG).unwrap();
+        assert_eq!(value.as_deref(), Some("avx,sve"));
+        assert_eq!(args, ["app"]);
+
+        let mut args: Vec<String> = ["app", "--disable-cpu-features"].map(String::from).to_vec();
+        assert!(take_option(&mut args, DISABLE_CPU_FEATURES_ARG).is_err());
+    }
 }
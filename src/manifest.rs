@@ -0,0 +1,302 @@
+//! Манифесты вариантов библиотеки.
+//!
+//! Рядом с каждой библиотекой в директории lib лежит JSON-манифест,
+//! описывающий, для какой платформы и каких наборов инструкций она
+//! собрана:
+//!
+//! ```json
+//! {
+//!   "library": "libx86_64_x86-64-v3_system.so",
+//!   "variant": "x86-64-v3",
+//!   "arch": "x86_64",
+//!   "os": "linux",
+//!   "features": ["avx", "avx2", "bmi1", "bmi2", "fma"],
+//!   "allocator": "system",
+//!   "abi_version": 1,
+//!   "version": "0.1.0"
+//! }
+//! ```
+//!
+//! Загрузчик выбирает библиотеку по манифестам, а не по имени файла,
+//! поэтому вариант может требовать любое сочетание наборов инструкций.
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use cpu_detection::{FeatureSet, ABI_VERSION};
+
+/// Манифест варианта библиотеки
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibraryManifest {
+    /// Имя файла библиотеки в директории манифеста
+    pub library: String,
+    /// Вариант библиотеки (`x86-64-v3`, `avx2`, `neon`, ...)
+    pub variant: String,
+    /// Целевая архитектура (`x86_64`, `aarch64`, ...)
+    pub arch: String,
+    /// Целевая операционная система (`linux`, `windows`, `macos`)
+    pub os: String,
+    /// Наборы инструкций, с которыми собрана библиотека
+    #[serde(default)]
+    pub features: FeatureSet,
+    /// Аллокатор памяти (`system`, `jemalloc`, `mimalloc`)
+    pub allocator: String,
+    /// Версия интерфейса с приложением
+    pub abi_version: u32,
+    /// Версия библиотеки
+    #[serde(default)]
+    pub version: String,
+}
+
+/// Манифест, найденный в директории библиотек
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// Путь к файлу манифеста
+    pub manifest_path: PathBuf,
+    /// Путь к библиотеке
+    pub library_path: PathBuf,
+    /// Содержимое манифеста
+    pub manifest: LibraryManifest,
+}
+
+impl LibraryManifest {
+    /// Разбирает манифест из JSON
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Загружает манифест из файла
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_json(&content).map_err(|e| {
+            AppError::LibraryError(format!("Некорректный манифест {}: {}", path.display(), e))
+        })
+    }
+
+    /// Проверяет, может ли библиотека работать в текущем процессе
+    ///
+    /// `features` - наборы инструкций, по которым выбирается библиотека,
+    /// `allocators` - допустимые аллокаторы. Возвращает причину
+    /// несовместимости.
+    pub fn check_compatible(
+        &self,
+        arch: &str,
+        os: &str,
+        features: &FeatureSet,
+        allocators: &[String],
+    ) -> Result<(), String> {
+        if self.arch != arch {
+            return Err(format!("архитектура {}, требуется {}", self.arch, arch));
+        }
+        if self.os != os {
+            return Err(format!("ОС {}, требуется {}", self.os, os));
+        }
+        if self.abi_version != ABI_VERSION {
+            return Err(format!(
+                "версия ABI {}, поддерживается {}",
+                self.abi_version, ABI_VERSION
+            ));
+        }
+        if !allocators.contains(&self.allocator) {
+            return Err(format!(
+                "аллокатор {}, допустимы {}",
+                self.allocator,
+                allocators.join(",")
+            ));
+        }
+        if !features.is_superset(&self.features) {
+            return Err(format!(
+                "процессор не поддерживает {}",
+                self.features.difference(features)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Ключ сравнения версий библиотеки (`0.10.1` новее `0.9.3`)
+    pub fn version_key(&self) -> Vec<u64> {
+        self.version
+            .split(['.', '-', '+'])
+            .map_while(|part| part.parse().ok())
+            .collect()
+    }
+}
+
+/// Загружает манифесты (`*.json`) из директории библиотек
+///
+/// Некорректные манифесты, манифесты без файла библиотеки и манифесты,
+/// в которых вместо имени файла указан путь (`../lib.so`), пропускаются
+/// с предупреждением в журнале. Результат упорядочен по именам файлов.
+pub fn scan_manifests(lib_dir: &Path) -> Result<Vec<ManifestEntry>, AppError> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(lib_dir).map_err(|e| {
+        AppError::PathError(format!(
+            "Не удалось прочитать директорию библиотек {}: {}",
+            lib_dir.display(),
+            e
+        ))
+    })? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut entries = Vec::new();
+    for manifest_path in paths {
+        let manifest = match LibraryManifest::load(&manifest_path) {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                warn!("Манифест пропущен: {}", err);
+                continue;
+            },
+        };
+
+        if Path::new(&manifest.library).file_name() != Some(manifest.library.as_ref()) {
+            warn!(
+                "Манифест {} пропущен: имя библиотеки {} не является именем файла",
+                manifest_path.display(),
+                manifest.library
+            );
+            continue;
+        }
+
+        let library_path = lib_dir.join(&manifest.library);
+        if !library_path.is_file() {
+            warn!(
+                "Манифест {} пропущен: библиотека {} не найдена",
+                manifest_path.display(),
+                library_path.display()
+            );
+            continue;
+        }
+
+        entries.push(ManifestEntry {
+            manifest_path,
+            library_path,
+            manifest,
+        });
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cpu_detection::X86_64Level;
+    use tempfile::tempdir;
+
+    fn manifest(variant: &str, features: FeatureSet) -> LibraryManifest {
+        LibraryManifest {
+            library: format!("libx86_64_{}_system.so", variant),
+            variant: variant.to_string(),
+            arch: "x86_64".to_string(),
+            os: "linux".to_string(),
+            features,
+            allocator: "system".to_string(),
+            abi_version: ABI_VERSION,
+            version: "0.1.0".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_check_compatible() {
+        let v3 = manifest("x86-64-v3", X86_64Level::V3.required_features());
+        let cpu = X86_64Level::V3.required_features();
+        let system = ["system".to_string()];
+
+        assert_eq!(
+            v3.check_compatible("x86_64", "linux", &cpu, &system),
+            Ok(())
+        );
+        assert!(v3
+            .check_compatible("aarch64", "linux", &cpu, &system)
+            .is_err());
+        assert!(v3
+            .check_compatible("x86_64", "windows", &cpu, &system)
+            .is_err());
+        assert!(v3
+            .check_compatible("x86_64", "linux", &cpu, &["jemalloc".to_string()])
+            .is_err());
+        assert_eq!(
+            v3.check_compatible(
+                "x86_64",
+                "linux",
+                &X86_64Level::V2.required_features(),
+                &system
+            ),
+            Err(
+                "процессор не поддерживает fma,movbe,xsave,avx,f16c,bmi1,avx2,bmi2,lzcnt"
+                    .to_string()
+            )
+        );
+
+        let old_abi = LibraryManifest {
+            abi_version: 0,
+            ..v3.clone()
+        };
+        assert!(old_abi
+            .check_compatible("x86_64", "linux", &cpu, &system)
+            .is_err());
+
+        let newer = LibraryManifest {
+            version: "0.10.1".to_string(),
+            ..v3.clone()
+        };
+        assert!(newer.version_key() > v3.version_key());
+    }
+
+    #[test]
+    fn test_scan_manifests() {
+        let root = tempdir().unwrap();
+        let dir = root.path().join("lib");
+        std::fs::create_dir(&dir).unwrap();
+        for variant in ["x86-64-v3", "avx2"] {
+            let manifest = manifest(variant, "avx2".parse().unwrap());
+            std::fs::write(dir.join(&manifest.library), b"dummy library").unwrap();
+            std::fs::write(
+                dir.join(format!("libx86_64_{}_system.json", variant)),
+                serde_json::to_string(&manifest).unwrap(),
+            )
+            .unwrap();
+        }
+
+        // Манифест без библиотеки и некорректный манифест пропускаются
+        let orphan = manifest("sse4_2", "sse4.2".parse().unwrap());
+        std::fs::write(
+            dir.join("orphan.json"),
+            serde_json::to_string(&orphan).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(dir.join("broken.json"), "{").unwrap();
+        std::fs::write(
+            dir.join("unknown.json"),
+            r#"{"library": "x.so", "variant": "x", "arch": "x86_64", "os": "linux",
+                "features": ["avx9000"], "allocator": "system", "abi_version": 1}"#,
+        )
+        .unwrap();
+
+        // Библиотека вне директории манифеста не используется
+        let mut evil = manifest("x86-64-v4", X86_64Level::V4.required_features());
+        evil.library = "../evil.so".to_string();
+        std::fs::write(root.path().join("evil.so"), b"dummy library").unwrap();
+        std::fs::write(dir.join("evil.json"), serde_json::to_string(&evil).unwrap()).unwrap();
+
+        let entries = scan_manifests(&dir).unwrap();
+        let variants: Vec<_> = entries
+            .iter()
+            .map(|entry| entry.manifest.variant.as_str())
+            .collect();
+        assert_eq!(variants, ["avx2", "x86-64-v3"]);
+        assert_eq!(
+            entries[0].library_path,
+            dir.join("libx86_64_avx2_system.so")
+        );
+
+        assert!(scan_manifests(&dir.join("missing")).is_err());
+    }
+}
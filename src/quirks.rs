@@ -0,0 +1,333 @@
+//! База известных особенностей процессоров, влияющих на выбор библиотеки.
+//!
+//! Некоторые процессоры поддерживают набор инструкций, но выполняют его
+//! медленно: AVX-512 снижает частоту на Skylake-SP, PDEP/PEXT реализованы
+//! микрокодом на Zen 1/2, а часть гипервизоров эмулирует AVX программно.
+//! Особенность понижает приоритет варианта библиотеки (`demote`) или
+//! запрещает его (`forbid`).
+//!
+//! Встроенная таблица дополняется файлом из переменной окружения
+//! `CPU_QUIRKS_FILE`: записи с тем же `id` заменяют встроенные, запись с
+//! `"enabled": false` отключает особенность.
+
+use log::warn;
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::error::AppError;
+use crate::lib_loader;
+use cpu_detection::CpuInfo;
+
+/// Встроенная таблица особенностей
+const DEFAULT_QUIRKS: &str = include_str!("default_quirks.json");
+
+/// Действие особенности над вариантами библиотеки
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuirkAction {
+    /// Вариант проверяется после всех остальных, кроме `base`
+    Demote,
+    /// Вариант не используется
+    Forbid,
+}
+
+/// Особенность процессора
+///
+/// Особенность применяется, если совпадают все указанные условия:
+/// производитель, семейство, одна из моделей и гипервизор. Семейство и
+/// модель задаются десятичными числами (Skylake-SP: семейство 6, модель 85).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Quirk {
+    /// Идентификатор особенности
+    pub id: String,
+    /// Описание для журнала
+    #[serde(default)]
+    pub description: String,
+    /// Производитель (`GenuineIntel`, `AuthenticAMD`)
+    #[serde(default)]
+    pub vendor: Option<String>,
+    /// Семейство процессора x86
+    #[serde(default)]
+    pub family: Option<u32>,
+    /// Модели процессора x86 (пустой список - любая модель)
+    #[serde(default)]
+    pub models: Vec<u32>,
+    /// Гипервизор (`kvm`, `qemu-tcg`, ...)
+    #[serde(default)]
+    pub hypervisor: Option<String>,
+    /// Действие над вариантами библиотеки
+    pub action: QuirkAction,
+    /// Варианты библиотеки (`x86-64-v4`, `avx2`, ...)
+    #[serde(default)]
+    pub variants: Vec<String>,
+    /// Включена ли особенность
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Quirk {
+    /// Проверяет, относится ли особенность к процессору
+    pub fn matches(&self, cpu_info: &CpuInfo) -> bool {
+        let signature = cpu_info.signature.as_ref();
+
+        self.enabled
+            && self
+                .vendor
+                .as_ref()
+                .is_none_or(|vendor| vendor.eq_ignore_ascii_case(&cpu_info.vendor))
+            && self
+                .family
+                .is_none_or(|family| signature.is_some_and(|s| s.family == family))
+            && (self.models.is_empty() || signature.is_some_and(|s| self.models.contains(&s.model)))
+            && self.hypervisor.as_ref().is_none_or(|hypervisor| {
+                cpu_info
+                    .virtualization
+                    .hypervisor
+                    .as_ref()
+                    .is_some_and(|detected| hypervisor.eq_ignore_ascii_case(detected.name()))
+            })
+    }
+
+    /// Проверяет, относится ли особенность к варианту библиотеки
+    fn affects(&self, variant: &str) -> bool {
+        self.variants.iter().any(|name| name == variant)
+    }
+}
+
+/// Таблица особенностей процессоров
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuirkTable {
+    quirks: Vec<Quirk>,
+}
+
+impl QuirkTable {
+    /// Встроенная таблица особенностей
+    pub fn builtin() -> Self {
+        Self::from_json(DEFAULT_QUIRKS).expect("встроенная таблица особенностей некорректна")
+    }
+
+    /// Разбирает таблицу особенностей из JSON (массив записей)
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        Ok(QuirkTable {
+            quirks: serde_json::from_str(json)?,
+        })
+    }
+
+    /// Встроенная таблица, дополненная файлом из `CPU_QUIRKS_FILE`
+    pub fn load() -> Result<Self, AppError> {
+        let mut table = Self::builtin();
+        if let Ok(path) = std::env::var("CPU_QUIRKS_FILE") {
+            table.merge(Self::load_file(Path::new(&path))?);
+        }
+        Ok(table)
+    }
+
+    /// Загружает таблицу особенностей из JSON-файла
+    pub fn load_file(path: &Path) -> Result<Self, AppError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            AppError::PathError(format!(
+                "Не удалось прочитать таблицу особенностей {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        Self::from_json(&content).map_err(|e| {
+            AppError::ConfigError(format!(
+                "Некорректная таблица особенностей {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Добавляет записи `other`; записи с совпадающим `id` заменяются
+    pub fn merge(&mut self, other: QuirkTable) {
+        for quirk in other.quirks {
+            match self.quirks.iter_mut().find(|known| known.id == quirk.id) {
+                Some(known) => *known = quirk,
+                None => self.quirks.push(quirk),
+            }
+        }
+    }
+
+    /// Особенность, запрещающая вариант библиотеки на процессоре
+    pub fn forbidding(&self, cpu_info: &CpuInfo, variant: &str) -> Option<&Quirk> {
+        self.find(cpu_info, QuirkAction::Forbid, variant)
+    }
+
+    /// Особенность, понижающая приоритет варианта библиотеки на процессоре
+    pub fn demoting(&self, cpu_info: &CpuInfo, variant: &str) -> Option<&Quirk> {
+        self.find(cpu_info, QuirkAction::Demote, variant)
+    }
+
+    fn find(&self, cpu_info: &CpuInfo, action: QuirkAction, variant: &str) -> Option<&Quirk> {
+        self.quirks.iter().find(|quirk| {
+            quirk.action == action && quirk.affects(variant) && quirk.matches(cpu_info)
+        })
+    }
+
+    /// Применяет особенности процессора к вариантам библиотеки
+    ///
+    /// Запрещенные варианты удаляются, пониженные переносятся сразу за
+    /// следующий непониженный вариант с сохранением взаимного порядка, но
+    /// не ниже базового уровня архитектуры и `base` (см.
+    /// [`lib_loader::is_baseline_variant`]): пониженный вариант все же
+    /// лучше базового. Каждое изменение записывается в журнал.
+    pub fn apply(&self, cpu_info: &CpuInfo, variants: Vec<&'static str>) -> Vec<&'static str> {
+        let arch = cpu_info.effective_arch();
+        let mut ordered = Vec::new();
+        let mut demoted = Vec::new();
+
+        for variant in variants {
+            if let Some(quirk) = self.forbidding(cpu_info, variant) {
+                warn!(
+                    "Вариант библиотеки {} запрещен особенностью {}: {}",
+                    variant, quirk.id, quirk.description
+                );
+                continue;
+            }
+
+            if let Some(quirk) = self.demoting(cpu_info, variant) {
+                warn!(
+                    "Приоритет варианта библиотеки {} понижен особенностью {}: {}",
+                    variant, quirk.id, quirk.description
+                );
+                demoted.push(variant);
+            } else if lib_loader::is_baseline_variant(variant, arch) {
+                ordered.append(&mut demoted);
+                ordered.push(variant);
+            } else {
+                ordered.push(variant);
+                ordered.append(&mut demoted);
+            }
+        }
+
+        ordered.append(&mut demoted);
+        ordered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cpu_detection::{CpuSignature, Hypervisor, X86_64Level};
+
+    fn cpu(vendor: &str, family: u32, model: u32, level: X86_64Level) -> CpuInfo {
+        CpuInfo {
+            arch: "x86_64".to_string(),
+            vendor: vendor.to_string(),
+            signature: Some(CpuSignature {
+                family,
+                model,
+                stepping: 4,
+            }),
+            features: level.required_features(),
+            ..CpuInfo::default()
+        }
+    }
+
+    fn variants(cpu_info: &CpuInfo) -> Vec<&'static str> {
+        lib_loader::supported_variants(&cpu_info.features, "x86_64").collect()
+    }
+
+    #[test]
+    fn test_builtin_quirks() {
+        let table = QuirkTable::builtin();
+
+        // Skylake-SP: x86-64-v4 проверяется сразу после x86-64-v3
+        let skylake_sp = cpu("GenuineIntel", 6, 0x55, X86_64Level::V4);
+        assert_eq!(
+            table.apply(&skylake_sp, variants(&skylake_sp)),
+            [
+                "x86-64-v3",
+                "x86-64-v4",
+                "avx2",
+                "avx",
+                "x86-64-v2",
+                "sse4_2",
+                "x86-64",
+                "base"
+            ]
+        );
+
+        // Zen 2: вместо x86-64-v3 (BMI2) выбирается avx2
+        let zen2 = cpu("AuthenticAMD", 0x17, 0x31, X86_64Level::V3);
+        assert_eq!(
+            table.apply(&zen2, variants(&zen2))[..3],
+            ["avx2", "x86-64-v3", "avx"]
+        );
+
+        // Пониженный вариант не опускается ниже базового уровня
+        assert_eq!(
+            table.apply(&skylake_sp, vec!["x86-64-v4", "x86-64", "base"]),
+            ["x86-64-v4", "x86-64", "base"]
+        );
+
+        // Ice Lake-SP и Zen 3 не затронуты
+        for cpu_info in [
+            cpu("GenuineIntel", 6, 0x6a, X86_64Level::V4),
+            cpu("AuthenticAMD", 0x19, 0x01, X86_64Level::V3),
+        ] {
+            assert_eq!(
+                table.apply(&cpu_info, variants(&cpu_info)),
+                variants(&cpu_info)
+            );
+        }
+
+        // Под QEMU TCG варианты с AVX запрещены
+        let mut tcg = cpu("GenuineIntel", 6, 0x6a, X86_64Level::V4);
+        tcg.virtualization.hypervisor = Some(Hypervisor::QemuTcg);
+        assert_eq!(
+            table.apply(&tcg, variants(&tcg)),
+            ["x86-64-v2", "sse4_2", "x86-64", "base"]
+        );
+        assert_eq!(
+            table.forbidding(&tcg, "avx2").unwrap().id,
+            "qemu-tcg-avx-emulation"
+        );
+    }
+
+    #[test]
+    fn test_quirk_override() {
+        let mut table = QuirkTable::builtin();
+        let user = QuirkTable::from_json(
+            r#"[
+                {"id": "skylake-sp-avx512-frequency", "action": "demote", "enabled": false},
+                {"id": "no-avx512-on-kvm", "description": "Политика площадки",
+                 "hypervisor": "KVM", "action": "forbid", "variants": ["x86-64-v4"]}
+            ]"#,
+        )
+        .unwrap();
+        table.merge(user);
+
+        let mut skylake_sp = cpu("GenuineIntel", 6, 0x55, X86_64Level::V4);
+        assert_eq!(
+            table.apply(&skylake_sp, variants(&skylake_sp))[0],
+            "x86-64-v4"
+        );
+
+        skylake_sp.virtualization.hypervisor = Some(Hypervisor::Kvm);
+        assert_eq!(
+            table.apply(&skylake_sp, variants(&skylake_sp))[0],
+            "x86-64-v3"
+        );
+
+        assert!(QuirkTable::from_json(r#"[{"id": "x", "action": "ignore"}]"#).is_err());
+
+        // Ошибка разбора файла указывает путь к нему
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("quirks.json");
+        std::fs::write(&path, r#"[{"id": "x"}]"#).unwrap();
+        match QuirkTable::load_file(&path) {
+            Err(AppError::ConfigError(message)) => {
+                assert!(message.contains(&path.display().to_string()))
+            },
+            other => panic!("ожидается ошибка конфигурации: {:?}", other.err()),
+        }
+    }
+}
@@ -0,0 +1,832 @@
+//! Выбор библиотеки в директориях поиска.
+//!
+//! [`LibraryResolver`] объединяет все, от чего зависит выбор: директории
+//! поиска, правила именования библиотек платформы, предпочтения
+//! аллокатора, гипервизоры для консервативного выбора и порядок
+//! приоритета вариантов. Архитектура берется из профиля процессора.
+//! Окружение процесса читает только [`LibraryResolver::from_env`],
+//! поэтому выбор можно проверить на временных директориях.
+
+use log::{debug, info, warn};
+use std::cmp::Reverse;
+use std::path::Path;
+
+use crate::allocator::{AllocatorPreference, SelectionPolicy};
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::lib_loader::{
+    self, candidate_variants, library_variants, selection_features, variant_requirements,
+};
+use crate::manifest::{self, LibraryManifest, ManifestEntry};
+use crate::quirks::QuirkTable;
+use crate::search_path::{SearchDir, SearchPathSources};
+use cpu_detection::{CpuInfo, FeatureSet};
+
+/// Правила именования библиотек платформы
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LibraryNaming {
+    /// Префикс имени файла (`lib` на Unix)
+    pub prefix: &'static str,
+    /// Расширение файла библиотеки без точки
+    pub extension: &'static str,
+}
+
+impl LibraryNaming {
+    /// Правила именования для операционной системы
+    pub fn for_os(os: &str) -> Self {
+        match os {
+            "windows" => Self {
+                prefix: "",
+                extension: "dll",
+            },
+            "macos" | "ios" => Self {
+                prefix: "lib",
+                extension: "dylib",
+            },
+            _ => Self {
+                prefix: "lib",
+                extension: "so",
+            },
+        }
+    }
+
+    /// Имя файла библиотеки в формате `{arch}_{variant}_{allocator}`,
+    /// который использует `scripts/build.sh`
+    pub fn file_name(&self, arch: &str, variant: &str, allocator: &str) -> String {
+        format!(
+            "{}{}_{}_{}.{}",
+            self.prefix, arch, variant, allocator, self.extension
+        )
+    }
+
+    /// Проверяет, что файл - динамическая библиотека платформы
+    pub fn is_library(&self, path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext == self.extension)
+    }
+}
+
+/// Выбор библиотеки по манифестам
+///
+/// Директории поиска просматриваются по порядку (см.
+/// [`crate::search_path`]): выбирается наилучшая библиотека из первой
+/// директории, в которой есть хотя бы одна совместимая. Несуществующие
+/// директории пропускаются.
+#[derive(Debug, Clone)]
+pub struct LibraryResolver {
+    /// Директории поиска в порядке приоритета
+    search_dirs: Vec<SearchDir>,
+    /// Операционная система, для которой выбирается библиотека
+    os: String,
+    /// Правила именования библиотек
+    naming: LibraryNaming,
+    /// Аллокаторы в порядке предпочтения и политика выбора
+    preference: AllocatorPreference,
+    /// Особенности процессоров, меняющие порядок вариантов
+    quirks: QuirkTable,
+    /// Гипервизоры через запятую, под которыми выбор консервативен (см.
+    /// [`selection_features`])
+    conservative_hypervisors: String,
+}
+
+impl LibraryResolver {
+    /// Создает выбор библиотеки для текущей операционной системы со
+    /// списком гипервизоров по умолчанию
+    pub fn new(
+        search_dirs: Vec<SearchDir>,
+        preference: AllocatorPreference,
+        quirks: QuirkTable,
+    ) -> Self {
+        Self {
+            search_dirs,
+            os: std::env::consts::OS.to_string(),
+            naming: LibraryNaming::for_os(std::env::consts::OS),
+            preference,
+            quirks,
+            conservative_hypervisors: lib_loader::DEFAULT_CONSERVATIVE_HYPERVISORS.to_string(),
+        }
+    }
+
+    /// Создает выбор библиотеки по окружению процесса и файлу
+    /// конфигурации: путь поиска (`CPU_OPT_LIB_PATH`, директории XDG и
+    /// системные директории, `lib` рядом с исполняемым файлом),
+    /// предпочтения аллокатора (`ALLOCATOR`, `CPU_OPT_SELECTION_POLICY`) и
+    /// гипервизоры для консервативного выбора
+    /// (`CPU_CONSERVATIVE_HYPERVISORS`)
+    pub fn from_env(quirks: QuirkTable) -> Result<Self, AppError> {
+        let config = AppConfig::load()?;
+        let preference = AllocatorPreference::from_env(config.as_ref().map(|(_, config)| config))?;
+        let search_dirs = SearchPathSources::from_env(config)?.search_path();
+        Ok(Self {
+            conservative_hypervisors: lib_loader::conservative_hypervisors_from_env(),
+            ..Self::new(search_dirs, preference, quirks)
+        })
+    }
+
+    /// Ищет наилучшую совместимую библиотеку в директориях поиска
+    ///
+    /// Сообщение об ошибке перечисляет все просмотренные директории с
+    /// источником и результатом.
+    pub fn resolve(&self, cpu_info: &CpuInfo) -> Result<ManifestEntry, AppError> {
+        info!(
+            "Выбор библиотеки: аллокаторы {}: {}",
+            self.preference,
+            match self.preference.policy {
+                SelectionPolicy::TierFirst => "уровень варианта важнее аллокатора",
+                SelectionPolicy::AllocatorFirst => "аллокатор важнее уровня варианта",
+            }
+        );
+
+        let (features, conservative) = selection_features(cpu_info, &self.conservative_hypervisors);
+        if conservative {
+            if let Some(hypervisor) = &cpu_info.virtualization.hypervisor {
+                warn!(
+                    "Консервативный выбор библиотеки под гипервизором {}: {}",
+                    hypervisor, features
+                );
+            }
+        }
+        let candidates = candidate_variants(cpu_info, &features, &self.quirks);
+
+        let mut searched = Vec::new();
+        let mut found_dir = false;
+
+        for dir in &self.search_dirs {
+            debug!("Поиск библиотек в {} ({})", dir.path.display(), dir.source);
+            if !dir.path.is_dir() {
+                searched.push(format!(
+                    "{} ({}): не существует",
+                    dir.path.display(),
+                    dir.source
+                ));
+                continue;
+            }
+            found_dir = true;
+
+            let entries = manifest::scan_manifests(&dir.path)?;
+            if let Some(entry) = self.select(&entries, cpu_info, &features, &candidates) {
+                info!(
+                    "Найдена оптимальная библиотека: {} (вариант {}, аллокатор {}, версия {}, манифест {})",
+                    entry.library_path.display(),
+                    entry.manifest.variant,
+                    entry.manifest.allocator,
+                    entry.manifest.version,
+                    entry.manifest_path.display()
+                );
+                if entry.manifest.allocator != self.preference.preferred() {
+                    warn!(
+                        "Используется аллокатор {} вместо {} (политика {})",
+                        entry.manifest.allocator,
+                        self.preference.preferred(),
+                        self.preference.policy
+                    );
+                }
+                return Ok(entry.clone());
+            }
+            searched.push(format!(
+                "{} ({}): манифестов {}, подходящих нет",
+                dir.path.display(),
+                dir.source,
+                entries.len()
+            ));
+        }
+
+        let searched = searched
+            .iter()
+            .map(|line| format!("\n  {}", line))
+            .collect::<String>();
+
+        if !found_dir {
+            return Err(AppError::PathError(format!(
+                "Директория библиотек не найдена, просмотрены:{}",
+                searched
+            )));
+        }
+
+        Err(AppError::LibraryError(format!(
+            "Не удалось найти подходящую библиотеку (ожидаются библиотеки вида {} \
+             с манифестами, аллокаторы {}), просмотрены:{}",
+            self.naming
+                .file_name(cpu_info.effective_arch(), "<вариант>", "<аллокатор>"),
+            self.preference.allocators().join(","),
+            searched
+        )))
+    }
+
+    /// Выбирает наилучшую библиотеку среди манифестов
+    ///
+    /// `features` и `candidates` - наборы инструкций для выбора
+    /// ([`selection_features`]) и варианты-кандидаты
+    /// ([`candidate_variants`]); они вычисляются один раз для всех
+    /// директорий поиска. Оценка совместимой библиотеки, по убыванию значимости:
+    ///
+    /// 1. уровень варианта в порядке [`candidate_variants`], то есть с
+    ///    учетом особенностей процессоров; для варианта с неизвестным
+    ///    именем уровнем считается наивысший известный вариант, все наборы
+    ///    инструкций которого перечислены в манифесте;
+    /// 2. позиция аллокатора в списке предпочтений; при политике
+    ///    [`SelectionPolicy::AllocatorFirst`] аллокатор и уровень меняются
+    ///    местами;
+    /// 3. количество наборов инструкций в манифесте: из двух библиотек
+    ///    одного уровня предпочитается более специализированная;
+    /// 4. версия библиотеки.
+    pub fn select<'a>(
+        &self,
+        entries: &'a [ManifestEntry],
+        cpu_info: &CpuInfo,
+        features: &FeatureSet,
+        candidates: &[&'static str],
+    ) -> Option<&'a ManifestEntry> {
+        let arch = cpu_info.effective_arch();
+
+        entries
+            .iter()
+            .filter_map(|entry| {
+                let manifest = &entry.manifest;
+                let rank = self
+                    .check_library_name(&entry.library_path)
+                    .and_then(|()| {
+                        manifest.check_compatible(
+                            arch,
+                            &self.os,
+                            features,
+                            self.preference.allocators(),
+                        )
+                    })
+                    .and_then(|()| self.variant_rank(manifest, cpu_info, arch, candidates));
+
+                match rank {
+                    Ok(rank) => {
+                        let allocator_rank = self.preference.rank(&manifest.allocator)?;
+                        debug!(
+                            "Библиотека {} подходит: вариант {}, уровень {}, аллокатор {} ({}-й в списке)",
+                            entry.library_path.display(),
+                            manifest.variant,
+                            candidates[rank],
+                            manifest.allocator,
+                            allocator_rank + 1
+                        );
+                        Some((entry, rank, allocator_rank))
+                    },
+                    Err(reason) => {
+                        debug!(
+                            "Библиотека {} отброшена: {}",
+                            entry.library_path.display(),
+                            reason
+                        );
+                        None
+                    },
+                }
+            })
+            .min_by_key(|&(entry, rank, allocator_rank)| {
+                let order = match self.preference.policy {
+                    SelectionPolicy::TierFirst => (rank, allocator_rank),
+                    SelectionPolicy::AllocatorFirst => (allocator_rank, rank),
+                };
+                (
+                    order,
+                    Reverse(entry.manifest.features.len()),
+                    Reverse(entry.manifest.version_key()),
+                )
+            })
+            .map(|(entry, _, _)| entry)
+    }
+
+    /// Проверяет, что манифест указывает на библиотеку платформы
+    fn check_library_name(&self, library_path: &Path) -> Result<(), String> {
+        if self.naming.is_library(library_path) {
+            Ok(())
+        } else {
+            Err(format!(
+                "файл не является библиотекой {} (ожидается .{})",
+                self.os, self.naming.extension
+            ))
+        }
+    }
+
+    /// Позиция уровня варианта библиотеки в списке `candidates`
+    fn variant_rank(
+        &self,
+        manifest: &LibraryManifest,
+        cpu_info: &CpuInfo,
+        arch: &str,
+        candidates: &[&'static str],
+    ) -> Result<usize, String> {
+        if let Some(quirk) = self.quirks.forbidding(cpu_info, &manifest.variant) {
+            return Err(format!("вариант запрещен особенностью {}", quirk.id));
+        }
+
+        let tier = library_variants(arch)
+            .find(|&variant| variant == manifest.variant)
+            .or_else(|| {
+                library_variants(arch).find(|variant| {
+                    manifest
+                        .features
+                        .is_superset(&variant_requirements(variant))
+                })
+            })
+            .ok_or_else(|| format!("вариант {} не относится к {}", manifest.variant, arch))?;
+
+        candidates
+            .iter()
+            .position(|&candidate| candidate == tier)
+            .ok_or_else(|| format!("уровень {} недоступен на процессоре", tier))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search_path::SearchSource;
+    use cpu_detection::{CpuFeature, CpuIdDump, CpuSignature, Hypervisor, X86_64Level};
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    /// Варианты библиотек x86_64
+    const VARIANTS: [&str; 8] = [
+        "x86-64-v4",
+        "x86-64-v3",
+        "avx2",
+        "avx",
+        "x86-64-v2",
+        "sse4_2",
+        "x86-64",
+        "base",
+    ];
+
+    /// Аллокаторы
+    const ALLOCATORS: [&str; 3] = ["system", "jemalloc", "mimalloc"];
+
+    /// Выбор библиотеки x86_64 для Linux независимо от платформы тестов
+    ///
+    /// `allocators` - список аллокаторов через запятую, политика
+    /// `tier-first`.
+    fn resolver(search_dirs: Vec<PathBuf>, allocators: &str) -> LibraryResolver {
+        LibraryResolver {
+            os: "linux".to_string(),
+            naming: LibraryNaming::for_os("linux"),
+            ..LibraryResolver::new(
+                search_dirs
+                    .into_iter()
+                    .map(|path| SearchDir::new(path, SearchSource::Env))
+                    .collect(),
+                preference(allocators, SelectionPolicy::TierFirst),
+                QuirkTable::builtin(),
+            )
+        }
+    }
+
+    fn preference(allocators: &str, policy: SelectionPolicy) -> AllocatorPreference {
+        AllocatorPreference::new(allocators.split(','), policy).unwrap()
+    }
+
+    fn manifest(
+        library: &str,
+        variant: &str,
+        features: FeatureSet,
+        allocator: &str,
+    ) -> LibraryManifest {
+        LibraryManifest {
+            library: library.to_string(),
+            variant: variant.to_string(),
+            arch: "x86_64".to_string(),
+            os: "linux".to_string(),
+            features,
+            allocator: allocator.to_string(),
+            abi_version: cpu_detection::ABI_VERSION,
+            version: "0.1.0".to_string(),
+        }
+    }
+
+    /// Создает фиктивную библиотеку и ее манифест, как `scripts/build.sh`
+    fn install(dir: &Path, variant: &str, allocator: &str) -> PathBuf {
+        let library = LibraryNaming::for_os("linux").file_name("x86_64", variant, allocator);
+        let manifest = manifest(&library, variant, variant_requirements(variant), allocator);
+
+        fs::create_dir_all(dir).unwrap();
+        let library_path = dir.join(&library);
+        fs::write(&library_path, b"dummy library").unwrap();
+        fs::write(
+            library_path.with_extension("json"),
+            serde_json::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+        library_path
+    }
+
+    /// Выбор библиотеки среди манифестов, как в [`LibraryResolver::resolve`]
+    fn select_for<'a>(
+        resolver: &LibraryResolver,
+        entries: &'a [ManifestEntry],
+        cpu_info: &CpuInfo,
+    ) -> Option<&'a ManifestEntry> {
+        let (features, _) = selection_features(cpu_info, &resolver.conservative_hypervisors);
+        let candidates = candidate_variants(cpu_info, &features, &resolver.quirks);
+        resolver.select(entries, cpu_info, &features, &candidates)
+    }
+
+    fn cpu(features: FeatureSet) -> CpuInfo {
+        CpuInfo {
+            arch: "x86_64".to_string(),
+            features,
+            ..CpuInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_library_naming() {
+        let linux = LibraryNaming::for_os("linux");
+        assert_eq!(
+            linux.file_name("x86_64", "avx2", "system"),
+            "libx86_64_avx2_system.so"
+        );
+        assert!(linux.is_library(Path::new("lib/libx86_64_avx2_system.so")));
+        assert!(!linux.is_library(Path::new("lib/x86_64_avx2_system.dll")));
+
+        assert_eq!(
+            LibraryNaming::for_os("windows").file_name("x86_64", "avx2", "mimalloc"),
+            "x86_64_avx2_mimalloc.dll"
+        );
+        assert_eq!(
+            LibraryNaming::for_os("macos").file_name("aarch64", "neon", "jemalloc"),
+            "libaarch64_neon_jemalloc.dylib"
+        );
+    }
+
+    #[test]
+    fn test_resolve_all_combinations() {
+        let dir = tempdir().unwrap();
+        for variant in VARIANTS {
+            for allocator in ALLOCATORS {
+                install(dir.path(), variant, allocator);
+            }
+        }
+
+        let v2 = X86_64Level::V2.required_features();
+        let mut avx = v2;
+        avx.extend([CpuFeature::Avx, CpuFeature::Xsave]);
+        let mut avx2 = avx;
+        avx2.insert(CpuFeature::Avx2);
+
+        // Наборы инструкций процессора и ожидаемый вариант библиотеки
+        let cases = [
+            (X86_64Level::V4.required_features(), "x86-64-v4"),
+            (X86_64Level::V3.required_features(), "x86-64-v3"),
+            (avx2, "avx2"),
+            (avx, "avx"),
+            (v2, "x86-64-v2"),
+            ("sse4.2".parse().unwrap(), "sse4_2"),
+            (FeatureSet::new(), "x86-64"),
+        ];
+
+        for allocator in ALLOCATORS {
+            let resolver = resolver(vec![dir.path().to_path_buf()], allocator);
+            for (features, variant) in &cases {
+                assert_eq!(
+                    resolver.resolve(&cpu(*features)).unwrap().library_path,
+                    dir.path().join(
+                        LibraryNaming::for_os("linux").file_name("x86_64", variant, allocator)
+                    ),
+                    "процессор {}, аллокатор {}",
+                    features,
+                    allocator
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_resolve_cpuid_dumps() {
+        let dir = tempdir().unwrap();
+        for variant in VARIANTS {
+            install(dir.path(), variant, "system");
+        }
+        let resolver = resolver(vec![dir.path().to_path_buf()], "system");
+
+        // Дампы CPUID реальных систем и ожидаемый вариант библиотеки
+        let cases = [
+            (
+                include_str!(
+                    "../cpu_detection/tests/fixtures/cpuid/x86_64_sapphire_rapids_kvm.json"
+                ),
+                "x86-64-v4",
+            ),
+            // Ivy Bridge: AVX без AVX2
+            (
+                include_str!("../cpu_detection/tests/fixtures/cpuid/x86_64_ivybridge_ep.json"),
+                "avx",
+            ),
+            // Zen 2: x86-64-v3 понижен из-за медленных PDEP/PEXT
+            (
+                include_str!("../cpu_detection/tests/fixtures/cpuid/x86_64_zen2_rome.json"),
+                "avx2",
+            ),
+            // Penryn: только базовый уровень
+            (
+                include_str!("../cpu_detection/tests/fixtures/cpuid/x86_64_penryn.json"),
+                "x86-64",
+            ),
+            (
+                include_str!("../cpu_detection/tests/fixtures/cpuid/x86_64_westmere_ep.json"),
+                "x86-64-v2",
+            ),
+            (
+                include_str!("../cpu_detection/tests/fixtures/cpuid/x86_64_haswell.json"),
+                "x86-64-v3",
+            ),
+            // Skylake-SP: x86-64-v4 понижен из-за снижения частоты AVX-512
+            (
+                include_str!("../cpu_detection/tests/fixtures/cpuid/x86_64_skylake_sp.json"),
+                "x86-64-v3",
+            ),
+            // Alder Lake: AVX-512 отключен на гибридных процессорах
+            (
+                include_str!("../cpu_detection/tests/fixtures/cpuid/x86_64_alderlake.json"),
+                "x86-64-v3",
+            ),
+            // Zen 4: особенности Zen 1/2 не применяются
+            (
+                include_str!("../cpu_detection/tests/fixtures/cpuid/x86_64_zen4_genoa.json"),
+                "x86-64-v4",
+            ),
+        ];
+
+        for (dump, variant) in cases {
+            let cpu_info =
+                cpu_detection::cpu_info_from_cpuid_dump(&CpuIdDump::from_json(dump).unwrap())
+                    .unwrap();
+            assert_eq!(
+                resolver.resolve(&cpu_info).unwrap().manifest.variant,
+                variant,
+                "{}",
+                cpu_info.model
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_demoted_variant() {
+        let mut skylake_sp = cpu(X86_64Level::V4.required_features());
+        skylake_sp.vendor = "GenuineIntel".to_string();
+        skylake_sp.signature = Some(CpuSignature {
+            family: 6,
+            model: 0x55,
+            stepping: 4,
+        });
+
+        // Пониженный x86-64-v4 уступает x86-64-v3, но не базовому уровню
+        let dir = tempdir().unwrap();
+        let v4 = install(dir.path(), "x86-64-v4", "system");
+        install(dir.path(), "x86-64", "system");
+        install(dir.path(), "base", "system");
+        let resolver = resolver(vec![dir.path().to_path_buf()], "system");
+        assert_eq!(resolver.resolve(&skylake_sp).unwrap().library_path, v4);
+
+        let v3 = install(dir.path(), "x86-64-v3", "system");
+        assert_eq!(resolver.resolve(&skylake_sp).unwrap().library_path, v3);
+    }
+
+    #[test]
+    fn test_resolve_search_dirs() {
+        let root = tempdir().unwrap();
+        let missing = root.path().join("missing");
+        let jemalloc_only = root.path().join("jemalloc");
+        let system = root.path().join("system");
+
+        let v4_jemalloc = install(&jemalloc_only, "x86-64-v4", "jemalloc");
+        install(&system, "base", "system");
+        let v3 = install(&system, "x86-64-v3", "system");
+        let v3_cpu = cpu(X86_64Level::V3.required_features());
+        let v4_cpu = cpu(X86_64Level::V4.required_features());
+
+        // Несуществующие директории и директории без совместимых
+        // библиотек пропускаются
+        let dirs = vec![missing.clone(), jemalloc_only.clone(), system.clone()];
+        assert_eq!(
+            resolver(dirs.clone(), "system")
+                .resolve(&v3_cpu)
+                .unwrap()
+                .library_path,
+            v3
+        );
+        assert_eq!(
+            resolver(dirs.clone(), "jemalloc")
+                .resolve(&v4_cpu)
+                .unwrap()
+                .library_path,
+            v4_jemalloc
+        );
+
+        // Библиотека из первой директории важнее лучшей из следующих
+        let first = root.path().join("first");
+        let first_base = install(&first, "base", "system");
+        assert_eq!(
+            resolver(vec![first, system.clone()], "system")
+                .resolve(&v3_cpu)
+                .unwrap()
+                .library_path,
+            first_base
+        );
+
+        // В сообщении об ошибке перечислены все просмотренные директории
+        let err = resolver(vec![missing.clone(), jemalloc_only.clone()], "mimalloc")
+            .resolve(&v3_cpu)
+            .unwrap_err();
+        assert!(matches!(err, AppError::LibraryError(_)));
+        let message = err.to_string();
+        assert!(message.contains(&format!(
+            "{} (CPU_OPT_LIB_PATH): не существует",
+            missing.display()
+        )));
+        assert!(message.contains(&format!(
+            "{} (CPU_OPT_LIB_PATH): манифестов 1, подходящих нет",
+            jemalloc_only.display()
+        )));
+        assert!(message.contains("libx86_64_<вариант>_<аллокатор>.so"));
+        assert!(message.contains("аллокаторы mimalloc,system"));
+
+        let err = resolver(vec![missing], "system")
+            .resolve(&v3_cpu)
+            .unwrap_err();
+        assert!(matches!(err, AppError::PathError(_)));
+
+        // Манифест должен указывать на библиотеку платформы
+        let dll = manifest(
+            "x86_64_x86-64-v4_system.dll",
+            "x86-64-v4",
+            X86_64Level::V4.required_features(),
+            "system",
+        );
+        fs::write(system.join(&dll.library), b"dummy library").unwrap();
+        fs::write(
+            system.join("x86_64_x86-64-v4_system.json"),
+            serde_json::to_string(&dll).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            resolver(vec![system], "system")
+                .resolve(&v4_cpu)
+                .unwrap()
+                .library_path,
+            v3
+        );
+    }
+
+    #[test]
+    fn test_allocator_fallback() {
+        let root = tempdir().unwrap();
+        let mixed = root.path().join("mixed");
+        let v3_system = install(&mixed, "x86-64-v3", "system");
+        let v2_jemalloc = install(&mixed, "x86-64-v2", "jemalloc");
+        install(&mixed, "base", "jemalloc");
+        let v3_cpu = cpu(X86_64Level::V3.required_features());
+
+        let resolved = |allocators: &str, policy: SelectionPolicy| {
+            LibraryResolver {
+                preference: preference(allocators, policy),
+                ..resolver(vec![mixed.clone()], allocators)
+            }
+            .resolve(&v3_cpu)
+            .unwrap()
+            .library_path
+        };
+
+        // Лучший уровень собран только с system
+        assert_eq!(resolved("jemalloc", SelectionPolicy::TierFirst), v3_system);
+        assert_eq!(
+            resolved("jemalloc", SelectionPolicy::AllocatorFirst),
+            v2_jemalloc
+        );
+        assert_eq!(
+            resolved("mimalloc,jemalloc", SelectionPolicy::AllocatorFirst),
+            v2_jemalloc
+        );
+        assert_eq!(
+            resolved("system,jemalloc", SelectionPolicy::AllocatorFirst),
+            v3_system
+        );
+
+        // Есть только сборки system: ALLOCATOR=jemalloc не мешает выбору
+        let system_only = root.path().join("system");
+        let v3 = install(&system_only, "x86-64-v3", "system");
+        assert_eq!(
+            resolver(vec![system_only], "jemalloc")
+                .resolve(&v3_cpu)
+                .unwrap()
+                .library_path,
+            v3
+        );
+    }
+
+    #[test]
+    fn test_select_library() {
+        let entry = |variant: &str, features: FeatureSet, allocator: &str| {
+            let library = format!("{}_{}.so", variant, allocator);
+            ManifestEntry {
+                manifest_path: PathBuf::from(format!("{}_{}.json", variant, allocator)),
+                library_path: PathBuf::from(&library),
+                manifest: manifest(&library, variant, features, allocator),
+            }
+        };
+        let selected = |entries: &[ManifestEntry], cpu_info: &CpuInfo, allocator: &str| {
+            select_for(&resolver(Vec::new(), allocator), entries, cpu_info)
+                .map(|entry| entry.manifest.variant.clone())
+        };
+
+        // Вариант, которому нужны одновременно AVX-512 и VNNI
+        let mut v4_vnni = X86_64Level::V4.required_features();
+        v4_vnni.insert(CpuFeature::Avx512vnni);
+
+        let entries = [
+            entry("base", FeatureSet::new(), "system"),
+            entry("avx2", "avx2".parse().unwrap(), "system"),
+            entry("x86-64-v2", X86_64Level::V2.required_features(), "system"),
+            entry("x86-64-v3", X86_64Level::V3.required_features(), "system"),
+            entry("x86-64-v3", X86_64Level::V3.required_features(), "jemalloc"),
+            entry("x86-64-v4", X86_64Level::V4.required_features(), "system"),
+            entry("avx512-vnni", v4_vnni, "system"),
+        ];
+
+        // Более специализированный вариант того же уровня предпочтительнее
+        let mut icelake = v4_vnni;
+        icelake.insert(CpuFeature::Aes);
+        assert_eq!(
+            selected(&entries, &cpu(icelake), "system").as_deref(),
+            Some("avx512-vnni")
+        );
+        assert_eq!(
+            selected(
+                &entries,
+                &cpu(X86_64Level::V4.required_features()),
+                "system"
+            )
+            .as_deref(),
+            Some("x86-64-v4")
+        );
+
+        // avx2 приоритетнее x86-64-v2, хотя требует меньше наборов
+        let mut haswell_no_bmi = X86_64Level::V2.required_features();
+        haswell_no_bmi.extend([CpuFeature::Avx, CpuFeature::Avx2, CpuFeature::Xsave]);
+        assert_eq!(
+            selected(&entries, &cpu(haswell_no_bmi), "system").as_deref(),
+            Some("avx2")
+        );
+
+        // Лучший уровень собран только с менее предпочтительным
+        // аллокатором: выбор зависит от политики
+        let v4 = cpu(X86_64Level::V4.required_features());
+        assert_eq!(
+            selected(&entries, &v4, "jemalloc").as_deref(),
+            Some("x86-64-v4")
+        );
+        let allocator_first = LibraryResolver {
+            preference: preference("jemalloc", SelectionPolicy::AllocatorFirst),
+            ..resolver(Vec::new(), "jemalloc")
+        };
+        assert_eq!(
+            select_for(&allocator_first, &entries, &v4)
+                .map(|entry| entry.manifest.variant.as_str()),
+            Some("x86-64-v3")
+        );
+
+        // Без сборок jemalloc используется system
+        assert_eq!(
+            selected(&entries, &cpu(FeatureSet::new()), "jemalloc").as_deref(),
+            Some("base")
+        );
+
+        // Под QEMU TCG варианты с AVX запрещены особенностью процессора
+        let mut tcg = cpu(icelake);
+        tcg.virtualization.hypervisor = Some(Hypervisor::QemuTcg);
+        assert_eq!(
+            selected(&entries, &tcg, "system").as_deref(),
+            Some("x86-64-v2")
+        );
+
+        // Под KVM выбор консервативен, только если KVM есть в списке
+        let mut kvm = cpu(icelake);
+        kvm.virtualization.hypervisor = Some(Hypervisor::Kvm);
+        assert_eq!(
+            selected(&entries, &kvm, "system").as_deref(),
+            Some("avx512-vnni")
+        );
+        let conservative_kvm = LibraryResolver {
+            conservative_hypervisors: "qemu-tcg,kvm".to_string(),
+            ..resolver(Vec::new(), "system")
+        };
+        assert_eq!(
+            select_for(&conservative_kvm, &entries, &kvm)
+                .map(|entry| entry.manifest.variant.as_str()),
+            Some("x86-64-v2")
+        );
+
+        // Архитектура берется из профиля: библиотеки x86_64 не подходят
+        // процессору aarch64
+        let aarch64 = CpuInfo {
+            arch: "aarch64".to_string(),
+            features: "neon".parse().unwrap(),
+            ..CpuInfo::default()
+        };
+        assert_eq!(selected(&entries, &aarch64, "system"), None);
+    }
+}
@@ -0,0 +1,218 @@
+//! Путь поиска библиотек.
+//!
+//! Директории просматриваются в порядке:
+//!
+//! 1. `CPU_OPT_LIB_PATH` (через `:`, на Windows через `;`);
+//! 2. `lib_path` из файла конфигурации (см. [`crate::config`]);
+//! 3. `$XDG_DATA_HOME/cpu_optimized_app/lib` (по умолчанию
+//!    `~/.local/share/cpu_optimized_app/lib`);
+//! 4. `/usr/lib/cpu_optimized_app` (только Unix);
+//! 5. `lib` рядом с исполняемым файлом; символические ссылки на
+//!    исполняемый файл разрешаются, поэтому используется директория
+//!    самого файла, а не ссылки.
+
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::config::{self, AppConfig, APP_DIR_NAME};
+use crate::error::AppError;
+
+/// Переменная окружения с директориями поиска библиотек
+pub const LIB_PATH_ENV: &str = "CPU_OPT_LIB_PATH";
+
+/// Системная директория библиотек
+pub const SYSTEM_LIB_DIR: &str = "/usr/lib/cpu_optimized_app";
+
+/// Откуда взята директория поиска
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchSource {
+    /// Переменная окружения `CPU_OPT_LIB_PATH`
+    Env,
+    /// Файл конфигурации
+    Config(PathBuf),
+    /// Пользовательская директория данных XDG
+    XdgData,
+    /// Системная директория
+    System,
+    /// Директория исполняемого файла
+    Executable,
+}
+
+impl fmt::Display for SearchSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchSource::Env => write!(f, "{}", LIB_PATH_ENV),
+            SearchSource::Config(path) => write!(f, "конфигурация {}", path.display()),
+            SearchSource::XdgData => write!(f, "XDG_DATA_HOME"),
+            SearchSource::System => write!(f, "системная директория"),
+            SearchSource::Executable => write!(f, "рядом с исполняемым файлом"),
+        }
+    }
+}
+
+/// Директория поиска библиотек
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchDir {
+    /// Путь к директории
+    pub path: PathBuf,
+    /// Источник директории
+    pub source: SearchSource,
+}
+
+impl SearchDir {
+    /// Создает директорию поиска
+    pub fn new(path: impl Into<PathBuf>, source: SearchSource) -> Self {
+        Self {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+/// Источники директорий поиска
+#[derive(Debug, Clone, Default)]
+pub struct SearchPathSources {
+    /// Значение `CPU_OPT_LIB_PATH`
+    pub lib_path_env: Option<OsString>,
+    /// Файл конфигурации и его содержимое
+    pub config: Option<(PathBuf, AppConfig)>,
+    /// Пользовательская директория данных XDG
+    pub data_home: Option<PathBuf>,
+    /// Системная директория библиотек
+    pub system_dir: Option<PathBuf>,
+    /// Директория исполняемого файла
+    pub exe_dir: Option<PathBuf>,
+}
+
+impl SearchPathSources {
+    /// Источники из окружения процесса и загруженного файла конфигурации
+    pub fn from_env(config: Option<(PathBuf, AppConfig)>) -> Result<Self, AppError> {
+        Ok(Self {
+            lib_path_env: std::env::var_os(LIB_PATH_ENV),
+            config,
+            data_home: config::xdg_dir("XDG_DATA_HOME", ".local/share"),
+            system_dir: cfg!(unix).then(|| PathBuf::from(SYSTEM_LIB_DIR)),
+            exe_dir: Some(exe_dir()?),
+        })
+    }
+
+    /// Директории поиска в порядке приоритета
+    ///
+    /// Пустые элементы `CPU_OPT_LIB_PATH` пропускаются, повторяющиеся
+    /// директории остаются только на первом месте.
+    pub fn search_path(&self) -> Vec<SearchDir> {
+        let mut dirs = Vec::new();
+
+        if let Some(lib_path) = &self.lib_path_env {
+            dirs.extend(
+                std::env::split_paths(lib_path)
+                    .filter(|path| !path.as_os_str().is_empty())
+                    .map(|path| SearchDir::new(path, SearchSource::Env)),
+            );
+        }
+        if let Some((config_path, config)) = &self.config {
+            dirs.extend(
+                config
+                    .lib_path
+                    .iter()
+                    .map(|path| SearchDir::new(path, SearchSource::Config(config_path.clone()))),
+            );
+        }
+        if let Some(data_home) = &self.data_home {
+            dirs.push(SearchDir::new(
+                data_home.join(APP_DIR_NAME).join("lib"),
+                SearchSource::XdgData,
+            ));
+        }
+        if let Some(system_dir) = &self.system_dir {
+            dirs.push(SearchDir::new(system_dir, SearchSource::System));
+        }
+        if let Some(exe_dir) = &self.exe_dir {
+            dirs.push(SearchDir::new(
+                exe_dir.join("lib"),
+                SearchSource::Executable,
+            ));
+        }
+
+        let mut unique: Vec<SearchDir> = Vec::with_capacity(dirs.len());
+        for dir in dirs {
+            if !unique.iter().any(|known| known.path == dir.path) {
+                unique.push(dir);
+            }
+        }
+        unique
+    }
+}
+
+/// Директория исполняемого файла с разрешенными символическими ссылками
+pub fn exe_dir() -> Result<PathBuf, AppError> {
+    resolve_exe_dir(&std::env::current_exe()?)
+}
+
+/// Директория файла `exe` после разрешения символических ссылок
+fn resolve_exe_dir(exe: &Path) -> Result<PathBuf, AppError> {
+    let exe = std::fs::canonicalize(exe).unwrap_or_else(|_| exe.to_path_buf());
+    exe.parent().map(Path::to_path_buf).ok_or_else(|| {
+        AppError::PathError("Не удалось определить директорию исполняемого файла".to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_path_order() {
+        let lib_path = std::env::join_paths(["/opt/ourapp/lib", "", "/srv/lib"]).unwrap();
+        let sources = SearchPathSources {
+            lib_path_env: Some(lib_path),
+            config: Some((
+                PathBuf::from("/etc/cpu_optimized_app/config.json"),
+                AppConfig {
+                    lib_path: vec![PathBuf::from("/srv/lib"), PathBuf::from("/data/lib")],
+                    ..AppConfig::default()
+                },
+            )),
+            data_home: Some(PathBuf::from("/home/user/.local/share")),
+            system_dir: Some(PathBuf::from(SYSTEM_LIB_DIR)),
+            exe_dir: Some(PathBuf::from("/opt/ourapp/bin")),
+        };
+
+        let config = SearchSource::Config(PathBuf::from("/etc/cpu_optimized_app/config.json"));
+        assert_eq!(
+            sources.search_path(),
+            [
+                SearchDir::new("/opt/ourapp/lib", SearchSource::Env),
+                SearchDir::new("/srv/lib", SearchSource::Env),
+                SearchDir::new("/data/lib", config),
+                SearchDir::new(
+                    "/home/user/.local/share/cpu_optimized_app/lib",
+                    SearchSource::XdgData
+                ),
+                SearchDir::new(SYSTEM_LIB_DIR, SearchSource::System),
+                SearchDir::new("/opt/ourapp/bin/lib", SearchSource::Executable),
+            ]
+        );
+
+        assert!(SearchPathSources::default().search_path().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_executable() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin = dir.path().join("opt").join("bin");
+        let link_dir = dir.path().join("usr").join("local").join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::create_dir_all(&link_dir).unwrap();
+        std::fs::write(bin.join("app"), b"").unwrap();
+        std::os::unix::fs::symlink(bin.join("app"), link_dir.join("app")).unwrap();
+
+        // Библиотеки ищутся рядом с файлом, а не рядом со ссылкой
+        assert_eq!(
+            resolve_exe_dir(&link_dir.join("app")).unwrap(),
+            std::fs::canonicalize(&bin).unwrap()
+        );
+    }
+}